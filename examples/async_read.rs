@@ -0,0 +1,37 @@
+/****************************************************************************
+    Copyright (c) 2015 Osspial All Rights Reserved.
+
+    This file is part of hidapi-rs, based on hidapi_rust by Roland Ruckerbauer.
+****************************************************************************/
+
+//! Opens the first hid device it can find and reads reports from it in a tokio task.
+//!
+//! `AsyncHidDevice` does not depend on a runtime, any other executor works the same way.
+
+extern crate hidapi;
+
+use hidapi::{AsyncHidDevice, HidApi, HidResult};
+
+async fn read_reports(device: AsyncHidDevice) -> HidResult<()> {
+    let mut buf = [0u8; 256];
+    loop {
+        let len = device.read(&mut buf).await?;
+        println!("{:02x?}", &buf[..len]);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let api = HidApi::new().expect("Failed to create API instance");
+
+    let info = api.device_list().next().expect("No devices are available");
+    let device = info.open_device().expect("Failed to open device");
+    let device = AsyncHidDevice::new(device).expect("Failed to start reading");
+
+    let reader = tokio::spawn(read_reports(device));
+    match reader.await {
+        Ok(Err(e)) => eprintln!("Error: {}", e),
+        Ok(Ok(())) => {}
+        Err(e) => eprintln!("The reader task failed: {}", e),
+    }
+}
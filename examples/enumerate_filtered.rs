@@ -0,0 +1,34 @@
+//! Compares the time it takes to enumerate all devices and filter them by usage page afterwards,
+//! with enumerating only the devices of that usage page.
+//!
+//! Pass the usage page in hex as the first argument, it defaults to the vendor defined `ff00`.
+
+use std::time::Instant;
+
+use hidapi::{DeviceFilter, HidApi, HidError};
+
+fn main() -> Result<(), HidError> {
+    let usage_page = match std::env::args().nth(1) {
+        Some(arg) => u16::from_str_radix(arg.trim_start_matches("0x"), 16)
+            .expect("the usage page must be a hexadecimal number"),
+        None => 0xff00,
+    };
+    let filter = DeviceFilter::new().usage_page(usage_page);
+    let mut api = HidApi::new()?;
+
+    let start = Instant::now();
+    api.refresh_devices()?;
+    let full = api.device_list_filtered(filter.clone()).count();
+    println!("full enumeration: {full} devices in {:?}", start.elapsed());
+
+    api.reset_devices()?;
+    let start = Instant::now();
+    api.add_devices_filtered(filter)?;
+    let filtered = api.device_list().count();
+    println!(
+        "filtered enumeration: {filtered} devices in {:?}",
+        start.elapsed()
+    );
+
+    Ok(())
+}
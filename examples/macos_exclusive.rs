@@ -0,0 +1,38 @@
+//! Opens the first hid device in shared mode on macOS and prints its location ID and whether it
+//! was opened exclusively.
+
+extern crate hidapi;
+
+#[cfg(target_os = "macos")]
+fn main() {
+    use hidapi::{HidApi, HidError};
+
+    fn run() -> Result<(), HidError> {
+        let hidapi = HidApi::new()?;
+
+        // Devices are opened in exclusive mode by default on macOS
+        hidapi.set_open_exclusive(false);
+        println!("Open exclusive: {}", hidapi.get_open_exclusive());
+
+        let device_info = hidapi
+            .device_list()
+            .next()
+            .expect("No devices are available!");
+        println!("Opening device: {}", device_info);
+
+        let device = device_info.open_device()?;
+        println!("Location ID: {:#010x}", device.get_location_id()?);
+        println!("Opened exclusive: {}", device.is_open_exclusive()?);
+
+        Ok(())
+    }
+
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {
+    eprintln!("This example only works on macOS");
+}
@@ -0,0 +1,48 @@
+//! Reads input reports from the first device with the given vendor and product ID, and keeps
+//! reading after the device was unplugged and plugged in again.
+//!
+//! Usage: `cargo run --example reconnect -- <vid> <pid>`, with the IDs in hex.
+
+extern crate hidapi;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use hidapi::{HidApi, HidError, ReconnectingDevice};
+
+fn main() {
+    fn run() -> Result<(), HidError> {
+        let args: Vec<String> = std::env::args().collect();
+        if args.len() != 3 {
+            eprintln!("Usage: {} <vid> <pid>", args[0]);
+            std::process::exit(1);
+        }
+        let vid = u16::from_str_radix(&args[1], 16).expect("invalid vendor ID");
+        let pid = u16::from_str_radix(&args[2], 16).expect("invalid product ID");
+
+        let api = HidApi::new()?;
+        let info = api
+            .device_list()
+            .find(|d| d.vendor_id() == vid && d.product_id() == pid)
+            .expect("No matching device is available!")
+            .clone();
+        let device = ReconnectingDevice::new(info)?;
+
+        let mut buf = [0u8; 64];
+        loop {
+            match device.read_timeout(&mut buf, 1000) {
+                Ok(0) => {}
+                Ok(len) => println!("{:?}", &buf[..len]),
+                Err(HidError::Disconnected) => {
+                    println!("Waiting for the device ...");
+                    sleep(Duration::from_secs(1));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+    }
+}
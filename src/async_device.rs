@@ -0,0 +1,368 @@
+//! An async wrapper around [`HidDevice`] that does not depend on a specific runtime.
+//!
+//! With the `linux-native` backend the hidraw file descriptor is registered with the reactor of
+//! `async-io`, which runs on its own thread and works with any executor. The other backends don't
+//! expose a file descriptor or handle, so their blocking reads happen on a dedicated thread per
+//! device, which wakes the waiting tasks once a report arrived.
+
+use std::future::poll_fn;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use crate::{HidDevice, HidResult, MAX_REPORT_LEN};
+
+#[cfg(all(feature = "linux-native", target_os = "linux"))]
+use {
+    crate::HidError,
+    async_io::Async,
+    std::os::fd::{AsFd, BorrowedFd, RawFd},
+};
+
+/// How often the reader thread checks whether the wrapper has been dropped
+const SHUTDOWN_CHECK_MS: i32 = 100;
+
+#[derive(Default)]
+struct State {
+    requested: bool,
+    report: Option<HidResult<Vec<u8>>>,
+    wakers: Vec<Waker>,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// The file descriptor of a device, registered with the `async-io` reactor.
+#[cfg(all(feature = "linux-native", target_os = "linux"))]
+struct DeviceFd(RawFd);
+
+#[cfg(all(feature = "linux-native", target_os = "linux"))]
+impl AsFd for DeviceFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: The registration is dropped before the device that owns the descriptor
+        unsafe { BorrowedFd::borrow_raw(self.0) }
+    }
+}
+
+enum Reader {
+    #[cfg(all(feature = "linux-native", target_os = "linux"))]
+    Fd(Async<DeviceFd>),
+    Thread {
+        shared: Arc<Shared>,
+        thread: Option<JoinHandle<()>>,
+    },
+}
+
+/// A [`HidDevice`] that can be read from async code.
+///
+/// With the `linux-native` backend, reads wait for the device file to become readable. The other
+/// backends read input reports on a background thread. Either way this works with any executor.
+/// A report is only read from the device while a read future is waiting for it. If that future
+/// is dropped before it completes, the report is kept and returned by the next read.
+///
+/// Several reads may wait at the same time, each report completes one of them.
+///
+/// Everything besides reading can be done through [`AsyncHidDevice::get_ref`].
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub struct AsyncHidDevice {
+    // Dropped before the device, whose file descriptor may be registered
+    reader: Reader,
+    device: Arc<HidDevice>,
+}
+
+impl AsyncHidDevice {
+    /// Wrap a device, registering its file descriptor or starting its reader thread.
+    pub fn new(device: HidDevice) -> HidResult<Self> {
+        let device = Arc::new(device);
+
+        #[cfg(all(feature = "linux-native", target_os = "linux"))]
+        if let Some(fd) = device.as_raw_fd() {
+            // The descriptor is already non-blocking, reads wait with poll
+            let reader = Reader::Fd(Async::new_nonblocking(DeviceFd(fd))?);
+            return Ok(Self { reader, device });
+        }
+
+        let shared = Arc::<Shared>::default();
+        let thread = thread::Builder::new().name("hidapi-reader".into()).spawn({
+            let device = device.clone();
+            let shared = shared.clone();
+            move || reader(&device, &shared)
+        })?;
+
+        Ok(Self {
+            reader: Reader::Thread {
+                shared,
+                thread: Some(thread),
+            },
+            device,
+        })
+    }
+
+    /// Get the wrapped device, e.g. to write to it.
+    pub fn get_ref(&self) -> &HidDevice {
+        &self.device
+    }
+
+    /// Stop reading and return the wrapped device.
+    ///
+    /// A report that was already read for a cancelled read future is lost.
+    pub fn into_inner(mut self) -> HidDevice {
+        self.stop();
+        let device = self.device.clone();
+        drop(self);
+        match Arc::try_unwrap(device) {
+            Ok(device) => device,
+            Err(_) => unreachable!("the reader thread has been stopped"),
+        }
+    }
+
+    /// Read an input report, see [`HidDevice::read`].
+    ///
+    /// The report is truncated if `buf` is too small to hold it.
+    pub async fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
+        match &self.reader {
+            #[cfg(all(feature = "linux-native", target_os = "linux"))]
+            Reader::Fd(fd) => loop {
+                match self.device.read_timeout_strict(buf, 0) {
+                    Err(HidError::Timeout) => fd.readable().await?,
+                    res => return res,
+                }
+            },
+            Reader::Thread { shared, .. } => poll_fn(|cx| poll_shared(shared, cx, buf)).await,
+        }
+    }
+
+    /// Poll for an input report, for use in manual [`Future`](std::future::Future) implementations.
+    ///
+    /// With the `linux-native` backend only the task of the latest call is woken, use
+    /// [`AsyncHidDevice::read`] to wait for reports from several tasks at the same time.
+    pub fn poll_read(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<HidResult<usize>> {
+        match &self.reader {
+            #[cfg(all(feature = "linux-native", target_os = "linux"))]
+            Reader::Fd(fd) => loop {
+                match self.device.read_timeout_strict(buf, 0) {
+                    Err(HidError::Timeout) => match fd.poll_readable(cx) {
+                        Poll::Ready(Ok(())) => continue,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                        Poll::Pending => return Poll::Pending,
+                    },
+                    res => return Poll::Ready(res),
+                }
+            },
+            Reader::Thread { shared, .. } => poll_shared(shared, cx, buf),
+        }
+    }
+
+    #[cfg_attr(
+        not(all(feature = "linux-native", target_os = "linux")),
+        allow(irrefutable_let_patterns)
+    )]
+    fn stop(&mut self) {
+        if let Reader::Thread { shared, thread } = &mut self.reader {
+            shared.state().shutdown = true;
+            shared.cond.notify_all();
+            if let Some(thread) = thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Take the report the reader thread read, or ask it for one.
+fn poll_shared(shared: &Shared, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<HidResult<usize>> {
+    let mut state = shared.state();
+    if let Some(report) = state.report.take() {
+        return Poll::Ready(report.map(|report| {
+            let len = report.len().min(buf.len());
+            buf[..len].copy_from_slice(&report[..len]);
+            len
+        }));
+    }
+
+    // Every waiting read is woken, the first one to poll takes the report
+    if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+        state.wakers.push(cx.waker().clone());
+    }
+    if !state.requested {
+        state.requested = true;
+        shared.cond.notify_all();
+    }
+    Poll::Pending
+}
+
+impl Drop for AsyncHidDevice {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl std::fmt::Debug for AsyncHidDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncHidDevice").finish_non_exhaustive()
+    }
+}
+
+fn reader(device: &HidDevice, shared: &Shared) {
+    let mut buf = vec![0u8; MAX_REPORT_LEN];
+    loop {
+        {
+            let state = shared
+                .cond
+                .wait_while(shared.state(), |s| !s.requested && !s.shutdown)
+                .unwrap();
+            if state.shutdown {
+                return;
+            }
+        }
+
+        let report = loop {
+            match device.read_timeout(&mut buf, SHUTDOWN_CHECK_MS) {
+                Ok(0) if shared.state().shutdown => return,
+                Ok(0) => continue,
+                Ok(len) => break Ok(buf[..len].to_vec()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut state = shared.state();
+        state.report = Some(report);
+        state.requested = false;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_async_read() {
+        let mock = MockDevice::new(0, 0);
+        let device = AsyncHidDevice::new(HidDevice::from_mock(mock.clone())).unwrap();
+
+        mock.push_input(vec![0x01, 0x02]);
+        let mut buf = [0u8; 4];
+        assert_eq!(block_on(device.read(&mut buf)).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0x01, 0x02]);
+
+        let feeder = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(10));
+            mock.push_input(vec![0x03]);
+        });
+        assert_eq!(block_on(device.read(&mut buf)).unwrap(), 1);
+        assert_eq!(buf[0], 0x03);
+        feeder.join().unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        let mock = MockDevice::new(0, 0);
+        let device = AsyncHidDevice::new(HidDevice::from_mock(mock.clone())).unwrap();
+
+        thread::scope(|scope| {
+            let readers: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut buf = [0u8; 4];
+                        let len = block_on(device.read(&mut buf)).unwrap();
+                        buf[..len].to_vec()
+                    })
+                })
+                .collect();
+            thread::sleep(std::time::Duration::from_millis(10));
+            mock.push_input(vec![0x01]);
+            mock.push_input(vec![0x02]);
+
+            let mut reports: Vec<_> = readers.into_iter().map(|r| r.join().unwrap()).collect();
+            reports.sort();
+            assert_eq!(reports, vec![vec![0x01], vec![0x02]]);
+        });
+    }
+
+    #[cfg(all(feature = "linux-native", target_os = "linux"))]
+    #[test]
+    fn test_async_read_fd() {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let write_end = unsafe { OwnedFd::from_raw_fd(write_end) };
+        let device = crate::linux_native::HidDevice::from_hidraw_fd(unsafe {
+            OwnedFd::from_raw_fd(read_end)
+        });
+        let device = AsyncHidDevice::new(HidDevice::from_backend(Box::new(device))).unwrap();
+        assert!(matches!(device.reader, Reader::Fd(_)));
+
+        let writer = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(10));
+            nix::unistd::write(write_end.as_raw_fd(), &[0x01, 0x02]).unwrap();
+            // Closing the pipe would look like a disconnect
+            write_end
+        });
+        let mut buf = [0u8; 4];
+        assert_eq!(block_on(device.read(&mut buf)).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0x01, 0x02]);
+        drop(writer.join().unwrap());
+    }
+
+    #[test]
+    fn test_cancelled_read_keeps_report() {
+        let mock = MockDevice::new(0, 0);
+        let device = AsyncHidDevice::new(HidDevice::from_mock(mock.clone())).unwrap();
+
+        let mut buf = [0u8; 4];
+        {
+            let mut read = pin!(device.read(&mut buf));
+            let waker = Arc::new(ThreadWaker(thread::current())).into();
+            assert!(read
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending());
+            mock.push_input(vec![0x07]);
+            thread::park_timeout(std::time::Duration::from_secs(1));
+        }
+
+        assert_eq!(block_on(device.read(&mut buf)).unwrap(), 1);
+        assert_eq!(buf[0], 0x07);
+
+        let device = device.into_inner();
+        mock.push_input(vec![0x08]);
+        assert_eq!(device.read(&mut buf).unwrap(), 1);
+    }
+}
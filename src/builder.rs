@@ -0,0 +1,90 @@
+use crate::{HidApi, HidResult};
+
+/// Configuration of a [`HidApi`], created with [`HidApi::builder`].
+///
+/// The settings apply to the global state of the C library, so the same rules as for the
+/// functions they replace apply.
+///
+/// ```no_run
+/// use hidapi::HidApi;
+///
+/// let api = HidApi::builder()
+///     .device_discovery(false)
+///     .macos_exclusive(false)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct HidApiBuilder {
+    device_discovery: bool,
+    macos_exclusive: Option<bool>,
+}
+
+impl Default for HidApiBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HidApiBuilder {
+    /// Create a builder with the default settings of [`HidApi::new`].
+    pub fn new() -> Self {
+        Self {
+            device_discovery: true,
+            macos_exclusive: None,
+        }
+    }
+
+    /// Enable or disable device discovery when the C library is initialized, see
+    /// [`HidApi::disable_device_discovery`].
+    ///
+    /// Enabling it keeps the current setting, it can't be enabled again once disabled.
+    pub fn device_discovery(mut self, enabled: bool) -> Self {
+        self.device_discovery = enabled;
+        self
+    }
+
+    /// Open devices in exclusive or shared mode on macOS, see `HidApi::set_open_exclusive`.
+    ///
+    /// This has no effect on other platforms.
+    pub fn macos_exclusive(mut self, exclusive: bool) -> Self {
+        self.macos_exclusive = Some(exclusive);
+        self
+    }
+
+    /// Create the [`HidApi`] with this configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if device discovery is disabled, but an hidapi context has already been initialized
+    /// with device discovery.
+    pub fn build(self) -> HidResult<HidApi> {
+        if !self.device_discovery {
+            HidApi::disable_device_discovery();
+        }
+        let api = HidApi::new()?;
+        #[cfg(target_os = "macos")]
+        if let Some(exclusive) = self.macos_exclusive {
+            api.set_open_exclusive(exclusive);
+        }
+        Ok(api)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_settings() {
+        let builder = HidApi::builder();
+        assert_eq!(builder, HidApiBuilder::default());
+        assert!(builder.device_discovery);
+        assert_eq!(builder.macos_exclusive, None);
+
+        let builder = builder.device_discovery(false).macos_exclusive(true);
+        assert!(!builder.device_discovery);
+        assert_eq!(builder.macos_exclusive, Some(true));
+    }
+}
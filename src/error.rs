@@ -11,6 +11,7 @@ use std::fmt::{Display, Formatter, Result};
 use crate::DeviceInfo;
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum HidError {
     HidApiError {
         message: String,
@@ -31,6 +32,52 @@ pub enum HidError {
     OpenHidDeviceWithDeviceInfoError {
         device_info: Box<DeviceInfo>,
     },
+    /// The path of a [`DeviceInfo`] no longer refers to a device, and the device has no serial
+    /// number to find it under a different path.
+    DevicePathGone {
+        device_info: Box<DeviceInfo>,
+    },
+    /// No connected device matches the vid, pid and serial number of a [`DeviceInfo`].
+    NoMatchingDevice {
+        device_info: Box<DeviceInfo>,
+    },
+    /// The device at a path is not the expected one, see [`HidApi::open_path_verified`].
+    /// `device_info` describes the device that was found instead.
+    ///
+    /// [`HidApi::open_path_verified`]: crate::HidApi::open_path_verified
+    Mismatch {
+        device_info: Box<DeviceInfo>,
+    },
+    /// The device has been disconnected
+    Disconnected,
+    /// The user has no permission to open the device at `path`.
+    ///
+    /// On Linux, access to hidraw nodes is usually granted with a udev rule.
+    PermissionDenied {
+        path: String,
+    },
+    /// No device matches the [`DeviceFilter`], see [`HidApi::open_first`].
+    ///
+    /// [`DeviceFilter`]: crate::DeviceFilter
+    /// [`HidApi::open_first`]: crate::HidApi::open_first
+    NotFound,
+    /// No report arrived before the timeout expired, see [`HidDevice::read_timeout_strict`].
+    ///
+    /// [`HidDevice::read_timeout_strict`]: crate::HidDevice::read_timeout_strict
+    Timeout,
+    /// The backend can not retrieve the report descriptor of the device.
+    ///
+    /// The libusb backend has to request it from the device, which fails for some devices.
+    ReportDescriptorUnavailable,
+    /// A report descriptor is larger than [`MAX_REPORT_DESCRIPTOR_SIZE`], which only happens
+    /// with malformed devices.
+    ///
+    /// `size` is the number of bytes read, reading stops shortly after exceeding the limit.
+    ///
+    /// [`MAX_REPORT_DESCRIPTOR_SIZE`]: crate::MAX_REPORT_DESCRIPTOR_SIZE
+    ReportDescriptorTooLarge {
+        size: usize,
+    },
     /// An IO error or a system error that can be represented as such
     IoError {
         error: std::io::Error,
@@ -60,6 +107,34 @@ impl Display for HidError {
             HidError::OpenHidDeviceWithDeviceInfoError { device_info } => {
                 write!(f, "Can not open hid device with: {:?}", *device_info)
             }
+            HidError::DevicePathGone { device_info } => write!(
+                f,
+                "device path {:?} no longer exists and the device has no serial number",
+                device_info.path
+            ),
+            HidError::NoMatchingDevice { device_info } => {
+                write!(f, "no connected device matches {:?}", *device_info)
+            }
+            HidError::Mismatch { device_info } => write!(
+                f,
+                "the device at {:?} is not the expected one: {:?}",
+                device_info.path, *device_info
+            ),
+            HidError::Disconnected => write!(f, "device disconnected"),
+            HidError::PermissionDenied { path } => write!(
+                f,
+                "permission denied for device {path}, on Linux a udev rule has to grant access"
+            ),
+            HidError::NotFound => write!(f, "no matching device found"),
+            HidError::Timeout => write!(f, "timed out"),
+            HidError::ReportDescriptorUnavailable => {
+                write!(f, "the report descriptor of the device is not available")
+            }
+            HidError::ReportDescriptorTooLarge { size } => write!(
+                f,
+                "report descriptor of {size} bytes exceeds the maximum of {} bytes",
+                crate::MAX_REPORT_DESCRIPTOR_SIZE
+            ),
             HidError::IoError { error } => {
                 write!(f, "{error}")
             }
@@ -69,15 +144,252 @@ impl Display for HidError {
 
 impl Error for HidError {}
 
+/// A portable category of a [`HidError`], see [`HidError::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HidErrorKind {
+    /// The device has been disconnected
+    Disconnected,
+    /// The device or a requested resource does not exist
+    NotFound,
+    /// Access to the device was denied
+    PermissionDenied,
+    /// The device is in use or temporarily unavailable
+    Busy,
+    /// The operation did not complete in time
+    Timeout,
+    /// The device is in non-blocking mode and the operation would have to wait
+    WouldBlock,
+    /// The data passed to or returned from the device was invalid
+    InvalidData,
+    /// Another IO or system error
+    Io,
+    /// Any other error, usually reported by the backend only as a message
+    Other,
+}
+
+impl HidErrorKind {
+    /// Check if errors of this kind may go away by themselves, e.g. while a device reconnects.
+    pub fn is_transient(self) -> bool {
+        matches!(
+            self,
+            HidErrorKind::Busy | HidErrorKind::PermissionDenied | HidErrorKind::NotFound
+        )
+    }
+}
+
+impl HidError {
+    /// Get the category of this error, for handling errors the same way on all platforms.
+    pub fn kind(&self) -> HidErrorKind {
+        match self {
+            HidError::Disconnected => HidErrorKind::Disconnected,
+            HidError::Timeout => HidErrorKind::Timeout,
+            HidError::PermissionDenied { .. } => HidErrorKind::PermissionDenied,
+            HidError::NotFound
+            | HidError::OpenHidDeviceWithDeviceInfoError { .. }
+            | HidError::DevicePathGone { .. }
+            | HidError::NoMatchingDevice { .. }
+            | HidError::Mismatch { .. } => HidErrorKind::NotFound,
+            HidError::FromWideCharError { .. }
+            | HidError::InvalidZeroSizeData
+            | HidError::ReportDescriptorTooLarge { .. } => HidErrorKind::InvalidData,
+            HidError::IncompleteSendError { .. } => HidErrorKind::Io,
+            HidError::IoError { error } => io_error_kind(error),
+            HidError::HidApiError { .. }
+            | HidError::HidApiErrorEmpty
+            | HidError::InitializationError
+            | HidError::SetBlockingModeError { .. }
+            | HidError::ReportDescriptorUnavailable => HidErrorKind::Other,
+        }
+    }
+
+    /// Make a copy of this error, `HidError` itself can't be `Clone` because `std::io::Error`
+    /// isn't.
+    ///
+    /// IO errors keep their OS error code if they have one, otherwise only their kind and message.
+    #[cfg_attr(
+        not(all(feature = "linux-native", target_os = "linux")),
+        allow(dead_code)
+    )]
+    pub(crate) fn duplicate(&self) -> HidError {
+        match self {
+            HidError::HidApiError { message } => HidError::HidApiError {
+                message: message.clone(),
+            },
+            HidError::HidApiErrorEmpty => HidError::HidApiErrorEmpty,
+            HidError::FromWideCharError { wide_char } => HidError::FromWideCharError {
+                wide_char: *wide_char,
+            },
+            HidError::InitializationError => HidError::InitializationError,
+            HidError::InvalidZeroSizeData => HidError::InvalidZeroSizeData,
+            HidError::IncompleteSendError { sent, all } => HidError::IncompleteSendError {
+                sent: *sent,
+                all: *all,
+            },
+            HidError::SetBlockingModeError { mode } => HidError::SetBlockingModeError { mode },
+            HidError::OpenHidDeviceWithDeviceInfoError { device_info } => {
+                HidError::OpenHidDeviceWithDeviceInfoError {
+                    device_info: device_info.clone(),
+                }
+            }
+            HidError::DevicePathGone { device_info } => HidError::DevicePathGone {
+                device_info: device_info.clone(),
+            },
+            HidError::NoMatchingDevice { device_info } => HidError::NoMatchingDevice {
+                device_info: device_info.clone(),
+            },
+            HidError::Mismatch { device_info } => HidError::Mismatch {
+                device_info: device_info.clone(),
+            },
+            HidError::Disconnected => HidError::Disconnected,
+            HidError::PermissionDenied { path } => {
+                HidError::PermissionDenied { path: path.clone() }
+            }
+            HidError::NotFound => HidError::NotFound,
+            HidError::Timeout => HidError::Timeout,
+            HidError::ReportDescriptorUnavailable => HidError::ReportDescriptorUnavailable,
+            HidError::ReportDescriptorTooLarge { size } => {
+                HidError::ReportDescriptorTooLarge { size: *size }
+            }
+            HidError::IoError { error } => HidError::IoError {
+                error: match error.raw_os_error() {
+                    Some(code) => std::io::Error::from_raw_os_error(code),
+                    None => std::io::Error::new(error.kind(), error.to_string()),
+                },
+            },
+        }
+    }
+}
+
+fn io_error_kind(error: &std::io::Error) -> HidErrorKind {
+    use std::io::ErrorKind;
+
+    #[cfg(unix)]
+    if error.raw_os_error() == Some(libc::ENODEV) {
+        return HidErrorKind::Disconnected;
+    }
+
+    match error.kind() {
+        ErrorKind::NotConnected | ErrorKind::BrokenPipe => HidErrorKind::Disconnected,
+        ErrorKind::NotFound => HidErrorKind::NotFound,
+        ErrorKind::PermissionDenied => HidErrorKind::PermissionDenied,
+        ErrorKind::ResourceBusy => HidErrorKind::Busy,
+        ErrorKind::TimedOut => HidErrorKind::Timeout,
+        ErrorKind::WouldBlock => HidErrorKind::WouldBlock,
+        ErrorKind::InvalidData | ErrorKind::InvalidInput => HidErrorKind::InvalidData,
+        _ => HidErrorKind::Io,
+    }
+}
+
 impl From<std::io::Error> for HidError {
     fn from(e: std::io::Error) -> Self {
         Self::IoError { error: e }
     }
 }
 
+impl From<HidError> for std::io::Error {
+    fn from(e: HidError) -> Self {
+        match e {
+            HidError::IoError { error } => error,
+            e @ HidError::PermissionDenied { .. } => {
+                std::io::Error::new(std::io::ErrorKind::PermissionDenied, e)
+            }
+            e => std::io::Error::other(e),
+        }
+    }
+}
+
 #[cfg(all(feature = "linux-native", target_os = "linux"))]
 impl From<nix::errno::Errno> for HidError {
     fn from(e: nix::errno::Errno) -> Self {
         Self::IoError { error: e.into() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(HidError::Disconnected.kind(), HidErrorKind::Disconnected);
+        assert_eq!(HidError::NotFound.kind(), HidErrorKind::NotFound);
+        assert_eq!(HidError::Timeout.kind(), HidErrorKind::Timeout);
+        assert_eq!(
+            HidError::PermissionDenied {
+                path: "/dev/hidraw0".into()
+            }
+            .kind(),
+            HidErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            HidError::InvalidZeroSizeData.kind(),
+            HidErrorKind::InvalidData
+        );
+        assert_eq!(
+            HidError::IncompleteSendError { sent: 1, all: 2 }.kind(),
+            HidErrorKind::Io
+        );
+        assert_eq!(
+            HidError::HidApiError {
+                message: "hid_error is not implemented yet".into()
+            }
+            .kind(),
+            HidErrorKind::Other
+        );
+        assert_eq!(
+            HidError::ReportDescriptorUnavailable.kind(),
+            HidErrorKind::Other
+        );
+        assert_eq!(
+            HidError::ReportDescriptorTooLarge { size: 4097 }.kind(),
+            HidErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_io_error_kind() {
+        let kind = |error: std::io::Error| HidError::from(error).kind();
+
+        assert_eq!(kind(ErrorKind::NotFound.into()), HidErrorKind::NotFound);
+        assert_eq!(
+            kind(ErrorKind::PermissionDenied.into()),
+            HidErrorKind::PermissionDenied
+        );
+        assert_eq!(kind(ErrorKind::TimedOut.into()), HidErrorKind::Timeout);
+        assert_eq!(kind(ErrorKind::WouldBlock.into()), HidErrorKind::WouldBlock);
+        assert_eq!(kind(ErrorKind::ResourceBusy.into()), HidErrorKind::Busy);
+        assert_eq!(
+            kind(ErrorKind::BrokenPipe.into()),
+            HidErrorKind::Disconnected
+        );
+        assert_eq!(kind(ErrorKind::Other.into()), HidErrorKind::Io);
+        #[cfg(unix)]
+        assert_eq!(
+            kind(std::io::Error::from_raw_os_error(libc::ENODEV)),
+            HidErrorKind::Disconnected
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            kind(std::io::Error::from_raw_os_error(libc::EBUSY)),
+            HidErrorKind::Busy
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            kind(std::io::Error::from_raw_os_error(libc::EAGAIN)),
+            HidErrorKind::WouldBlock
+        );
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(HidErrorKind::Busy.is_transient());
+        assert!(HidErrorKind::PermissionDenied.is_transient());
+        assert!(HidErrorKind::NotFound.is_transient());
+        assert!(!HidErrorKind::InvalidData.is_transient());
+        assert!(!HidErrorKind::Disconnected.is_transient());
+        assert!(!HidErrorKind::Other.is_transient());
+        assert!(!HidErrorKind::WouldBlock.is_transient());
+    }
+}
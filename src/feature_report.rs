@@ -0,0 +1,102 @@
+/// A feature report with its report ID, see [`HidDevice::send_feature`].
+///
+/// The backends expect the report ID in the first byte of a feature report, followed by the
+/// data. Devices that don't use numbered reports still need a leading 0, which is not sent to the
+/// device. This type keeps both apart, so the framing can't go wrong.
+///
+/// ```
+/// use hidapi::FeatureReport;
+///
+/// let report = FeatureReport::new(0x02, vec![0x10, 0x20]);
+/// assert_eq!(report.to_bytes(), [0x02, 0x10, 0x20]);
+///
+/// let report = FeatureReport::unnumbered(vec![0x10, 0x20]);
+/// assert_eq!(report.to_bytes(), [0x00, 0x10, 0x20]);
+/// ```
+///
+/// [`HidDevice::send_feature`]: crate::HidDevice::send_feature
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FeatureReport {
+    id: u8,
+    data: Vec<u8>,
+}
+
+impl FeatureReport {
+    /// Create a report with the given report ID, 0 for devices that don't use numbered reports.
+    pub fn new(id: u8, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id,
+            data: data.into(),
+        }
+    }
+
+    /// Create a report for a device that doesn't use numbered reports.
+    pub fn unnumbered(data: impl Into<Vec<u8>>) -> Self {
+        Self::new(0, data)
+    }
+
+    /// The report ID, or 0 if the report is not numbered.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The report data, without the report ID.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Take the report data, without the report ID.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The buffer passed to [`HidDevice::send_feature_report`], starting with the report ID.
+    ///
+    /// [`HidDevice::send_feature_report`]: crate::HidDevice::send_feature_report
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.data.len() + 1);
+        buf.push(self.id);
+        buf.extend_from_slice(&self.data);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use crate::HidDevice;
+
+    #[test]
+    fn test_numbered_feature_report() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        device
+            .send_feature(&FeatureReport::new(0x03, vec![0xAA, 0xBB]))
+            .unwrap();
+        assert_eq!(mock.take_feature_reports(), vec![vec![0x03, 0xAA, 0xBB]]);
+
+        mock.set_feature_report(vec![0x05, 0x01, 0x02, 0x03]);
+        let report = device.get_feature(0x05).unwrap();
+        assert_eq!(report.id(), 0x05);
+        assert_eq!(report.data(), &[0x01, 0x02, 0x03]);
+        assert!(device.get_feature(0x06).is_err());
+    }
+
+    #[test]
+    fn test_unnumbered_feature_report() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        device
+            .send_feature(&FeatureReport::unnumbered(vec![0xAA]))
+            .unwrap();
+        assert_eq!(mock.take_feature_reports(), vec![vec![0x00, 0xAA]]);
+
+        mock.set_feature_report(vec![0x00, 0x01, 0x02]);
+        let report = device.get_feature(0).unwrap();
+        assert_eq!(report, FeatureReport::unnumbered(vec![0x01, 0x02]));
+        assert_eq!(report.into_data(), vec![0x01, 0x02]);
+    }
+}
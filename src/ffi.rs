@@ -6,10 +6,13 @@
 /// This file is part of hidapi-rs, based on hidapi_rust by Roland Ruckerbauer.
 /// *************************************************************************
 // For documentation look at the corresponding C header file hidapi.h
-use libc::{c_char, c_int, c_uchar, c_ushort, c_void, intptr_t, size_t, wchar_t};
-type HidBusType = crate::BusType;
+use libc::{c_char, c_int, c_uchar, c_uint, c_ushort, c_void, intptr_t, size_t, wchar_t};
+// A C enum, which can hold values that `BusType` doesn't know
+type HidBusType = c_int;
 pub type HidDevice = c_void;
 type LibusbContext = c_void;
+pub type LibusbDevice = c_void;
+pub type LibusbDeviceHandle = c_void;
 
 #[repr(C)]
 pub struct HidDeviceInfo {
@@ -44,6 +47,46 @@ extern "C" {
     pub fn hid_libusb_wrap_sys_device(sys_dev: intptr_t, interface_num: c_int) -> *mut HidDevice;
     #[cfg(all(libusb, not(target_os = "freebsd")))]
     pub fn libusb_set_option(ctx: *mut LibusbContext, option: c_int);
+    #[cfg(libusb)]
+    pub fn libusb_init(ctx: *mut *mut LibusbContext) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_exit(ctx: *mut LibusbContext);
+    #[cfg(libusb)]
+    pub fn libusb_get_device_list(
+        ctx: *mut LibusbContext,
+        list: *mut *const *mut LibusbDevice,
+    ) -> isize;
+    #[cfg(libusb)]
+    pub fn libusb_free_device_list(list: *const *mut LibusbDevice, unref_devices: c_int);
+    #[cfg(libusb)]
+    pub fn libusb_get_bus_number(device: *mut LibusbDevice) -> u8;
+    #[cfg(libusb)]
+    pub fn libusb_get_port_numbers(
+        device: *mut LibusbDevice,
+        port_numbers: *mut u8,
+        port_numbers_len: c_int,
+    ) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_open(device: *mut LibusbDevice, handle: *mut *mut LibusbDeviceHandle) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_close(handle: *mut LibusbDeviceHandle);
+    #[cfg(libusb)]
+    pub fn libusb_kernel_driver_active(handle: *mut LibusbDeviceHandle, interface: c_int) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_detach_kernel_driver(handle: *mut LibusbDeviceHandle, interface: c_int) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_attach_kernel_driver(handle: *mut LibusbDeviceHandle, interface: c_int) -> c_int;
+    #[cfg(libusb)]
+    pub fn libusb_control_transfer(
+        handle: *mut LibusbDeviceHandle,
+        request_type: u8,
+        request: u8,
+        value: u16,
+        index: u16,
+        data: *mut c_uchar,
+        length: u16,
+        timeout: c_uint,
+    ) -> c_int;
     pub fn hid_write(device: *mut HidDevice, data: *const c_uchar, length: size_t) -> c_int;
     pub fn hid_read_timeout(
         device: *mut HidDevice,
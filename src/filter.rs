@@ -0,0 +1,193 @@
+use crate::{BusType, DeviceInfo};
+
+/// Criteria to select devices from [`HidApi::device_list_filtered`](crate::HidApi::device_list_filtered).
+///
+/// Every criterion that is not set matches all devices.
+///
+/// ```
+/// use hidapi::DeviceFilter;
+///
+/// let filter = DeviceFilter::new().vendor_id(0x046d).usage_page(0xff00);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceFilter {
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    usage_page: Option<u16>,
+    usage: Option<u16>,
+    interface_number: Option<i32>,
+    bus_type: Option<BusType>,
+}
+
+impl DeviceFilter {
+    /// Create a filter that matches all devices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match devices with the given vendor ID.
+    pub fn vendor_id(mut self, vendor_id: u16) -> Self {
+        self.vendor_id = Some(vendor_id);
+        self
+    }
+
+    /// Only match devices with the given product ID.
+    pub fn product_id(mut self, product_id: u16) -> Self {
+        self.product_id = Some(product_id);
+        self
+    }
+
+    /// Only match devices with the given usage page.
+    ///
+    /// Devices with multiple top level collections are listed once per usage, so this is
+    /// checked against each of those entries.
+    pub fn usage_page(mut self, usage_page: u16) -> Self {
+        self.usage_page = Some(usage_page);
+        self
+    }
+
+    /// Only match devices with the given usage.
+    pub fn usage(mut self, usage: u16) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Only match devices with the given interface number.
+    pub fn interface_number(mut self, interface_number: i32) -> Self {
+        self.interface_number = Some(interface_number);
+        self
+    }
+
+    /// Only match devices connected through the given bus.
+    pub fn bus_type(mut self, bus_type: BusType) -> Self {
+        self.bus_type = Some(bus_type);
+        self
+    }
+
+    /// Check if a device matches all criteria of the filter.
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        check(self.vendor_id, info.vendor_id)
+            && check(self.product_id, info.product_id)
+            && self.matches_usage(info.usage_page, info.usage)
+            && check(self.interface_number, info.interface_number)
+            && check(self.bus_type, info.bus_type)
+    }
+
+    /// Create a filter from the vendor and product ID arguments of the enumeration, where 0
+    /// matches all devices.
+    #[cfg_attr(
+        not(all(feature = "windows-native", target_os = "windows")),
+        allow(dead_code)
+    )]
+    pub(crate) fn from_vid_pid(vendor_id: u16, product_id: u16) -> Self {
+        Self {
+            vendor_id: (vendor_id != 0).then_some(vendor_id),
+            product_id: (product_id != 0).then_some(product_id),
+            ..Self::default()
+        }
+    }
+
+    /// The vendor and product ID to pass to the backend enumeration, 0 matches all devices.
+    pub(crate) fn vid_pid(&self) -> (u16, u16) {
+        (self.vendor_id.unwrap_or(0), self.product_id.unwrap_or(0))
+    }
+
+    /// Check if the filter has criteria for the usage page or usage.
+    #[cfg_attr(
+        not(all(feature = "windows-native", target_os = "windows")),
+        allow(dead_code)
+    )]
+    pub(crate) fn has_usage(&self) -> bool {
+        self.usage_page.is_some() || self.usage.is_some()
+    }
+
+    /// Check only the usage page and usage criteria, which some backends know before the rest of
+    /// the device info.
+    pub(crate) fn matches_usage(&self, usage_page: u16, usage: u16) -> bool {
+        check(self.usage_page, usage_page) && check(self.usage, usage)
+    }
+}
+
+fn check<T: PartialEq>(filter: Option<T>, value: T) -> bool {
+    filter.is_none_or(|f| f == value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use crate::{api_with, HidApi, HidDevice};
+
+    fn device_info(
+        vendor_id: u16,
+        usage_page: u16,
+        usage: u16,
+        interface_number: i32,
+        bus_type: BusType,
+    ) -> DeviceInfo {
+        let mut info = HidDevice::from_mock(MockDevice::new(vendor_id, 0x0001))
+            .get_device_info()
+            .unwrap();
+        info.usage_page = usage_page;
+        info.usage = usage;
+        info.interface_number = interface_number;
+        info.bus_type = bus_type;
+        info
+    }
+
+    fn api() -> HidApi {
+        api_with(vec![
+            // A receiver with multiple usages on one interface
+            device_info(0x046d, 0x0001, 0x0002, 2, BusType::Usb),
+            device_info(0x046d, 0x000c, 0x0001, 2, BusType::Usb),
+            device_info(0x046d, 0xff00, 0x0001, 2, BusType::Usb),
+            device_info(0x1234, 0xff00, 0x0001, -1, BusType::Bluetooth),
+        ])
+    }
+
+    fn count(api: &HidApi, filter: DeviceFilter) -> usize {
+        api.device_list_filtered(filter).count()
+    }
+
+    #[test]
+    fn test_empty_filter_matches_all() {
+        let api = api();
+        assert_eq!(count(&api, DeviceFilter::new()), 4);
+    }
+
+    #[test]
+    fn test_filter_per_usage() {
+        let api = api();
+        assert_eq!(count(&api, DeviceFilter::new().usage_page(0xff00)), 2);
+        assert_eq!(
+            count(
+                &api,
+                DeviceFilter::new().vendor_id(0x046d).usage_page(0xff00)
+            ),
+            1
+        );
+        assert_eq!(count(&api, DeviceFilter::new().usage(0x0001)), 3);
+        assert_eq!(
+            count(&api, DeviceFilter::new().usage_page(0x000c).usage(0x0002)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_filter_ids_interface_and_bus() {
+        let api = api();
+        assert_eq!(count(&api, DeviceFilter::new().product_id(0x0001)), 4);
+        assert_eq!(count(&api, DeviceFilter::new().product_id(0x0002)), 0);
+        assert_eq!(count(&api, DeviceFilter::new().interface_number(2)), 3);
+        assert_eq!(
+            count(&api, DeviceFilter::new().bus_type(BusType::Bluetooth)),
+            1
+        );
+
+        let info = api
+            .device_list_filtered(DeviceFilter::new().bus_type(BusType::Bluetooth))
+            .next()
+            .unwrap();
+        assert_eq!(info.vendor_id(), 0x1234);
+    }
+}
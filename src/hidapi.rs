@@ -3,18 +3,29 @@
 use std::{
     ffi::CStr,
     fmt::{self, Debug},
+    mem::MaybeUninit,
 };
 use std::sync::atomic::AtomicBool;
 use libc::{c_int, size_t, wchar_t};
 
-use crate::{ffi, DeviceInfo, HidDeviceBackendBase, HidError, HidResult, WcharString};
+use cfg_if::cfg_if;
 
+use crate::{
+    ffi, language_ids, read_string_growing, AccessMode, BusType, DeviceInfo, GroupId,
+    HidDeviceBackendBase, HidError, HidResult, HidString,
+};
+
+#[cfg(libusb)]
+mod libusb;
+#[cfg(all(target_os = "linux", not(libusb)))]
+mod linux;
 #[cfg(target_os = "macos")]
 mod macos;
 #[cfg(target_os = "windows")]
 mod windows;
 
-const STRING_BUF_LEN: usize = 128;
+#[cfg(target_os = "macos")]
+pub(crate) use macos::DeviceNotification;
 
 pub struct HidApiBackend;
 
@@ -79,11 +90,32 @@ impl HidApiBackend {
         }
     }
 
+    pub fn open_path_with(device_path: &CStr, access: AccessMode) -> HidResult<HidDevice> {
+        match access {
+            AccessMode::ReadWrite => Self::open_path(device_path),
+            _ => Err(HidError::HidApiError {
+                message: "open_path_with: only AccessMode::ReadWrite is supported".into(),
+            }),
+        }
+    }
+
+    pub fn is_present(device_path: &CStr) -> bool {
+        cfg_if! {
+            if #[cfg(all(target_os = "linux", not(libusb)))] {
+                linux::is_device_node(device_path)
+            } else {
+                // The paths of the other backends don't refer to files
+                Self::get_hid_device_info_vector(0, 0)
+                    .is_ok_and(|devices| devices.iter().any(|d| d.path.as_c_str() == device_path))
+            }
+        }
+    }
+
     pub fn check_error() -> HidResult<HidError> {
         Ok(HidError::HidApiError {
             message: unsafe {
                 match wchar_to_string(ffi::hid_error(std::ptr::null_mut())) {
-                    WcharString::String(s) => s,
+                    HidString::String(s) => s,
                     _ => return Err(HidError::HidApiErrorEmpty),
                 }
             },
@@ -91,10 +123,10 @@ impl HidApiBackend {
     }
 }
 
-/// Converts a pointer to a `*const wchar_t` to a WcharString.
-unsafe fn wchar_to_string(wstr: *const wchar_t) -> WcharString {
+/// Converts a pointer to a `*const wchar_t` to a HidString.
+unsafe fn wchar_to_string(wstr: *const wchar_t) -> HidString {
     if wstr.is_null() {
-        return WcharString::None;
+        return HidString::None;
     }
 
     let mut char_vector: Vec<char> = Vec::with_capacity(8);
@@ -121,29 +153,74 @@ unsafe fn wchar_to_string(wstr: *const wchar_t) -> WcharString {
     }
 
     if !invalid_char {
-        WcharString::String(char_vector.into_iter().collect())
+        HidString::String(char_vector.into_iter().collect())
     } else {
-        WcharString::Raw(raw_vector)
+        HidString::Raw(raw_vector)
     }
 }
 
 /// Convert the CFFI `HidDeviceInfo` struct to a native `HidDeviceInfo` struct
 pub unsafe fn conv_hid_device_info(src: *mut ffi::HidDeviceInfo) -> HidResult<DeviceInfo> {
+    let path = CStr::from_ptr((*src).path);
+    #[cfg(target_os = "macos")]
+    let location_id = macos::location_id_from_path(path);
+    #[cfg(not(target_os = "macos"))]
+    let location_id = None;
+    let bus_type = u8::try_from((*src).bus_type)
+        .ok()
+        .and_then(|bus_type| BusType::try_from(bus_type).ok())
+        .unwrap_or(BusType::Unknown);
+    #[cfg(target_os = "macos")]
+    let bus_type = macos::fill_in_bus_type(path, bus_type);
+    #[cfg(libusb)]
+    let (usage_page, usage) = libusb::fill_in_usage(
+        path,
+        (*src).vendor_id,
+        (*src).product_id,
+        (*src).usage_page,
+        (*src).usage,
+    );
+    #[cfg(not(libusb))]
+    let (usage_page, usage) = ((*src).usage_page, (*src).usage);
+
     Ok(DeviceInfo {
-        path: CStr::from_ptr((*src).path).to_owned(),
+        path: path.to_owned(),
         vendor_id: (*src).vendor_id,
         product_id: (*src).product_id,
         serial_number: wchar_to_string((*src).serial_number),
         release_number: (*src).release_number,
         manufacturer_string: wchar_to_string((*src).manufacturer_string),
         product_string: wchar_to_string((*src).product_string),
-        usage_page: (*src).usage_page,
-        usage: (*src).usage,
+        usage_page,
+        usage,
         interface_number: (*src).interface_number,
-        bus_type: (*src).bus_type,
+        bus_type,
+        location_id,
+        bluetooth_address: None,
+        group_id: group_id(path, location_id),
     })
 }
 
+/// Derive the group of a device from the information provided by the C library
+fn group_id(path: &CStr, location_id: Option<u32>) -> Option<GroupId> {
+    if let Some(location_id) = location_id {
+        return Some(GroupId::new(format!("{:08x}", location_id)));
+    }
+
+    cfg_if! {
+        if #[cfg(libusb)] {
+            // libusb paths have the format `<bus>-<ports>:<config>.<interface>`
+            let (device, _) = path.to_str().ok()?.split_once(':')?;
+            Some(GroupId::new(device))
+        } else if #[cfg(target_os = "linux")] {
+            linux::group_id_from_hidraw(path)
+        } else {
+            let _ = path;
+            None
+        }
+    }
+}
+
 /// Object for accessing HID device
 pub struct HidDevice {
     _hid_device: *mut ffi::HidDevice,
@@ -190,6 +267,55 @@ impl HidDevice {
             Ok(res as usize)
         }
     }
+
+    /// Read a string with one of the `hid_get_*_string` functions, without converting it
+    fn get_string_raw(
+        &self,
+        get_string: unsafe extern "C" fn(*mut ffi::HidDevice, *mut wchar_t, size_t) -> c_int,
+    ) -> HidResult<Option<Vec<wchar_t>>> {
+        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(HidError::HidApiError {
+                message: "HidDevice is closed".to_string(),
+            });
+        }
+
+        let string = read_string_growing(|buf| {
+            let res = unsafe { get_string(self._hid_device, buf.as_mut_ptr(), buf.len()) };
+            self.check_size(res).map(drop)
+        })?;
+        Ok(Some(string))
+    }
+
+    /// Read a string with one of the `hid_get_*_string` functions
+    fn get_string(
+        &self,
+        get_string: unsafe extern "C" fn(*mut ffi::HidDevice, *mut wchar_t, size_t) -> c_int,
+    ) -> HidResult<Option<String>> {
+        let mut string = self.get_string_raw(get_string)?.unwrap_or_default();
+        string.push(0);
+        unsafe { Ok(wchar_to_string(string.as_ptr()).into()) }
+    }
+
+    /// Read the string descriptor with `index` as it is
+    fn get_indexed_string_raw(&self, index: i32) -> HidResult<Vec<wchar_t>> {
+        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(HidError::HidApiError {
+                message: "HidDevice is closed".to_string(),
+            });
+        }
+
+        read_string_growing(|buf| {
+            let res = unsafe {
+                ffi::hid_get_indexed_string(
+                    self._hid_device,
+                    index as c_int,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                )
+            };
+            self.check_size(res).map(drop)
+        })
+    }
 }
 
 impl HidDeviceBackendBase for HidDevice {
@@ -202,7 +328,7 @@ impl HidDeviceBackendBase for HidDevice {
         Ok(HidError::HidApiError {
             message: unsafe {
                 match wchar_to_string(ffi::hid_error(self._hid_device)) {
-                    WcharString::String(s) => s,
+                    HidString::String(s) => s,
                     _ => return Err(HidError::HidApiErrorEmpty),
                 }
             },
@@ -235,6 +361,12 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        // SAFETY: hidapi only writes the report into the buffer
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.read_timeout_uninit(buf, timeout)
+    }
+
+    fn read_timeout_uninit(&self, buf: &mut [MaybeUninit<u8>], timeout: i32) -> HidResult<usize> {
         if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
             return Err(HidError::HidApiError {
                 message: "HidDevice is closed".to_string(),
@@ -244,7 +376,7 @@ impl HidDeviceBackendBase for HidDevice {
         let res = unsafe {
             ffi::hid_read_timeout(
                 self._hid_device,
-                buf.as_mut_ptr(),
+                buf.as_mut_ptr().cast(),
                 buf.len() as size_t,
                 timeout,
             )
@@ -372,60 +504,27 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
-        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(HidError::HidApiError {
-                message: "HidDevice is closed".to_string(),
-            });
-        }
-
-        let mut buf = [0 as wchar_t; STRING_BUF_LEN];
-        let res = unsafe {
-            ffi::hid_get_manufacturer_string(
-                self._hid_device,
-                buf.as_mut_ptr(),
-                STRING_BUF_LEN as size_t,
-            )
-        };
-        let res = self.check_size(res)?;
-        unsafe { Ok(wchar_to_string(buf[..res].as_ptr()).into()) }
+        self.get_string(ffi::hid_get_manufacturer_string)
     }
 
     fn get_product_string(&self) -> HidResult<Option<String>> {
-        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(HidError::HidApiError {
-                message: "HidDevice is closed".to_string(),
-            });
-        }
-
-        let mut buf = [0 as wchar_t; STRING_BUF_LEN];
-        let res = unsafe {
-            ffi::hid_get_product_string(
-                self._hid_device,
-                buf.as_mut_ptr(),
-                STRING_BUF_LEN as size_t,
-            )
-        };
-        let res = self.check_size(res)?;
-        unsafe { Ok(wchar_to_string(buf[..res].as_ptr()).into()) }
+        self.get_string(ffi::hid_get_product_string)
     }
 
     fn get_serial_number_string(&self) -> HidResult<Option<String>> {
-        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(HidError::HidApiError {
-                message: "HidDevice is closed".to_string(),
-            });
-        }
+        self.get_string(ffi::hid_get_serial_number_string)
+    }
 
-        let mut buf = [0 as wchar_t; STRING_BUF_LEN];
-        let res = unsafe {
-            ffi::hid_get_serial_number_string(
-                self._hid_device,
-                buf.as_mut_ptr(),
-                STRING_BUF_LEN as size_t,
-            )
-        };
-        let res = self.check_size(res)?;
-        unsafe { Ok(wchar_to_string(buf[..res].as_ptr()).into()) }
+    fn get_manufacturer_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.get_string_raw(ffi::hid_get_manufacturer_string)
+    }
+
+    fn get_product_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.get_string_raw(ffi::hid_get_product_string)
+    }
+
+    fn get_serial_number_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.get_string_raw(ffi::hid_get_serial_number_string)
     }
 
     fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
@@ -438,27 +537,73 @@ impl HidDeviceBackendBase for HidDevice {
         let res = unsafe {
             ffi::hid_get_report_descriptor(self._hid_device, buf.as_mut_ptr(), buf.len())
         };
+        if res == -1 && cfg!(libusb) {
+            // libusb requests the descriptor from the device with a control transfer, which does
+            // not work for every device, and hid_error only reports a generic failure for it
+            return Err(HidError::ReportDescriptorUnavailable);
+        }
         self.check_size(res)
     }
 
     fn get_indexed_string(&self, index: i32) -> HidResult<Option<String>> {
-        if self.is_closed.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(HidError::HidApiError {
-                message: "HidDevice is closed".to_string(),
-            });
-        }
+        let mut string = self.get_indexed_string_raw(index)?;
+        string.push(0);
+        unsafe { Ok(wchar_to_string(string.as_ptr()).into()) }
+    }
 
-        let mut buf = [0 as wchar_t; STRING_BUF_LEN];
-        let res = unsafe {
-            ffi::hid_get_indexed_string(
-                self._hid_device,
-                index as c_int,
-                buf.as_mut_ptr(),
-                STRING_BUF_LEN,
-            )
-        };
-        let res = self.check_size(res)?;
-        unsafe { Ok(wchar_to_string(buf[..res].as_ptr()).into()) }
+    fn supported_languages(&self) -> HidResult<Vec<u16>> {
+        // The descriptor at index 0 holds the language IDs, which are decoded like characters
+        language_ids(&self.get_indexed_string_raw(0)?)
+    }
+
+    // The libusb backend of the C library doesn't keep track of errors
+    #[cfg(not(libusb))]
+    fn last_error(&self) -> Option<HidError> {
+        // `hid_error` returns "Success" if the last operation didn't fail
+        self.check_error()
+            .ok()
+            .filter(|err| !matches!(err, HidError::HidApiError { message } if message == "Success"))
+    }
+
+    #[cfg(libusb)]
+    fn kernel_driver_active(&self) -> HidResult<bool> {
+        libusb::kernel_driver_active(&self.get_device_info()?.path)
+    }
+
+    #[cfg(libusb)]
+    fn detach_kernel_driver(&self) -> HidResult<()> {
+        libusb::detach_kernel_driver(&self.get_device_info()?.path)
+    }
+
+    #[cfg(libusb)]
+    fn reattach_kernel_driver(&self) -> HidResult<()> {
+        libusb::reattach_kernel_driver(&self.get_device_info()?.path)
+    }
+
+    #[cfg(libusb)]
+    fn set_protocol(&self, protocol: crate::HidProtocol) -> HidResult<()> {
+        libusb::set_protocol(&self.get_device_info()?.path, protocol)
+    }
+
+    #[cfg(libusb)]
+    fn get_protocol(&self) -> HidResult<crate::HidProtocol> {
+        libusb::get_protocol(&self.get_device_info()?.path)
+    }
+
+    #[cfg(libusb)]
+    fn set_idle(&self, report_id: u8, rate: u8) -> HidResult<()> {
+        libusb::set_idle(&self.get_device_info()?.path, report_id, rate)
+    }
+
+    #[cfg(libusb)]
+    fn get_idle(&self, report_id: u8) -> HidResult<u8> {
+        libusb::get_idle(&self.get_device_info()?.path, report_id)
+    }
+
+    #[cfg(libusb)]
+    fn refresh_strings(&self) -> HidResult<()> {
+        // libusb reads the strings from the device on every call
+        Ok(())
     }
 
     fn close(&self) -> HidResult<()> {
@@ -472,3 +617,19 @@ impl HidDeviceBackendBase for HidDevice {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_utf8_string() {
+        // An unpaired surrogate is not a valid char
+        let buf: [wchar_t; 5] = [0x41, 0xD800, 0x42, 0, 0x43];
+
+        assert!(matches!(
+            unsafe { wchar_to_string(buf.as_ptr()) },
+            HidString::Raw(ref raw) if raw[..] == buf[..3]
+        ));
+    }
+}
@@ -0,0 +1,495 @@
+//! The extra behaviour for the libusb backend
+
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::ptr::null_mut;
+use std::sync::Mutex;
+
+use libc::{c_int, c_uint};
+
+use super::HidDevice;
+use crate::{
+    ffi, HidDeviceBackendBase, HidError, HidProtocol, HidResult, ReportDescriptor,
+    MAX_REPORT_DESCRIPTOR_SIZE,
+};
+
+// From libusb.h
+const LIBUSB_ERROR_ACCESS: c_int = -3;
+const LIBUSB_ERROR_NO_DEVICE: c_int = -4;
+const LIBUSB_ERROR_NOT_FOUND: c_int = -5;
+const LIBUSB_ERROR_BUSY: c_int = -6;
+const LIBUSB_ERROR_NOT_SUPPORTED: c_int = -12;
+
+// HID class requests to an interface, from the USB HID specification 7.2
+const REQUEST_TYPE_GET: u8 = 0xa1;
+const REQUEST_TYPE_SET: u8 = 0x21;
+const HID_GET_IDLE: u8 = 0x02;
+const HID_GET_PROTOCOL: u8 = 0x03;
+const HID_SET_IDLE: u8 = 0x0a;
+const HID_SET_PROTOCOL: u8 = 0x0b;
+
+/// In milliseconds
+const CONTROL_TIMEOUT: c_uint = 1000;
+
+/// A HID class request, sent to the interface of the device
+#[derive(Debug, PartialEq)]
+struct ClassRequest {
+    request_type: u8,
+    request: u8,
+    value: u16,
+}
+
+impl ClassRequest {
+    fn set_protocol(protocol: HidProtocol) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_SET,
+            request: HID_SET_PROTOCOL,
+            value: u16::from(protocol as u8),
+        }
+    }
+
+    fn get_protocol() -> Self {
+        Self {
+            request_type: REQUEST_TYPE_GET,
+            request: HID_GET_PROTOCOL,
+            value: 0,
+        }
+    }
+
+    fn set_idle(report_id: u8, rate: u8) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_SET,
+            request: HID_SET_IDLE,
+            value: (u16::from(rate) << 8) | u16::from(report_id),
+        }
+    }
+
+    fn get_idle(report_id: u8) -> Self {
+        Self {
+            request_type: REQUEST_TYPE_GET,
+            request: HID_GET_IDLE,
+            value: u16::from(report_id),
+        }
+    }
+}
+
+/// Where a device is connected, parsed from a path of the C library
+#[derive(Debug, PartialEq)]
+struct UsbLocation {
+    bus: u8,
+    ports: Vec<u8>,
+    interface: u8,
+}
+
+/// Parse a path of the format `<bus>-<port>.<port>:<config>.<interface>`.
+fn parse_path(path: &str) -> Option<UsbLocation> {
+    let (device, interface) = path.split_once(':')?;
+    let (bus, ports) = device.split_once('-')?;
+    let (_config, interface) = interface.split_once('.')?;
+    Some(UsbLocation {
+        bus: bus.parse().ok()?,
+        ports: ports
+            .split('.')
+            .map(|port| port.parse().ok())
+            .collect::<Option<_>>()?,
+        interface: interface.parse().ok()?,
+    })
+}
+
+/// Check if a kernel driver is bound to the interface of the device at `path`.
+pub(super) fn kernel_driver_active(path: &CStr) -> HidResult<bool> {
+    with_interface(path, "kernel_driver_active", |handle, interface| unsafe {
+        ffi::libusb_kernel_driver_active(handle, interface)
+    })
+    .map(|active| active == 1)
+}
+
+/// Detach the kernel driver from the interface of the device at `path`.
+///
+/// Succeeds if no kernel driver was bound to the interface.
+pub(super) fn detach_kernel_driver(path: &CStr) -> HidResult<()> {
+    with_interface(path, "detach_kernel_driver", |handle, interface| {
+        match unsafe { ffi::libusb_detach_kernel_driver(handle, interface) } {
+            LIBUSB_ERROR_NOT_FOUND => 0,
+            res => res,
+        }
+    })
+    .map(|_| ())
+}
+
+/// Attach the kernel driver to the interface of the device at `path` again.
+pub(super) fn reattach_kernel_driver(path: &CStr) -> HidResult<()> {
+    with_interface(path, "reattach_kernel_driver", |handle, interface| unsafe {
+        ffi::libusb_attach_kernel_driver(handle, interface)
+    })
+    .map(|_| ())
+}
+
+/// Switch the device at `path` to the boot or report protocol.
+pub(super) fn set_protocol(path: &CStr, protocol: HidProtocol) -> HidResult<()> {
+    let request = ClassRequest::set_protocol(protocol);
+    class_request(path, "set_protocol", request, &mut []).map(|_| ())
+}
+
+/// Get the protocol the device at `path` currently uses.
+pub(super) fn get_protocol(path: &CStr) -> HidResult<HidProtocol> {
+    let mut protocol = [0u8];
+    match class_request(
+        path,
+        "get_protocol",
+        ClassRequest::get_protocol(),
+        &mut protocol,
+    )? {
+        1 => HidProtocol::try_from(protocol[0]),
+        _ => Err(HidError::HidApiError {
+            message: "get_protocol: the device sent no protocol".to_string(),
+        }),
+    }
+}
+
+/// Set the idle rate of a report of the device at `path`, in units of 4 ms.
+pub(super) fn set_idle(path: &CStr, report_id: u8, rate: u8) -> HidResult<()> {
+    let request = ClassRequest::set_idle(report_id, rate);
+    class_request(path, "set_idle", request, &mut []).map(|_| ())
+}
+
+/// Get the idle rate of a report of the device at `path`, in units of 4 ms.
+pub(super) fn get_idle(path: &CStr, report_id: u8) -> HidResult<u8> {
+    let mut rate = [0u8];
+    match class_request(
+        path,
+        "get_idle",
+        ClassRequest::get_idle(report_id),
+        &mut rate,
+    )? {
+        1 => Ok(rate[0]),
+        _ => Err(HidError::HidApiError {
+            message: "get_idle: the device sent no idle rate".to_string(),
+        }),
+    }
+}
+
+/// Send a HID class request to the interface of the device at `path`, returns the number of bytes
+/// sent or received in `data`.
+fn class_request(
+    path: &CStr,
+    name: &str,
+    request: ClassRequest,
+    data: &mut [u8],
+) -> HidResult<usize> {
+    // usbfs only accepts requests to an interface from the handle that claimed it, which is the
+    // one the C library keeps to itself, so a second handle would always fail with EBUSY
+    if cfg!(target_os = "linux") {
+        return Err(HidError::HidApiError {
+            message: format!("{name}: not supported on this platform"),
+        });
+    }
+    let length = u16::try_from(data.len()).unwrap_or(u16::MAX);
+    with_interface(path, name, |handle, interface| unsafe {
+        ffi::libusb_control_transfer(
+            handle,
+            request.request_type,
+            request.request,
+            request.value,
+            interface as u16,
+            data.as_mut_ptr(),
+            length,
+            CONTROL_TIMEOUT,
+        )
+    })
+    .map(|len| len as usize)
+}
+
+/// Run a libusb function on the interface of the device at `path`.
+///
+/// The C library keeps its libusb handle to itself, so this opens the device a second time in
+/// a separate libusb context.
+fn with_interface(
+    path: &CStr,
+    name: &str,
+    op: impl FnOnce(*mut ffi::LibusbDeviceHandle, c_int) -> c_int,
+) -> HidResult<c_int> {
+    let location =
+        path.to_str()
+            .ok()
+            .and_then(parse_path)
+            .ok_or_else(|| HidError::HidApiError {
+                message: format!("{name}: unexpected device path {:?}", path),
+            })?;
+
+    let path = path.to_string_lossy();
+    let mut ctx = null_mut();
+    check(unsafe { ffi::libusb_init(&mut ctx) }, &path, name)?;
+    let mut list = std::ptr::null();
+    let res = match unsafe { ffi::libusb_get_device_list(ctx, &mut list) } {
+        len if len < 0 => check(len as c_int, &path, name),
+        len => {
+            let devices = unsafe { std::slice::from_raw_parts(list, len as usize) };
+            let res = match devices.iter().find(|&&device| is_at(device, &location)) {
+                Some(&device) => run_on(device, location.interface, op, &path, name),
+                None => Err(HidError::Disconnected),
+            };
+            unsafe { ffi::libusb_free_device_list(list, 1) };
+            res
+        }
+    };
+    unsafe { ffi::libusb_exit(ctx) };
+    res
+}
+
+fn is_at(device: *mut ffi::LibusbDevice, location: &UsbLocation) -> bool {
+    let mut ports = [0u8; 8];
+    let len =
+        unsafe { ffi::libusb_get_port_numbers(device, ports.as_mut_ptr(), ports.len() as c_int) };
+    let bus = unsafe { ffi::libusb_get_bus_number(device) };
+    bus == location.bus && usize::try_from(len).is_ok_and(|len| ports[..len] == location.ports[..])
+}
+
+fn run_on(
+    device: *mut ffi::LibusbDevice,
+    interface: u8,
+    op: impl FnOnce(*mut ffi::LibusbDeviceHandle, c_int) -> c_int,
+    path: &str,
+    name: &str,
+) -> HidResult<c_int> {
+    let mut handle = null_mut();
+    check(unsafe { ffi::libusb_open(device, &mut handle) }, path, name)?;
+    let res = op(handle, c_int::from(interface));
+    unsafe { ffi::libusb_close(handle) };
+    check(res, path, name)
+}
+
+/// The usages derived by [`fill_in_usage`], by path, vendor ID and product ID of the device
+type UsageKey = (CString, u16, u16);
+static USAGES: Mutex<BTreeMap<UsageKey, (u16, u16)>> = Mutex::new(BTreeMap::new());
+
+/// Fill in the usage of a device that the C library reported as 0, from its report descriptor.
+///
+/// The C library only reads the usage during enumeration when it is built with
+/// `INVASIVE_GET_USAGE`, as that detaches the kernel driver on Linux. On the other platforms,
+/// e.g. illumos, the device is briefly opened here to read the descriptor instead. This is only
+/// done the first time a device is enumerated, so rescans, e.g. of the hotplug monitor, don't
+/// open every device again.
+pub(super) fn fill_in_usage(
+    path: &CStr,
+    vendor_id: u16,
+    product_id: u16,
+    usage_page: u16,
+    usage: u16,
+) -> (u16, u16) {
+    if usage_page != 0 || usage != 0 || cfg!(target_os = "linux") {
+        return (usage_page, usage);
+    }
+    cached_usage((path.to_owned(), vendor_id, product_id), || {
+        read_report_descriptor(path)
+            .and_then(|descriptor| primary_usage(&descriptor))
+            .unwrap_or((usage_page, usage))
+    })
+}
+
+/// Get the usage of a device from the cache, or derive and remember it.
+///
+/// The result is remembered even if the device couldn't be opened, so a device without access
+/// keeps the usage of the C library instead of being opened on every enumeration.
+fn cached_usage(key: UsageKey, derive: impl FnOnce() -> (u16, u16)) -> (u16, u16) {
+    if let Some(&usage) = USAGES.lock().unwrap().get(&key) {
+        return usage;
+    }
+    // Not locked while the device is opened
+    let usage = derive();
+    USAGES.lock().unwrap().insert(key, usage);
+    usage
+}
+
+fn read_report_descriptor(path: &CStr) -> Option<Vec<u8>> {
+    let device = unsafe { ffi::hid_open_path(path.as_ptr()) };
+    if device.is_null() {
+        return None;
+    }
+    // Closed again when dropped
+    let device = HidDevice::from_raw(device);
+    let mut buf = vec![0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+    let len = device.get_report_descriptor(&mut buf).ok()?;
+    buf.truncate(len);
+    Some(buf)
+}
+
+fn primary_usage(descriptor: &[u8]) -> Option<(u16, u16)> {
+    ReportDescriptor::parse(descriptor).ok()?.primary_usage()
+}
+
+/// Convert the return code of the libusb function used for `name`, which is negative for errors.
+fn check(res: c_int, path: &str, name: &str) -> HidResult<c_int> {
+    match res {
+        res if res >= 0 => Ok(res),
+        LIBUSB_ERROR_ACCESS => Err(HidError::PermissionDenied {
+            path: path.to_string(),
+        }),
+        LIBUSB_ERROR_NO_DEVICE => Err(HidError::Disconnected),
+        LIBUSB_ERROR_BUSY => Err(HidError::HidApiError {
+            message: format!("{name}: the interface is claimed by another handle or driver"),
+        }),
+        LIBUSB_ERROR_NOT_SUPPORTED => Err(HidError::HidApiError {
+            message: format!("{name}: not supported on this platform"),
+        }),
+        res => Err(HidError::HidApiError {
+            message: format!("libusb error {res}"),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report_descriptor::tests::fixture_bytes;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("1-4.2:1.0"),
+            Some(UsbLocation {
+                bus: 1,
+                ports: vec![4, 2],
+                interface: 0
+            })
+        );
+        assert_eq!(
+            parse_path("3-1:1.2"),
+            Some(UsbLocation {
+                bus: 3,
+                ports: vec![1],
+                interface: 2
+            })
+        );
+        assert_eq!(parse_path("/dev/hidraw0"), None);
+        assert_eq!(parse_path("1-x:1.0"), None);
+    }
+
+    #[test]
+    fn test_protocol_requests() {
+        assert_eq!(
+            ClassRequest::set_protocol(HidProtocol::Boot),
+            ClassRequest {
+                request_type: 0x21,
+                request: 0x0b,
+                value: 0
+            }
+        );
+        assert_eq!(ClassRequest::set_protocol(HidProtocol::Report).value, 1);
+        assert_eq!(
+            ClassRequest::get_protocol(),
+            ClassRequest {
+                request_type: 0xa1,
+                request: 0x03,
+                value: 0
+            }
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_class_requests_not_supported_on_linux() {
+        // Fails before looking for the device
+        let path = CString::new("1-4:1.0").unwrap();
+        assert!(matches!(
+            set_protocol(&path, HidProtocol::Boot),
+            Err(HidError::HidApiError { message })
+                if message == "set_protocol: not supported on this platform"
+        ));
+        assert!(matches!(
+            get_protocol(&path),
+            Err(HidError::HidApiError { message })
+                if message == "get_protocol: not supported on this platform"
+        ));
+        assert!(matches!(
+            set_idle(&path, 0, 125),
+            Err(HidError::HidApiError { message })
+                if message == "set_idle: not supported on this platform"
+        ));
+        assert!(matches!(
+            get_idle(&path, 0),
+            Err(HidError::HidApiError { message })
+                if message == "get_idle: not supported on this platform"
+        ));
+    }
+
+    #[test]
+    fn test_idle_requests() {
+        // 500 ms for report 2
+        assert_eq!(
+            ClassRequest::set_idle(2, 125),
+            ClassRequest {
+                request_type: 0x21,
+                request: 0x0a,
+                value: 0x7d02
+            }
+        );
+        assert_eq!(
+            ClassRequest::get_idle(2),
+            ClassRequest {
+                request_type: 0xa1,
+                request: 0x02,
+                value: 0x0002
+            }
+        );
+    }
+
+    #[test]
+    fn test_primary_usage() {
+        // A keyboard and the consumer control interface of a receiver
+        let keyboard = fixture_bytes("046A_0011_0006_0001");
+        assert_eq!(primary_usage(&keyboard), Some((0x01, 0x06)));
+        let receiver = fixture_bytes("046D_C52F_0001_000C");
+        assert_eq!(primary_usage(&receiver), Some((0x0C, 0x01)));
+        assert_eq!(primary_usage(&[]), None);
+        assert_eq!(primary_usage(&[0x05]), None);
+    }
+
+    #[test]
+    fn test_fill_in_usage_keeps_known_usage() {
+        // Doesn't open the device when the C library already knows the usage
+        let path = CString::new("1-4:1.0").unwrap();
+        assert_eq!(
+            fill_in_usage(&path, 0x046d, 0xc52f, 0x01, 0x02),
+            (0x01, 0x02)
+        );
+    }
+
+    #[test]
+    fn test_cached_usage() {
+        let key = || (CString::new("2-1:1.0").unwrap(), 0x046d, 0xc52f);
+        assert_eq!(cached_usage(key(), || (0x01, 0x06)), (0x01, 0x06));
+        // Derived only once
+        assert_eq!(cached_usage(key(), || unreachable!()), (0x01, 0x06));
+        // Another device at the same path is derived again
+        let other = (CString::new("2-1:1.0").unwrap(), 0x046d, 0xc534);
+        assert_eq!(cached_usage(other, || (0x0c, 0x01)), (0x0c, 0x01));
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(0, "1-4:1.0", "kernel_driver_active").unwrap(), 0);
+        assert_eq!(check(1, "1-4:1.0", "kernel_driver_active").unwrap(), 1);
+        assert!(matches!(
+            check(LIBUSB_ERROR_ACCESS, "1-4:1.0", "kernel_driver_active"),
+            Err(HidError::PermissionDenied { path }) if path == "1-4:1.0"
+        ));
+        assert!(matches!(
+            check(LIBUSB_ERROR_NO_DEVICE, "1-4:1.0", "kernel_driver_active"),
+            Err(HidError::Disconnected)
+        ));
+        assert!(matches!(
+            check(LIBUSB_ERROR_BUSY, "1-4:1.0", "set_idle"),
+            Err(HidError::HidApiError { message })
+                if message == "set_idle: the interface is claimed by another handle or driver"
+        ));
+        assert!(matches!(
+            check(LIBUSB_ERROR_NOT_SUPPORTED, "1-4:1.0", "kernel_driver_active"),
+            Err(HidError::HidApiError { message }) if message == "kernel_driver_active: not supported on this platform"
+        ));
+        assert!(matches!(
+            check(-99, "1-4:1.0", "kernel_driver_active"),
+            Err(HidError::HidApiError { message }) if message == "libusb error -99"
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+//! The extra behaviour for the hidraw backend on Linux
+
+use std::ffi::{CStr, OsStr};
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+use crate::GroupId;
+
+/// Find the group of a hidraw device through sysfs.
+pub(super) fn group_id_from_hidraw(path: &CStr) -> Option<GroupId> {
+    let name = Path::new(OsStr::from_bytes(path.to_bytes())).file_name()?;
+    let hid_device =
+        fs::canonicalize(Path::new("/sys/class/hidraw").join(name).join("device")).ok()?;
+    Some(group_id_from_sysfs(&hid_device))
+}
+
+/// Check if a hidraw path refers to an existing device node.
+pub(super) fn is_device_node(path: &CStr) -> bool {
+    let path = Path::new(OsStr::from_bytes(path.to_bytes()));
+    fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_char_device())
+}
+
+/// Identify the physical device by its sysfs path, which is the USB device for USB interfaces.
+///
+/// USB devices are recognized by their `busnum` attribute. Devices on other buses have a single
+/// HID device each.
+fn group_id_from_sysfs(hid_device: &Path) -> GroupId {
+    let device = hid_device
+        .ancestors()
+        .find(|dir| dir.join("busnum").is_file())
+        .unwrap_or(hid_device);
+    GroupId::new(device.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_is_device_node() {
+        assert!(is_device_node(&CString::new("/dev/null").unwrap()));
+        assert!(!is_device_node(&CString::new("/dev/hidraw-bogus").unwrap()));
+        assert!(!is_device_node(&CString::new("/dev").unwrap()));
+    }
+
+    #[test]
+    fn test_group_id_from_sysfs() {
+        let root = std::env::temp_dir().join(format!("hidapi-sysfs-{}", std::process::id()));
+        let usb_device = root.join("usb1/1-1");
+        let keyboard = usb_device.join("1-1:1.0/0003:046D:C31C.0001");
+        let consumer = usb_device.join("1-1:1.1/0003:046D:C31C.0002");
+        let bluetooth = root.join("virtual/misc/uhid/0005:046D:B01A.0003");
+        for dir in [&keyboard, &consumer, &bluetooth] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(root.join("usb1/busnum"), "1").unwrap();
+        fs::write(usb_device.join("busnum"), "1").unwrap();
+
+        let group = group_id_from_sysfs(&keyboard);
+        assert_eq!(group, GroupId::new(usb_device.to_string_lossy()));
+        assert_eq!(group_id_from_sysfs(&consumer), group);
+        assert_eq!(
+            group_id_from_sysfs(&bluetooth),
+            GroupId::new(bluetooth.to_string_lossy())
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,7 +1,12 @@
 //! The extra beahviour for macOS
 
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr::null;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
 use super::HidDevice;
-use crate::{ffi, HidDeviceBackendBase, HidDeviceBackendMacos, HidResult};
+use crate::{ffi, BusType, HidDeviceBackendBase, HidDeviceBackendMacos, HidError, HidResult};
 
 impl HidDeviceBackendMacos for HidDevice {
     fn get_location_id(&self) -> HidResult<u32> {
@@ -34,3 +39,306 @@ impl HidDeviceBackendMacos for HidDevice {
         }
     }
 }
+
+/// Look up the location ID of a device by the registry entry ID in its path, without opening it.
+pub(super) fn location_id_from_path(path: &CStr) -> Option<u32> {
+    registry_property(path, b"LocationID\0", |value| unsafe {
+        let mut location_id: i32 = 0;
+        let found = iokit::CFGetTypeID(value) == iokit::CFNumberGetTypeID()
+            && iokit::CFNumberGetValue(
+                value,
+                iokit::K_CF_NUMBER_SINT32_TYPE,
+                &mut location_id as *mut i32 as *mut c_void,
+            ) != 0;
+        found.then_some(location_id as u32)
+    })
+}
+
+/// Fill in the bus type of a device that the C library reported as unknown.
+///
+/// The C library only recognizes a few exact transport strings, IOKit has more spellings for the
+/// same buses.
+pub(super) fn fill_in_bus_type(path: &CStr, bus_type: BusType) -> BusType {
+    if bus_type != BusType::Unknown {
+        return bus_type;
+    }
+    transport_from_path(path).map_or(bus_type, |transport| bus_type_from_transport(&transport))
+}
+
+/// Look up the `Transport` property of a device by the registry entry ID in its path.
+fn transport_from_path(path: &CStr) -> Option<String> {
+    registry_property(path, b"Transport\0", |value| unsafe {
+        if iokit::CFGetTypeID(value) != iokit::CFStringGetTypeID() {
+            return None;
+        }
+        let mut buf = [0 as c_char; 64];
+        let found = iokit::CFStringGetCString(
+            value,
+            buf.as_mut_ptr(),
+            buf.len() as isize,
+            iokit::K_CF_STRING_ENCODING_UTF8,
+        ) != 0;
+        found.then(|| CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned())
+    })
+}
+
+/// Map an IOKit transport string to the bus type.
+fn bus_type_from_transport(transport: &str) -> BusType {
+    let transport = transport.trim().to_ascii_lowercase();
+    match transport.as_str() {
+        "usb" => BusType::Usb,
+        // "Bluetooth", "BluetoothLowEnergy" and "Bluetooth Low Energy"
+        t if t.starts_with("bluetooth") => BusType::Bluetooth,
+        "i2c" => BusType::I2c,
+        "spi" => BusType::Spi,
+        _ => BusType::Unknown,
+    }
+}
+
+/// Read a property of the registry entry with the ID in a device path, without opening it.
+///
+/// `key` must be nul terminated. The value passed to `convert` is released afterwards.
+fn registry_property<T>(
+    path: &CStr,
+    key: &[u8],
+    convert: impl FnOnce(iokit::CFTypeRef) -> Option<T>,
+) -> Option<T> {
+    let entry_id: u64 = path
+        .to_str()
+        .ok()?
+        .strip_prefix("DevSrvsID:")?
+        .parse()
+        .ok()?;
+
+    unsafe {
+        // IOServiceGetMatchingService consumes the matching dictionary
+        let service = iokit::IOServiceGetMatchingService(
+            iokit::K_IO_MAIN_PORT_DEFAULT,
+            iokit::IORegistryEntryIDMatching(entry_id),
+        );
+        if service == 0 {
+            return None;
+        }
+
+        let key = iokit::CFStringCreateWithCString(
+            null(),
+            key.as_ptr() as *const c_char,
+            iokit::K_CF_STRING_ENCODING_UTF8,
+        );
+        let value = if key.is_null() {
+            null()
+        } else {
+            let value = iokit::IORegistryEntryCreateCFProperty(service, key, null(), 0);
+            iokit::CFRelease(key);
+            value
+        };
+        iokit::IOObjectRelease(service);
+        if value.is_null() {
+            return None;
+        }
+
+        let result = convert(value);
+        iokit::CFRelease(value);
+        result
+    }
+}
+
+/// IOKit notifications about HID devices that were matched or terminated, delivered on the run
+/// loop of the thread that registered them.
+pub(crate) struct DeviceNotification {
+    port: iokit::IONotificationPortRef,
+    iterators: Vec<iokit::IoObject>,
+    // Referenced by the callback until the port is destroyed
+    changed: Box<AtomicBool>,
+}
+
+impl DeviceNotification {
+    /// Register for notifications on the run loop of the current thread.
+    pub(crate) fn register() -> HidResult<Self> {
+        let port = unsafe { iokit::IONotificationPortCreate(iokit::K_IO_MAIN_PORT_DEFAULT) };
+        if port.is_null() {
+            return Err(HidError::HidApiError {
+                message: "IONotificationPortCreate failed".to_string(),
+            });
+        }
+        let mut notification = Self {
+            port,
+            iterators: Vec::with_capacity(2),
+            changed: Box::default(),
+        };
+        unsafe {
+            iokit::CFRunLoopAddSource(
+                iokit::CFRunLoopGetCurrent(),
+                iokit::IONotificationPortGetRunLoopSource(port),
+                iokit::kCFRunLoopDefaultMode,
+            );
+        }
+
+        for kind in [
+            iokit::K_IO_FIRST_MATCH_NOTIFICATION,
+            iokit::K_IO_TERMINATED_NOTIFICATION,
+        ] {
+            let mut iterator = 0;
+            // IOServiceAddMatchingNotification consumes the matching dictionary
+            let res = unsafe {
+                iokit::IOServiceAddMatchingNotification(
+                    port,
+                    kind.as_ptr() as *const c_char,
+                    iokit::IOServiceMatching(iokit::K_IO_HID_DEVICE.as_ptr() as *const c_char),
+                    device_changed,
+                    &*notification.changed as *const AtomicBool as *mut c_void,
+                    &mut iterator,
+                )
+            };
+            if res != 0 {
+                return Err(HidError::HidApiError {
+                    message: format!("IOServiceAddMatchingNotification failed: {res:#x}"),
+                });
+            }
+            notification.iterators.push(iterator);
+            // The notification is only armed once the existing devices were iterated
+            drain(iterator);
+        }
+        Ok(notification)
+    }
+
+    /// Run the run loop of the current thread until a device was matched or terminated, or
+    /// the timeout expired. Returns whether a device changed.
+    pub(crate) fn wait(&self, timeout: Duration) -> bool {
+        unsafe {
+            iokit::CFRunLoopRunInMode(iokit::kCFRunLoopDefaultMode, timeout.as_secs_f64(), 1);
+        }
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Drop for DeviceNotification {
+    fn drop(&mut self) {
+        unsafe {
+            for &iterator in &self.iterators {
+                iokit::IOObjectRelease(iterator);
+            }
+            // Also removes the run loop source
+            iokit::IONotificationPortDestroy(self.port);
+        }
+    }
+}
+
+extern "C" fn device_changed(refcon: *mut c_void, iterator: iokit::IoObject) {
+    drain(iterator);
+    let changed = unsafe { &*(refcon as *const AtomicBool) };
+    changed.store(true, Ordering::Relaxed);
+}
+
+/// Release the services of a notification iterator, which re-arms the notification.
+fn drain(iterator: iokit::IoObject) {
+    loop {
+        let service = unsafe { iokit::IOIteratorNext(iterator) };
+        if service == 0 {
+            break;
+        }
+        unsafe { iokit::IOObjectRelease(service) };
+    }
+}
+
+/// The few IOKit and CoreFoundation functions needed to read registry properties and get
+/// notified about devices
+#[allow(non_snake_case, non_upper_case_globals)]
+mod iokit {
+    use std::ffi::{c_char, c_void};
+
+    pub type CFTypeRef = *const c_void;
+    pub type CFStringRef = *const c_void;
+    pub type CFMutableDictionaryRef = *mut c_void;
+    pub type CFRunLoopRef = *mut c_void;
+    pub type CFRunLoopSourceRef = *mut c_void;
+    pub type IONotificationPortRef = *mut c_void;
+    pub type IoObject = u32;
+    pub type IOServiceMatchingCallback = extern "C" fn(refcon: *mut c_void, iterator: IoObject);
+
+    pub const K_IO_MAIN_PORT_DEFAULT: u32 = 0;
+    pub const K_IO_HID_DEVICE: &[u8] = b"IOHIDDevice\0";
+    pub const K_IO_FIRST_MATCH_NOTIFICATION: &[u8] = b"IOServiceFirstMatch\0";
+    pub const K_IO_TERMINATED_NOTIFICATION: &[u8] = b"IOServiceTerminate\0";
+    pub const K_CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
+    pub const K_CF_NUMBER_SINT32_TYPE: isize = 3;
+
+    extern "C" {
+        pub fn IORegistryEntryIDMatching(entry_id: u64) -> CFMutableDictionaryRef;
+        pub fn IOServiceGetMatchingService(
+            main_port: u32,
+            matching: CFMutableDictionaryRef,
+        ) -> IoObject;
+        pub fn IORegistryEntryCreateCFProperty(
+            entry: IoObject,
+            key: CFStringRef,
+            allocator: *const c_void,
+            options: u32,
+        ) -> CFTypeRef;
+        pub fn IOObjectRelease(object: IoObject) -> i32;
+        pub fn IOServiceMatching(name: *const c_char) -> CFMutableDictionaryRef;
+        pub fn IONotificationPortCreate(main_port: u32) -> IONotificationPortRef;
+        pub fn IONotificationPortDestroy(port: IONotificationPortRef);
+        pub fn IONotificationPortGetRunLoopSource(
+            port: IONotificationPortRef,
+        ) -> CFRunLoopSourceRef;
+        pub fn IOServiceAddMatchingNotification(
+            port: IONotificationPortRef,
+            notification_type: *const c_char,
+            matching: CFMutableDictionaryRef,
+            callback: IOServiceMatchingCallback,
+            refcon: *mut c_void,
+            notification: *mut IoObject,
+        ) -> i32;
+        pub fn IOIteratorNext(iterator: IoObject) -> IoObject;
+
+        pub static kCFRunLoopDefaultMode: CFStringRef;
+        pub fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+        pub fn CFRunLoopAddSource(rl: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+        pub fn CFRunLoopRunInMode(mode: CFStringRef, seconds: f64, return_after_source: u8) -> i32;
+
+        pub fn CFStringCreateWithCString(
+            allocator: *const c_void,
+            c_str: *const c_char,
+            encoding: u32,
+        ) -> CFStringRef;
+        pub fn CFGetTypeID(cf: CFTypeRef) -> usize;
+        pub fn CFNumberGetTypeID() -> usize;
+        pub fn CFNumberGetValue(number: CFTypeRef, the_type: isize, value: *mut c_void) -> u8;
+        pub fn CFStringGetTypeID() -> usize;
+        pub fn CFStringGetCString(
+            string: CFStringRef,
+            buffer: *mut c_char,
+            buffer_size: isize,
+            encoding: u32,
+        ) -> u8;
+        pub fn CFRelease(cf: CFTypeRef);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_type_from_transport() {
+        // Transport strings as reported by IOKit for various devices
+        for (transport, bus_type) in [
+            ("USB", BusType::Usb),
+            ("usb", BusType::Usb),
+            ("Bluetooth", BusType::Bluetooth),
+            ("BluetoothLowEnergy", BusType::Bluetooth),
+            ("Bluetooth Low Energy", BusType::Bluetooth),
+            ("I2C", BusType::I2c),
+            ("SPI", BusType::Spi),
+            ("Virtual", BusType::Unknown),
+            ("", BusType::Unknown),
+        ] {
+            assert_eq!(
+                bus_type_from_transport(transport),
+                bus_type,
+                "{transport:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,640 @@
+//! Hotplug callbacks shared by all backends.
+//!
+//! A background thread keeps a snapshot of the connected devices and rescans whenever the backend
+//! reports that something changed. The difference between two snapshots is reported to the
+//! registered callbacks, which means removal events carry the full [`DeviceInfo`] of the device.
+
+use std::ops::BitOr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, TryLockError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{DeviceInfo, HidApiBackend, HidResult};
+
+/// How often devices are rescanned on backends without change notifications
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The kind of events a hotplug callback is interested in.
+///
+/// Flags can be combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HotplugFlags(u8);
+
+impl HotplugFlags {
+    /// A device was connected
+    pub const DEVICE_ARRIVED: Self = Self(1 << 0);
+    /// A device was disconnected
+    pub const DEVICE_LEFT: Self = Self(1 << 1);
+    /// Both arrival and removal of devices
+    pub const ALL: Self = Self(Self::DEVICE_ARRIVED.0 | Self::DEVICE_LEFT.0);
+
+    /// Check if all flags in `other` are set.
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for HotplugFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The event passed to a hotplug callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotplugEventKind {
+    Arrived,
+    Left,
+}
+
+impl HotplugEventKind {
+    fn flag(self) -> HotplugFlags {
+        match self {
+            HotplugEventKind::Arrived => HotplugFlags::DEVICE_ARRIVED,
+            HotplugEventKind::Left => HotplugFlags::DEVICE_LEFT,
+        }
+    }
+}
+
+/// Identifies a registered hotplug callback, see
+/// [`HidApi::deregister_hotplug_callback`](crate::HidApi::deregister_hotplug_callback).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HotplugRegistrationId(u64);
+
+pub type HotplugCallback = Box<dyn FnMut(&DeviceInfo, HotplugEventKind) + Send>;
+
+/// A callback that can be called without holding the lock of the [`Registry`]. It is taken out
+/// when the callback is deregistered.
+struct CallbackSlot {
+    callback: Mutex<Option<HotplugCallback>>,
+    /// Set when a running callback deregisters itself, as it can't be taken out then
+    removed: AtomicBool,
+}
+
+type SharedCallback = Arc<CallbackSlot>;
+
+impl CallbackSlot {
+    fn new(callback: HotplugCallback) -> SharedCallback {
+        Arc::new(Self {
+            callback: Mutex::new(Some(callback)),
+            removed: AtomicBool::new(false),
+        })
+    }
+
+    /// Make sure the callback is not called again, returns `false` if it already was removed.
+    ///
+    /// Waits for the callback if it is running on another thread. Callbacks run on the monitor
+    /// thread, so `on_monitor_thread` means that this is called from a callback, which may be
+    /// the running one that holds the lock.
+    fn remove(&self, on_monitor_thread: bool) -> bool {
+        if self.removed.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        if on_monitor_thread {
+            return match self.callback.try_lock() {
+                Ok(mut callback) => callback.take().is_some(),
+                // The callback deregisters itself, `dispatch` skips it from now on
+                Err(TryLockError::WouldBlock) => true,
+                Err(TryLockError::Poisoned(e)) => e.into_inner().take().is_some(),
+            };
+        }
+        let mut callback = self.callback.lock().unwrap_or_else(|e| e.into_inner());
+        callback.take().is_some()
+    }
+}
+
+struct Registration {
+    id: HotplugRegistrationId,
+    vendor_id: u16,
+    product_id: u16,
+    events: HotplugFlags,
+    callback: SharedCallback,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    registrations: Vec<Registration>,
+}
+
+impl Registry {
+    fn register(
+        &mut self,
+        vendor_id: u16,
+        product_id: u16,
+        events: HotplugFlags,
+        callback: HotplugCallback,
+    ) -> HotplugRegistrationId {
+        let id = HotplugRegistrationId(self.next_id);
+        self.next_id += 1;
+        self.registrations.push(Registration {
+            id,
+            vendor_id,
+            product_id,
+            events,
+            callback: CallbackSlot::new(callback),
+        });
+        id
+    }
+
+    fn deregister(&mut self, id: HotplugRegistrationId) -> Option<SharedCallback> {
+        let index = self.registrations.iter().position(|r| r.id == id)?;
+        Some(self.registrations.remove(index).callback)
+    }
+
+    /// The callbacks that are interested in an event.
+    fn matching(&self, info: &DeviceInfo, kind: HotplugEventKind) -> Vec<SharedCallback> {
+        // Same filter semantics as `HidApi::add_devices`, 0 matches everything
+        self.registrations
+            .iter()
+            .filter(|r| {
+                r.events.contains(kind.flag())
+                    && (r.vendor_id == 0 || r.vendor_id == info.vendor_id)
+                    && (r.product_id == 0 || r.product_id == info.product_id)
+            })
+            .map(|r| r.callback.clone())
+            .collect()
+    }
+}
+
+/// Call the callbacks that are still registered.
+fn dispatch(callbacks: &[SharedCallback], info: &DeviceInfo, kind: HotplugEventKind) {
+    for slot in callbacks {
+        let mut callback = slot.callback.lock().unwrap();
+        if slot.removed.load(Ordering::SeqCst) {
+            continue;
+        }
+        if let Some(callback) = callback.as_mut() {
+            callback(info, kind);
+        }
+    }
+}
+
+#[derive(Default)]
+struct SignalState {
+    changed: bool,
+    stopped: bool,
+}
+
+/// Wakes up the monitor thread when the devices may have changed or it should stop
+#[derive(Default)]
+pub(crate) struct Signal {
+    state: Mutex<SignalState>,
+    cond: Condvar,
+}
+
+impl Signal {
+    #[cfg_attr(
+        not(all(feature = "windows-native", target_os = "windows")),
+        allow(dead_code)
+    )]
+    pub(crate) fn notify_changed(&self) {
+        self.state.lock().unwrap().changed = true;
+        self.cond.notify_all();
+    }
+
+    fn stop(&self) {
+        self.state.lock().unwrap().stopped = true;
+        self.cond.notify_all();
+    }
+
+    #[cfg_attr(
+        not(any(
+            all(
+                feature = "linux-native",
+                not(feature = "linux-native-basic-udev"),
+                target_os = "linux"
+            ),
+            target_os = "macos"
+        )),
+        allow(dead_code)
+    )]
+    fn is_stopped(&self) -> bool {
+        self.state.lock().unwrap().stopped
+    }
+
+    /// Wait for a change, treating an expired timeout as a possible change.
+    ///
+    /// Returns `false` once the monitor has been stopped.
+    fn wait(&self, timeout: Option<Duration>) -> bool {
+        let state = self.state.lock().unwrap();
+        let condition = |s: &mut SignalState| !s.changed && !s.stopped;
+        let mut state = match timeout {
+            Some(timeout) => {
+                self.cond
+                    .wait_timeout_while(state, timeout, condition)
+                    .unwrap()
+                    .0
+            }
+            None => self.cond.wait_while(state, condition).unwrap(),
+        };
+        state.changed = false;
+        !state.stopped
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(
+        feature = "linux-native",
+        not(feature = "linux-native-basic-udev"),
+        target_os = "linux"
+    ))] {
+        use crate::HotplugWatcher;
+
+        /// How long to wait for udev events before checking if the monitor has been stopped
+        const STOP_CHECK_INTERVAL_MS: i32 = 250;
+
+        struct ChangeSource {
+            signal: Arc<Signal>,
+            watcher: Option<HotplugWatcher>,
+        }
+
+        impl ChangeSource {
+            fn new(signal: Arc<Signal>) -> Self {
+                Self {
+                    signal,
+                    watcher: HotplugWatcher::new().ok(),
+                }
+            }
+
+            fn wait_for_change(&mut self) -> bool {
+                let Some(watcher) = &mut self.watcher else {
+                    return self.signal.wait(Some(POLL_INTERVAL));
+                };
+
+                while !self.signal.is_stopped() {
+                    match watcher.next_event(STOP_CHECK_INTERVAL_MS) {
+                        Ok(Some(_)) => {
+                            // A single rescan covers every event that is already queued
+                            while let Ok(Some(_)) = watcher.next_event(0) {}
+                            return true;
+                        }
+                        Ok(None) => continue,
+                        Err(_) => return self.signal.wait(Some(POLL_INTERVAL)),
+                    }
+                }
+                false
+            }
+        }
+    } else if #[cfg(all(feature = "windows-native", target_os = "windows"))] {
+        use crate::windows_native::DeviceNotification;
+
+        struct ChangeSource {
+            signal: Arc<Signal>,
+            notification: Option<DeviceNotification>,
+        }
+
+        impl ChangeSource {
+            fn new(signal: Arc<Signal>) -> Self {
+                Self {
+                    notification: DeviceNotification::register(signal.clone()).ok(),
+                    signal,
+                }
+            }
+
+            fn wait_for_change(&mut self) -> bool {
+                match self.notification {
+                    Some(_) => self.signal.wait(None),
+                    None => self.signal.wait(Some(POLL_INTERVAL)),
+                }
+            }
+        }
+    } else if #[cfg(target_os = "macos")] {
+        use crate::hidapi::DeviceNotification;
+
+        /// How long to run the run loop before checking if the monitor has been stopped
+        const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+        struct ChangeSource {
+            signal: Arc<Signal>,
+            notification: Option<DeviceNotification>,
+        }
+
+        impl ChangeSource {
+            /// Must be called on the monitor thread, whose run loop receives the notifications
+            fn new(signal: Arc<Signal>) -> Self {
+                Self {
+                    signal,
+                    notification: DeviceNotification::register().ok(),
+                }
+            }
+
+            fn wait_for_change(&mut self) -> bool {
+                let Some(notification) = &self.notification else {
+                    return self.signal.wait(Some(POLL_INTERVAL));
+                };
+
+                while !self.signal.is_stopped() {
+                    if notification.wait(STOP_CHECK_INTERVAL) {
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    } else {
+        /// The hidapi C library has no change notifications on these platforms, so rescan
+        /// periodically
+        struct ChangeSource {
+            signal: Arc<Signal>,
+        }
+
+        impl ChangeSource {
+            fn new(signal: Arc<Signal>) -> Self {
+                Self { signal }
+            }
+
+            fn wait_for_change(&mut self) -> bool {
+                self.signal.wait(Some(POLL_INTERVAL))
+            }
+        }
+    }
+}
+
+/// Runs the hotplug callbacks on a background thread
+pub(crate) struct HotplugMonitor {
+    registry: Arc<Mutex<Registry>>,
+    signal: Arc<Signal>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HotplugMonitor {
+    pub(crate) fn new() -> HidResult<Self> {
+        // Take the initial snapshot right away, so that devices connected after the
+        // registration returns are always reported.
+        let devices = HidApiBackend::get_hid_device_info_vector(0, 0)?;
+        let registry = Arc::<Mutex<Registry>>::default();
+        let signal = Arc::<Signal>::default();
+
+        let thread = thread::Builder::new()
+            .name("hidapi-hotplug".into())
+            .spawn({
+                let registry = registry.clone();
+                let signal = signal.clone();
+                move || run(devices, ChangeSource::new(signal), &registry)
+            })?;
+
+        Ok(Self {
+            registry,
+            signal,
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn register(
+        &self,
+        vendor_id: u16,
+        product_id: u16,
+        events: HotplugFlags,
+        callback: HotplugCallback,
+    ) -> HotplugRegistrationId {
+        self.registry
+            .lock()
+            .unwrap()
+            .register(vendor_id, product_id, events, callback)
+    }
+
+    /// Remove a callback. Once this returns the callback won't be called again, and it is not
+    /// running unless it is the callback that called this.
+    pub(crate) fn deregister(&self, id: HotplugRegistrationId) -> bool {
+        let callback = self.registry.lock().unwrap().deregister(id);
+        callback.is_some_and(|callback| callback.remove(self.on_monitor_thread()))
+    }
+
+    /// Check if this is called from a callback, which runs on the monitor thread.
+    fn on_monitor_thread(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| thread.thread().id() == thread::current().id())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.registry.lock().unwrap().registrations.is_empty()
+    }
+}
+
+impl Drop for HotplugMonitor {
+    fn drop(&mut self) {
+        self.signal.stop();
+        // A callback that deregisters the last callback drops the monitor on its own thread,
+        // which can't be joined. The thread ends by itself once the callback returned.
+        if self.on_monitor_thread() {
+            return;
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn same_device(a: &DeviceInfo, b: &DeviceInfo) -> bool {
+    a.path == b.path && a.usage_page == b.usage_page && a.usage == b.usage
+}
+
+fn run(mut devices: Vec<DeviceInfo>, mut source: ChangeSource, registry: &Mutex<Registry>) {
+    while source.wait_for_change() {
+        let current = match HidApiBackend::get_hid_device_info_vector(0, 0) {
+            Ok(current) => current,
+            Err(_) => continue,
+        };
+
+        let left = devices
+            .iter()
+            .filter(|d| !current.iter().any(|c| same_device(c, d)))
+            .map(|info| (info, HotplugEventKind::Left));
+        let arrived = current
+            .iter()
+            .filter(|c| !devices.iter().any(|d| same_device(c, d)))
+            .map(|info| (info, HotplugEventKind::Arrived));
+        // Callbacks run without the lock, so they don't block registering and deregistering
+        let events: Vec<_> = {
+            let registry = registry.lock().unwrap();
+            left.chain(arrived)
+                .map(|(info, kind)| (info, kind, registry.matching(info, kind)))
+                .collect()
+        };
+        for (info, kind, callbacks) in events {
+            dispatch(&callbacks, info, kind);
+        }
+
+        devices = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use crate::HidDevice;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn device_info(vendor_id: u16, product_id: u16) -> DeviceInfo {
+        HidDevice::from_mock(MockDevice::new(vendor_id, product_id))
+            .get_device_info()
+            .unwrap()
+    }
+
+    fn counting_callback(counter: &Arc<AtomicUsize>) -> HotplugCallback {
+        let counter = counter.clone();
+        Box::new(move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn test_flags() {
+        assert!(HotplugFlags::ALL.contains(HotplugFlags::DEVICE_LEFT));
+        assert!(!HotplugFlags::DEVICE_ARRIVED.contains(HotplugFlags::DEVICE_LEFT));
+        assert_eq!(
+            HotplugFlags::DEVICE_ARRIVED | HotplugFlags::DEVICE_LEFT,
+            HotplugFlags::ALL
+        );
+    }
+
+    #[test]
+    fn test_dispatch_filters() {
+        let mut registry = Registry::default();
+        let any = Arc::new(AtomicUsize::new(0));
+        let vendor = Arc::new(AtomicUsize::new(0));
+        let left = Arc::new(AtomicUsize::new(0));
+        registry.register(0, 0, HotplugFlags::ALL, counting_callback(&any));
+        registry.register(0x1234, 0, HotplugFlags::ALL, counting_callback(&vendor));
+        registry.register(0, 0, HotplugFlags::DEVICE_LEFT, counting_callback(&left));
+
+        for (info, kind) in [
+            (device_info(0x1234, 1), HotplugEventKind::Arrived),
+            (device_info(0x4321, 1), HotplugEventKind::Left),
+        ] {
+            dispatch(&registry.matching(&info, kind), &info, kind);
+        }
+
+        assert_eq!(any.load(Ordering::SeqCst), 2);
+        assert_eq!(vendor.load(Ordering::SeqCst), 1);
+        assert_eq!(left.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_deregister_stops_callback() {
+        let mut registry = Registry::default();
+        let counter = Arc::new(AtomicUsize::new(0));
+        let id = registry.register(0, 0, HotplugFlags::ALL, counting_callback(&counter));
+        let info = device_info(0x1234, 0x5678);
+
+        let arrived = registry.matching(&info, HotplugEventKind::Arrived);
+        dispatch(&arrived, &info, HotplugEventKind::Arrived);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        // Deregistered after the event was collected, but before it was dispatched
+        let left = registry.matching(&info, HotplugEventKind::Left);
+        let callback = registry.deregister(id).unwrap();
+        assert!(callback.remove(false));
+        assert!(!callback.remove(false));
+        assert!(registry.deregister(id).is_none());
+        dispatch(&left, &info, HotplugEventKind::Left);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_callback_can_register() {
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let counter = Arc::new(AtomicUsize::new(0));
+        registry.lock().unwrap().register(
+            0,
+            0,
+            HotplugFlags::ALL,
+            Box::new({
+                let registry = registry.clone();
+                let counter = counter.clone();
+                move |_, _| {
+                    let callback = counting_callback(&counter);
+                    registry
+                        .lock()
+                        .unwrap()
+                        .register(0, 0, HotplugFlags::ALL, callback);
+                }
+            }),
+        );
+
+        let info = device_info(0x1234, 0x5678);
+        let callbacks = registry
+            .lock()
+            .unwrap()
+            .matching(&info, HotplugEventKind::Arrived);
+        dispatch(&callbacks, &info, HotplugEventKind::Arrived);
+        assert_eq!(registry.lock().unwrap().registrations.len(), 2);
+    }
+
+    #[test]
+    fn test_callback_can_deregister_itself() {
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let counter = Arc::new(AtomicUsize::new(0));
+        let id = Arc::new(Mutex::new(None));
+        let callback: HotplugCallback = Box::new({
+            let registry = registry.clone();
+            let counter = counter.clone();
+            let id = id.clone();
+            move |_, _| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                // A one-shot callback, called on the thread that dispatches the events
+                let id = id.lock().unwrap().unwrap();
+                let callback = registry.lock().unwrap().deregister(id).unwrap();
+                assert!(callback.remove(true));
+            }
+        });
+        *id.lock().unwrap() = Some(registry.lock().unwrap().register(
+            0,
+            0,
+            HotplugFlags::ALL,
+            callback,
+        ));
+
+        let info = device_info(0x1234, 0x5678);
+        let callbacks = registry
+            .lock()
+            .unwrap()
+            .matching(&info, HotplugEventKind::Arrived);
+        // The second event was collected before the callback deregistered itself
+        dispatch(&callbacks, &info, HotplugEventKind::Arrived);
+        dispatch(&callbacks, &info, HotplugEventKind::Left);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(registry.lock().unwrap().registrations.is_empty());
+    }
+
+    #[test]
+    fn test_monitor_dropped_on_its_thread() {
+        let (send_monitor, receive_monitor) = std::sync::mpsc::channel::<HotplugMonitor>();
+        let (send_done, receive_done) = std::sync::mpsc::channel();
+        let thread = thread::spawn(move || {
+            // Like a callback that deregisters the last callback
+            drop(receive_monitor.recv().unwrap());
+            send_done.send(()).unwrap();
+        });
+        let monitor = HotplugMonitor {
+            registry: Arc::default(),
+            signal: Arc::default(),
+            thread: Some(thread),
+        };
+        let signal = monitor.signal.clone();
+
+        send_monitor.send(monitor).unwrap();
+        receive_done
+            .recv_timeout(Duration::from_secs(5))
+            .expect("dropping the monitor on its own thread doesn't return");
+        assert!(signal.is_stopped());
+    }
+
+    #[test]
+    fn test_signal() {
+        let signal = Signal::default();
+        assert!(signal.wait(Some(Duration::from_millis(1))));
+
+        signal.notify_changed();
+        assert!(signal.wait(None));
+
+        signal.stop();
+        assert!(!signal.wait(None));
+    }
+}
@@ -48,12 +48,24 @@
 //! - `illumos-shared-libusb`: uses statically linked `hidraw` backend on Illumos
 //! - `macos-shared-device`: enables shared access to HID devices on MacOS
 //! - `windows-native`: talks to hid.dll directly without using the `hidapi` C library
+//! - `async`: adds [`AsyncHidDevice`], which can be read from async code with any executor
+//! - `serde`: implements `Serialize` and `Deserialize` for [`DeviceInfo`] and [`BusType`]
+//! - `mock`: exposes [`test::MockDevice`], an in-memory device for testing without hardware
+//! - `stats`: adds [`HidDevice::stats`], counting the reports a device read and wrote
+//! - `usage-tables`: adds [`usage_page_name()`] and [`usage_name()`] to name usage pages and usages
 //!
 //! ## Linux backends
 //!
 //! On linux the libusb backends do not support [`DeviceInfo::usage()`] and [`DeviceInfo::usage_page()`].
 //! The hidraw backend has support for them, but it might be buggy in older kernel versions.
 //!
+//! The libusb backends detach the kernel driver (usually `usbhid`) from the interface of a
+//! device when opening it, and reattach it when the [`HidDevice`] is dropped. This needs write
+//! access to the USB device node, and makes the device unavailable to the hidraw backends and
+//! the rest of the system while it is open. [`HidDevice::kernel_driver_active`],
+//! [`HidDevice::detach_kernel_driver`] and [`HidDevice::reattach_kernel_driver`] open the device
+//! a second time to check or change this, as the C library gives no access to its libusb handle.
+//!
 //! ## MacOS Shared device access
 //!
 //! Since `hidapi` 0.12 it is possible to open MacOS devices with shared access, so that multiple
@@ -61,24 +73,63 @@
 //! an opt-in that can be enabled with the `macos-shared-device` feature flag.
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+#[cfg(feature = "async")]
+mod async_device;
+mod builder;
 mod error;
+mod feature_report;
 mod ffi;
+mod filter;
+mod hotplug;
+mod reconnecting;
+mod report_descriptor;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "stats")]
+mod stats;
+#[cfg(any(test, feature = "mock"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+pub mod test;
+#[cfg(feature = "usage-tables")]
+mod usage_tables;
+mod vid_pid;
 
 use cfg_if::cfg_if;
 use libc::wchar_t;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
 use std::fmt::Debug;
-use std::sync::Mutex;
-
-pub use error::HidError;
+use std::mem::MaybeUninit;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use hotplug::HotplugMonitor;
+
+#[cfg(feature = "async")]
+pub use async_device::AsyncHidDevice;
+pub use builder::HidApiBuilder;
+pub use error::{HidError, HidErrorKind};
+pub use feature_report::FeatureReport;
+pub use filter::DeviceFilter;
+pub use hotplug::{HotplugCallback, HotplugEventKind, HotplugFlags, HotplugRegistrationId};
+pub use reconnecting::ReconnectingDevice;
+pub use report_descriptor::{Report, ReportDescriptor, ReportType};
+#[cfg(feature = "stats")]
+pub use stats::HidStats;
+#[cfg(feature = "usage-tables")]
+pub use usage_tables::{usage_name, usage_page_name};
+pub use vid_pid::{ParseVidPidError, VidPid};
 
 cfg_if! {
     if #[cfg(all(feature = "linux-native", target_os = "linux"))] {
         //#[cfg_attr(docsrs, doc(cfg(all(feature = "linux-native", target_os = "linux"))))]
         mod linux_native;
         use linux_native::HidApiBackend;
+        #[cfg(not(feature = "linux-native-basic-udev"))]
+        pub use linux_native::{HotplugEvent, HotplugWatcher};
     } else if #[cfg(all(feature = "windows-native", target_os = "windows"))] {
         //#[cfg_attr(docsrs, doc(cfg(all(feature = "windows-native", target_os = "windows"))))]
         mod windows_native;
@@ -96,11 +147,39 @@ cfg_if! {
     if #[cfg(target_os = "windows")] {
         #[cfg_attr(docsrs, doc(cfg(target_os = "windows")))]
         mod windows;
-        use windows::GUID;
+        pub use windows::GUID;
         /// A trait with the extra methods that are available on Windows
         trait HidDeviceBackendWindows {
             /// Get the container ID for a HID device
             fn get_container_id(&self) -> HidResult<GUID>;
+
+            /// Set the number of input reports buffered by the driver
+            fn set_input_report_buffer_count(&self, _count: u32) -> HidResult<()> {
+                Err(HidError::HidApiError {
+                    message: "set_input_report_buffer_count: not supported".to_string(),
+                })
+            }
+
+            /// Get the number of input reports buffered by the driver
+            fn get_input_report_buffer_count(&self) -> HidResult<u32> {
+                Err(HidError::HidApiError {
+                    message: "get_input_report_buffer_count: not supported".to_string(),
+                })
+            }
+
+            /// Copy the preparsed data of the device
+            fn preparsed_data(&self) -> HidResult<Vec<u8>> {
+                Err(HidError::HidApiError {
+                    message: "preparsed_data: not supported".to_string(),
+                })
+            }
+
+            /// Get the polling interval of the interrupt IN endpoint of a USB device
+            fn polling_interval(&self) -> HidResult<Option<Duration>> {
+                Err(HidError::HidApiError {
+                    message: "polling_interval: not supported".to_string(),
+                })
+            }
         }
         trait HidDeviceBackend: HidDeviceBackendBase + HidDeviceBackendWindows + Send {}
         impl<T> HidDeviceBackend for T where T: HidDeviceBackendBase + HidDeviceBackendWindows + Send {}
@@ -125,10 +204,42 @@ cfg_if! {
 
 pub type HidResult<T> = Result<T, HidError>;
 pub const MAX_REPORT_DESCRIPTOR_SIZE: usize = 4096;
+/// Buffer size used when the length of a report is not known
+pub const MAX_REPORT_LEN: usize = 4096;
 
 struct ContextState {
     device_discovery: bool,
     init_state: InitState,
+    /// Number of [`ContextGuard`]s, i.e. `HidApi` instances and open devices
+    users: usize,
+    /// Deinitialize the C library once the last user is gone, see [`HidApi::shutdown`]
+    shutdown: bool,
+}
+
+impl ContextState {
+    /// Disable device discovery for the initialization, returns false if the C library has
+    /// already been initialized with it.
+    fn disable_device_discovery(&mut self) -> bool {
+        match self.init_state {
+            // Only disable device discovery before init.
+            InitState::NotInit => {
+                self.device_discovery = false;
+                true
+            }
+            InitState::Init => !self.device_discovery,
+        }
+    }
+
+    /// Drop a user, returns whether the C library has to be deinitialized now.
+    fn release(&mut self) -> bool {
+        self.users -= 1;
+        if !self.shutdown || self.users > 0 {
+            return false;
+        }
+        self.shutdown = false;
+        self.init_state = InitState::NotInit;
+        true
+    }
 }
 
 enum InitState {
@@ -140,17 +251,57 @@ enum InitState {
 static CONTEXT_STATE: Mutex<ContextState> = Mutex::new(ContextState {
     device_discovery: true,
     init_state: InitState::NotInit,
+    users: 0,
+    shutdown: false,
 });
 
+/// Keeps the C library initialized while a `HidApi` or [`HidDevice`] exists.
+struct ContextGuard(());
+
+impl ContextGuard {
+    fn new(state: &mut ContextState) -> Self {
+        state.users += 1;
+        Self(())
+    }
+
+    fn acquire() -> Self {
+        Self::new(&mut CONTEXT_STATE.lock().unwrap())
+    }
+}
+
+/// A `HidApi` with a fixed device list, for tests that don't enumerate devices
+#[cfg(test)]
+fn api_with(devices: Vec<DeviceInfo>) -> HidApi {
+    HidApi {
+        device_list: devices,
+        hotplug: None,
+        _context: ContextGuard::acquire(),
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let mut state = CONTEXT_STATE.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.release() {
+            #[cfg(hidapi)]
+            unsafe {
+                ffi::hid_exit();
+            }
+        }
+    }
+}
+
 /// `hidapi` context.
 ///
 /// The `hidapi` C library is lazily initialized when creating the first instance,
-/// and never deinitialized. Therefore, it is allowed to create multiple `HidApi`
-/// instances.
+/// and only deinitialized after [`HidApi::shutdown`]. Therefore, it is allowed to create
+/// multiple `HidApi` instances.
 ///
 /// Each instance has its own device list cache.
 pub struct HidApi {
     device_list: Vec<DeviceInfo>,
+    hotplug: Option<HotplugMonitor>,
+    _context: ContextGuard,
 }
 
 impl HidApi {
@@ -184,13 +335,37 @@ impl HidApi {
             state.init_state = InitState::Init;
         }
 
+        let context = ContextGuard::new(&mut state);
+        // Dropping the context on errors locks the state again
+        drop(state);
+
         let mut api = HidApi {
             device_list: Vec::with_capacity(8),
+            hotplug: None,
+            _context: context,
         };
         api.add_devices(0, 0)?;
         Ok(api)
     }
 
+    /// Configure a new hidapi context, see [`HidApiBuilder`].
+    pub fn builder() -> HidApiBuilder {
+        HidApiBuilder::new()
+    }
+
+    /// Deinitialize the C library once this is the last `HidApi` and all devices are closed.
+    ///
+    /// This releases the global resources of the library, like the libusb or IOKit contexts, for
+    /// test harnesses and plugins that need a deterministic teardown. Until the remaining instances
+    /// and devices are dropped, they keep working. Creating a new `HidApi` afterwards initializes
+    /// the library again.
+    ///
+    /// Without calling this, the library stays initialized until the process exits. This has no
+    /// effect with the native backends, which have no global state.
+    pub fn shutdown(self) {
+        CONTEXT_STATE.lock().unwrap().shutdown = true;
+    }
+
     /// Disable device discovery on context creation.
     ///
     /// This may be necessary on Android, where access to USB device enumeration is limited.
@@ -210,9 +385,7 @@ impl HidApi {
     pub fn disable_device_discovery() {
         let mut state = CONTEXT_STATE.lock().unwrap();
 
-        if let InitState::NotInit = state.init_state {
-            state.device_discovery = false; // Only disable device discovery before init.
-        } else if state.device_discovery {
+        if !state.disable_device_discovery() {
             core::mem::drop(state); // Make sure we don't poison the lock when panicking.
             panic!("Cannot disable device discovery after HidApi has been initialized");
         }
@@ -243,6 +416,26 @@ impl HidApi {
         Ok(())
     }
 
+    /// Refresh the devices list like [`HidApi::refresh_devices`], returning the devices that were
+    /// connected and disconnected since the list was last updated.
+    ///
+    /// Devices are compared with the equality of [`DeviceInfo`]. If the list was filtered with
+    /// [`HidApi::add_devices`] before, every device that was left out is reported as added.
+    /// The list is left unchanged if the enumeration fails.
+    pub fn refresh_devices_delta(&mut self) -> HidResult<DeviceDelta> {
+        self.refresh_devices_delta_with(|| HidApiBackend::get_hid_device_info_vector(0, 0))
+    }
+
+    fn refresh_devices_delta_with(
+        &mut self,
+        enumerate: impl FnOnce() -> HidResult<Vec<DeviceInfo>>,
+    ) -> HidResult<DeviceDelta> {
+        let devices = enumerate()?;
+        let delta = DeviceDelta::between(&self.device_list, &devices);
+        self.device_list = devices;
+        Ok(delta)
+    }
+
     /// Reset devices list. Intended to be used with the `add_devices` method.
     pub fn reset_devices(&mut self) -> HidResult<()> {
         self.device_list.clear();
@@ -257,12 +450,204 @@ impl HidApi {
         Ok(())
     }
 
+    /// Indexes the devices that match `filter`, like [`HidApi::add_devices`] with more criteria.
+    ///
+    /// With the Windows native backend this skips reading the strings and bus type of devices
+    /// whose usage does not match, which makes it a lot faster than enumerating all devices and
+    /// filtering them afterwards with [`HidApi::device_list_filtered`].
+    pub fn add_devices_filtered(&mut self, filter: DeviceFilter) -> HidResult<()> {
+        self.device_list.append(&mut enumerate_filtered(&filter)?);
+        Ok(())
+    }
+
     /// Returns iterator containing information about attached HID devices
     /// that have been indexed, either by `refresh_devices` or `add_devices`.
     pub fn device_list(&self) -> impl Iterator<Item = &DeviceInfo> {
         self.device_list.iter()
     }
 
+    /// Returns an iterator over the indexed devices that the current process can open.
+    ///
+    /// Each device is opened and closed again while iterating, which takes much longer than
+    /// listing the devices, and fails for devices that are opened exclusively elsewhere. Devices
+    /// that the system only allows to open for feature reports, like keyboards on Windows, are
+    /// included.
+    pub fn device_list_openable(&self) -> impl Iterator<Item = &DeviceInfo> {
+        filter_openable(self.device_list(), HidApi::open_path)
+    }
+
+    /// Returns a copy of the indexed devices, which can outlive the `HidApi`.
+    pub fn device_list_owned(&self) -> Vec<DeviceInfo> {
+        self.device_list.clone()
+    }
+
+    /// Returns iterator over the indexed devices that match `filter`.
+    ///
+    /// ```no_run
+    /// use hidapi::{DeviceFilter, HidApi};
+    ///
+    /// let api = HidApi::new().unwrap();
+    /// for device in api.device_list_filtered(DeviceFilter::new().usage_page(0xff00)) {
+    ///     println!("{}", device);
+    /// }
+    /// ```
+    pub fn device_list_filtered(&self, filter: DeviceFilter) -> impl Iterator<Item = &DeviceInfo> {
+        self.device_list
+            .iter()
+            .filter(move |info| filter.matches(info))
+    }
+
+    /// Open the first indexed device that matches `filter`, in the order of [`HidApi::device_list`].
+    ///
+    /// Fails with [`HidError::NotFound`] if no indexed device matches.
+    ///
+    /// ```no_run
+    /// use hidapi::{DeviceFilter, HidApi};
+    ///
+    /// let api = HidApi::new().unwrap();
+    /// let device = api.open_first(DeviceFilter::new().vendor_id(0x046d)).unwrap();
+    /// ```
+    pub fn open_first(&self, filter: DeviceFilter) -> HidResult<HidDevice> {
+        self.find_first(&filter)?.open_device()
+    }
+
+    /// Open the interface with the given number of a composite device, e.g. the vendor-defined
+    /// interface of a keyboard instead of the keyboard itself.
+    ///
+    /// Only the indexed devices are searched. Fails with [`HidError::NotFound`] if the device
+    /// has no such interface.
+    pub fn open_interface(
+        &self,
+        vid: u16,
+        pid: u16,
+        interface_number: i32,
+    ) -> HidResult<HidDevice> {
+        self.open_first(
+            DeviceFilter::new()
+                .vendor_id(vid)
+                .product_id(pid)
+                .interface_number(interface_number),
+        )
+    }
+
+    /// Open the top-level collection with the given usage page and usage, e.g. the vendor-defined
+    /// collection of a device that also is a keyboard.
+    ///
+    /// On Windows every top-level collection has its own path, so only that collection is
+    /// opened. The other backends open the interface containing the collection, whose entries in
+    /// the device list share the same path, and the device then also receives the reports of the
+    /// other collections of that interface.
+    ///
+    /// Only the indexed devices are searched. Fails with [`HidError::NotFound`] if the device
+    /// has no such collection.
+    pub fn open_usage(
+        &self,
+        vid: u16,
+        pid: u16,
+        usage_page: u16,
+        usage: u16,
+    ) -> HidResult<HidDevice> {
+        self.open_first(
+            DeviceFilter::new()
+                .vendor_id(vid)
+                .product_id(pid)
+                .usage_page(usage_page)
+                .usage(usage),
+        )
+    }
+
+    fn find_first(&self, filter: &DeviceFilter) -> HidResult<&DeviceInfo> {
+        self.device_list
+            .iter()
+            .find(|info| filter.matches(info))
+            .ok_or(HidError::NotFound)
+    }
+
+    /// Start watching for HID devices being connected or disconnected.
+    ///
+    /// Events are only reported for changes that happen after this call, so use
+    /// [`HidApi::refresh_devices`] afterwards to get the initial set of devices without a gap.
+    #[cfg(all(
+        feature = "linux-native",
+        not(feature = "linux-native-basic-udev"),
+        target_os = "linux"
+    ))]
+    pub fn watch(&self) -> HidResult<HotplugWatcher> {
+        HotplugWatcher::new()
+    }
+
+    /// Register a callback that is called whenever a matching device is connected or
+    /// disconnected.
+    ///
+    /// As with [`HidApi::add_devices`], a `vendor_id` or `product_id` of 0 matches all devices.
+    /// Devices that are already connected when the callback is registered are not reported.
+    ///
+    /// The callbacks run on a background thread. It waits for udev events with the
+    /// `linux-native` backend, for configuration manager notifications with the
+    /// `windows-native` backend and for IOKit notifications on macOS. The other backends using
+    /// the hidapi C library rescan the devices periodically instead.
+    pub fn register_hotplug_callback(
+        &mut self,
+        vendor_id: u16,
+        product_id: u16,
+        events: HotplugFlags,
+        callback: HotplugCallback,
+    ) -> HidResult<HotplugRegistrationId> {
+        let monitor = match &mut self.hotplug {
+            Some(monitor) => monitor,
+            None => self.hotplug.insert(HotplugMonitor::new()?),
+        };
+        Ok(monitor.register(vendor_id, product_id, events, callback))
+    }
+
+    /// Remove a callback registered with [`HidApi::register_hotplug_callback`].
+    ///
+    /// Once this returns, the callback is not running and will not be called again. A callback
+    /// can deregister itself, e.g. after the device it waited for arrived. It is then still
+    /// running until it returns.
+    pub fn deregister_hotplug_callback(&mut self, id: HotplugRegistrationId) -> HidResult<()> {
+        let found = self.hotplug.as_ref().is_some_and(|m| m.deregister(id));
+        if self.hotplug.as_ref().is_some_and(HotplugMonitor::is_empty) {
+            // Stop the background thread when nobody is listening anymore
+            self.hotplug = None;
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(HidError::HidApiError {
+                message: "hotplug callback is not registered".into(),
+            })
+        }
+    }
+
+    /// Open the device described by `info`, which may come from another `HidApi` instance, a
+    /// hotplug event or an older enumeration.
+    ///
+    /// If the indexed devices contain the same device, its current path is used. Otherwise this
+    /// behaves like [`DeviceInfo::open_device`], including its fallback for stale paths.
+    pub fn open_device_info(&self, info: &DeviceInfo) -> HidResult<HidDevice> {
+        self.find_indexed(info).unwrap_or(info).open_device()
+    }
+
+    /// Check if the device described by `info` is still connected, without opening it.
+    ///
+    /// This checks that the device node exists on Linux with hidraw, and that the device
+    /// interface is still listed on Windows with `windows-native`. The other backends have to
+    /// enumerate the devices, which is still cheaper than opening one.
+    pub fn is_present(&self, info: &DeviceInfo) -> bool {
+        HidApiBackend::is_present(&info.path)
+    }
+
+    fn find_indexed(&self, info: &DeviceInfo) -> Option<&DeviceInfo> {
+        if !info.path.as_bytes().is_empty() {
+            if let Some(found) = self.device_list.iter().find(|d| d.path == info.path) {
+                return Some(found);
+            }
+        }
+        self.device_list.iter().find(|d| info.is_same_device(d))
+    }
+
     /// Open a HID device using a Vendor ID (VID) and Product ID (PID).
     ///
     /// When multiple devices with the same vid and pid are available, then the
@@ -273,6 +658,30 @@ impl HidApi {
         Ok(HidDevice::from_backend(Box::new(dev)))
     }
 
+    /// Open a HID device by its [`VidPid`], see [`HidApi::open`].
+    pub fn open_vid_pid(id: VidPid) -> HidResult<HidDevice> {
+        Self::open(id.vid, id.pid)
+    }
+
+    /// Open a HID device like [`HidApi::open`], in non-blocking mode.
+    ///
+    /// Devices are opened in blocking mode otherwise, see [`HidDevice::set_blocking_mode`].
+    /// The mode is set before the device is returned, so no read can happen in blocking mode.
+    pub fn open_nonblocking(vid: u16, pid: u16) -> HidResult<HidDevice> {
+        nonblocking(Self::open(vid, pid)?)
+    }
+
+    /// Open a HID device like [`HidApi::open`], retrying while it is reconnecting.
+    ///
+    /// Devices that re-enumerate can't be opened for a short time, even though they are listed
+    /// already. Errors of a [transient](HidErrorKind::is_transient) kind are retried until
+    /// `attempts` tries are made, waiting `delay` in between, after which the last error is
+    /// returned. Other errors are returned right away, including those that the backend only
+    /// reports as a message.
+    pub fn open_retry(vid: u16, pid: u16, attempts: u32, delay: Duration) -> HidResult<HidDevice> {
+        retry(attempts, delay, || Self::open(vid, pid))
+    }
+
     /// Open a HID device using a Vendor ID (VID), Product ID (PID) and
     /// a serial number.
     pub fn open_serial(vid: u16, pid: u16, sn: &str) -> HidResult<HidDevice> {
@@ -283,11 +692,59 @@ impl HidApi {
     /// The path name be determined by inspecting the device list available with [`HidApi::device_list`].
     ///
     /// Alternatively a platform-specific path name can be used (eg: /dev/hidraw0 on Linux).
+    /// The `linux-native` backend also accepts the sysfs directory of a hidraw device, like
+    /// `/sys/class/hidraw/hidraw0`, and opens its device node.
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         let dev = HidApiBackend::open_path(device_path)?;
         Ok(HidDevice::from_backend(Box::new(dev)))
     }
 
+    /// Open a HID device by path like [`HidApi::open_path`], and check that it is the expected
+    /// device.
+    ///
+    /// Paths are reused when devices are reconnected, so a path that was stored earlier may refer
+    /// to another device by now. This fails with [`HidError::Mismatch`] if the vendor ID,
+    /// product ID or, if given, the serial number of the opened device don't match.
+    pub fn open_path_verified(
+        device_path: &CStr,
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: Option<&str>,
+    ) -> HidResult<HidDevice> {
+        verify(
+            Self::open_path(device_path)?,
+            vendor_id,
+            product_id,
+            serial_number,
+        )
+    }
+
+    /// Open a HID device by path like [`HidApi::open_path`], in non-blocking mode, see
+    /// [`HidApi::open_nonblocking`].
+    pub fn open_path_nonblocking(device_path: &CStr) -> HidResult<HidDevice> {
+        nonblocking(Self::open_path(device_path)?)
+    }
+
+    /// Open a HID device by path with the given [`AccessMode`].
+    ///
+    /// [`HidApi::open_path`] is the same as opening with [`AccessMode::ReadWrite`]. The other modes
+    /// are only supported by the `linux-native` and `windows-native` backends, the C library
+    /// always opens devices for reading and writing.
+    pub fn open_path_with(device_path: &CStr, access: AccessMode) -> HidResult<HidDevice> {
+        let dev = HidApiBackend::open_path_with(device_path, access)?;
+        Ok(HidDevice::from_backend(Box::new(dev)))
+    }
+
+    /// Open a HID device from an already opened `hidraw` file descriptor.
+    ///
+    /// This is useful in sandboxed environments like Android, where a broker process opens the
+    /// device and passes the file descriptor on. Fails if `fd` does not refer to a `hidraw` node.
+    #[cfg(all(feature = "linux-native", target_os = "linux"))]
+    pub fn open_fd(fd: std::os::fd::OwnedFd) -> HidResult<HidDevice> {
+        let dev = linux_native::HidDevice::open_fd(fd)?;
+        Ok(HidDevice::from_backend(Box::new(dev)))
+    }
+
     /// Open a HID device using libusb_wrap_sys_device.
     #[cfg(libusb)]
     pub fn wrap_sys_device(&self, sys_dev: isize, interface_num: i32) -> HidResult<HidDevice> {
@@ -319,27 +776,198 @@ impl HidApi {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Clone, PartialEq)]
-enum WcharString {
+/// A string of a device, like its serial number, as the backend provided it.
+///
+/// See [`DeviceInfo::serial_number_hid`] and the other accessors returning it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HidString {
+    /// A valid Unicode string
     String(String),
-    #[cfg_attr(all(feature = "linux-native", target_os = "linux"), allow(dead_code))]
+    /// The `wchar_t` characters of a string that is not valid Unicode
     Raw(Vec<wchar_t>),
+    /// The device doesn't have the string, or the backend couldn't read it
     None,
 }
 
-impl From<WcharString> for Option<String> {
-    fn from(val: WcharString) -> Self {
+impl HidString {
+    /// The string if it is valid Unicode.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            HidString::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The `wchar_t` characters of a string that is not valid Unicode.
+    ///
+    /// Valid strings are only available as [`HidString::as_str`].
+    pub fn as_wide(&self) -> Option<&[wchar_t]> {
+        match self {
+            HidString::Raw(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The string, with invalid characters replaced by `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// `wchar_t` strings are UTF-16 on Windows and UTF-32 on the other platforms.
+    pub fn to_string_lossy(&self) -> Option<Cow<'_, str>> {
+        match self {
+            HidString::String(s) => Some(Cow::Borrowed(s)),
+            HidString::Raw(s) => Some(Cow::Owned(wchar_to_string_lossy(s))),
+            HidString::None => None,
+        }
+    }
+}
+
+fn wchar_to_string_lossy(s: &[wchar_t]) -> String {
+    #[cfg(windows)]
+    return String::from_utf16_lossy(s);
+    #[cfg(not(windows))]
+    return s
+        .iter()
+        .map(|&c| char::from_u32(c as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+}
+
+impl From<HidString> for Option<String> {
+    fn from(val: HidString) -> Self {
         match val {
-            WcharString::String(s) => Some(s),
+            HidString::String(s) => Some(s),
             _ => None,
         }
     }
 }
 
+/// Convert a string to the `wchar_t` characters the C library uses, which are UTF-16 code units on
+/// Windows.
+fn str_to_wchar(s: &str) -> Vec<wchar_t> {
+    #[cfg(windows)]
+    return s.encode_utf16().collect();
+    #[cfg(not(windows))]
+    return s.chars().map(|c| c as wchar_t).collect();
+}
+
+/// Convert the string descriptor at index 0, read like a string, to the language IDs it lists.
+#[cfg_attr(
+    not(any(hidapi, all(feature = "windows-native", target_os = "windows"))),
+    allow(dead_code)
+)]
+fn language_ids<T: Copy>(string: &[T]) -> HidResult<Vec<u16>>
+where
+    u16: TryFrom<T>,
+{
+    string
+        .iter()
+        .map(|&c| {
+            u16::try_from(c).map_err(|_| HidError::HidApiError {
+                message: "invalid language ID in string descriptor 0".to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Initial length of the buffers for reading strings from devices, in characters
+const STRING_BUF_LEN: usize = 128;
+/// Length at which [`read_string_growing`] stops growing the buffer
+const MAX_STRING_BUF_LEN: usize = 4096;
+
+/// Read a string with `read`, which fills a buffer with a null-terminated string and truncates
+/// it if the buffer is too small, retrying with larger buffers until the whole string fits.
+#[cfg_attr(
+    not(any(hidapi, all(feature = "windows-native", target_os = "windows"))),
+    allow(dead_code)
+)]
+fn read_string_growing<T: Copy + Default + PartialEq>(
+    mut read: impl FnMut(&mut [T]) -> HidResult<()>,
+) -> HidResult<Vec<T>> {
+    let mut len = STRING_BUF_LEN;
+    loop {
+        let mut buf = vec![T::default(); len];
+        read(&mut buf)?;
+        let end = buf.iter().position(|&c| c == T::default()).unwrap_or(len);
+        // A string that fills the whole buffer may have been cut off
+        if end + 1 < len || len >= MAX_STRING_BUF_LEN {
+            buf.truncate(end);
+            return Ok(buf);
+        }
+        len *= 2;
+    }
+}
+
+/// Keep the devices that `open` succeeds for.
+fn filter_openable<'a, T>(
+    devices: impl Iterator<Item = &'a DeviceInfo>,
+    open: impl Fn(&CStr) -> HidResult<T>,
+) -> impl Iterator<Item = &'a DeviceInfo> {
+    devices.filter(move |info| open(&info.path).is_ok())
+}
+
+/// Check the identity of a newly opened device, see [`HidApi::open_path_verified`].
+fn verify(
+    device: HidDevice,
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: Option<&str>,
+) -> HidResult<HidDevice> {
+    let info = device.get_device_info()?;
+    if info.vendor_id != vendor_id
+        || info.product_id != product_id
+        || serial_number.is_some_and(|sn| info.serial_number() != Some(sn))
+    {
+        return Err(HidError::Mismatch {
+            device_info: Box::new(info),
+        });
+    }
+    Ok(device)
+}
+
+/// Switch a newly opened device to non-blocking mode.
+fn nonblocking(device: HidDevice) -> HidResult<HidDevice> {
+    device.set_blocking_mode(false)?;
+    Ok(device)
+}
+
+/// Call `f` until it succeeds, fails with a non-transient error or was called `attempts` times.
+fn retry<T>(attempts: u32, delay: Duration, mut f: impl FnMut() -> HidResult<T>) -> HidResult<T> {
+    let mut tries = 1;
+    loop {
+        match f() {
+            Err(e) if tries < attempts && e.kind().is_transient() => {
+                tries += 1;
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// How a device is opened by [`HidApi::open_path_with`].
+///
+/// Feature reports and the device strings are available in every mode. Restricting the access
+/// makes the other operations fail instead:
+///
+/// * [`ReadOnly`](AccessMode::ReadOnly) - writing output reports fails.
+/// * [`WriteOnly`](AccessMode::WriteOnly) - reading input reports fails.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AccessMode {
+    /// Open for reading and writing.
+    ///
+    /// On Windows, devices that the system takes exclusive control over, like keyboards and mice,
+    /// are opened without read or write access instead, so only feature reports work.
+    #[default]
+    ReadWrite,
+    /// Open for reading input reports only.
+    ReadOnly,
+    /// Open for writing output reports only.
+    WriteOnly,
+}
+
 /// The underlying HID bus type.
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BusType {
     Unknown = 0x00,
     Usb = 0x01,
@@ -348,26 +976,145 @@ pub enum BusType {
     Spi = 0x04,
 }
 
+impl fmt::Display for BusType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BusType::Unknown => "Unknown",
+            BusType::Usb => "USB",
+            BusType::Bluetooth => "Bluetooth",
+            BusType::I2c => "I2C",
+            BusType::Spi => "SPI",
+        })
+    }
+}
+
+impl BusType {
+    /// The value of the bus type in the C library.
+    pub const fn as_u8(self) -> u8 {
+        self as u8
+    }
+}
+
+impl TryFrom<u8> for BusType {
+    type Error = HidError;
+
+    fn try_from(value: u8) -> HidResult<Self> {
+        match value {
+            0x00 => Ok(BusType::Unknown),
+            0x01 => Ok(BusType::Usb),
+            0x02 => Ok(BusType::Bluetooth),
+            0x03 => Ok(BusType::I2c),
+            0x04 => Ok(BusType::Spi),
+            _ => Err(HidError::HidApiError {
+                message: format!("invalid bus type: {}", value),
+            }),
+        }
+    }
+}
+
+/// The protocol of a device that supports the boot interface, see [`HidDevice::set_protocol`].
+///
+/// The values are the ones of the USB Set_Protocol and Get_Protocol requests.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HidProtocol {
+    /// The fixed report format of boot keyboards and mice, which needs no report descriptor.
+    Boot = 0,
+    /// The reports described by the report descriptor. Devices start in this protocol.
+    #[default]
+    Report = 1,
+}
+
+impl TryFrom<u8> for HidProtocol {
+    type Error = HidError;
+
+    fn try_from(value: u8) -> HidResult<Self> {
+        match value {
+            0 => Ok(HidProtocol::Boot),
+            1 => Ok(HidProtocol::Report),
+            _ => Err(HidError::HidApiError {
+                message: format!("invalid HID protocol: {}", value),
+            }),
+        }
+    }
+}
+
+/// The devices that were connected and disconnected, see [`HidApi::refresh_devices_delta`].
+#[derive(Clone, Debug, Default)]
+pub struct DeviceDelta {
+    /// The devices that are new in the list
+    pub added: Vec<DeviceInfo>,
+    /// The devices that are no longer in the list
+    pub removed: Vec<DeviceInfo>,
+}
+
+impl DeviceDelta {
+    fn between(old: &[DeviceInfo], new: &[DeviceInfo]) -> Self {
+        let old_set = old.iter().collect::<HashSet<_>>();
+        let new_set = new.iter().collect::<HashSet<_>>();
+        Self {
+            added: new
+                .iter()
+                .filter(|d| !old_set.contains(d))
+                .cloned()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|d| !new_set.contains(d))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Check if no device was added or removed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Identifies the physical device that a HID interface belongs to, see [`DeviceInfo::group_id`].
+///
+/// The value is opaque and depends on the backend, so it should only be compared to the group IDs
+/// of other devices enumerated on the same system.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupId(String);
+
+impl GroupId {
+    // Not every backend can determine the group of a device
+    #[allow(dead_code)]
+    fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+}
+
 /// Device information. Use accessors to extract information about Hid devices.
 ///
 /// Note: Methods like `serial_number()` may return None, if the conversion to a
 /// String failed internally. You can however access the raw hid representation of the
-/// string by calling `serial_number_raw()`
+/// string by calling `serial_number_raw()`, or get both as a [`HidString`] from
+/// `serial_number_hid()`
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceInfo {
+    #[cfg_attr(feature = "serde", serde(with = "serialize::path"))]
     path: CString,
     vendor_id: u16,
     product_id: u16,
-    serial_number: WcharString,
+    serial_number: HidString,
     release_number: u16,
-    manufacturer_string: WcharString,
-    product_string: WcharString,
+    manufacturer_string: HidString,
+    product_string: HidString,
     #[allow(dead_code)]
     usage_page: u16,
     #[allow(dead_code)]
     usage: u16,
     interface_number: i32,
     bus_type: BusType,
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    location_id: Option<u32>,
+    bluetooth_address: Option<[u8; 6]>,
+    group_id: Option<GroupId>,
 }
 
 impl DeviceInfo {
@@ -375,6 +1122,14 @@ impl DeviceInfo {
         &self.path
     }
 
+    /// The path as a string slice, or `None` if it is not valid UTF-8.
+    ///
+    /// This borrows from the [`DeviceInfo`] without allocating. The paths of all backends are
+    /// ASCII in practice, e.g. `/dev/hidraw0` on Linux or the device interface path on Windows.
+    pub fn path_str(&self) -> Option<&str> {
+        self.path.to_str().ok()
+    }
+
     pub fn vendor_id(&self) -> u16 {
         self.vendor_id
     }
@@ -386,18 +1141,27 @@ impl DeviceInfo {
     /// Try to call `serial_number_raw()`, if None is returned.
     pub fn serial_number(&self) -> Option<&str> {
         match self.serial_number {
-            WcharString::String(ref s) => Some(s),
+            HidString::String(ref s) => Some(s),
             _ => None,
         }
     }
 
     pub fn serial_number_raw(&self) -> Option<&[wchar_t]> {
         match self.serial_number {
-            WcharString::Raw(ref s) => Some(s),
+            HidString::Raw(ref s) => Some(s),
             _ => None,
         }
     }
 
+    /// The serial number whether or not it is valid Unicode, see [`HidString`].
+    pub fn serial_number_hid(&self) -> &HidString {
+        &self.serial_number
+    }
+
+    /// The device release number in binary-coded decimal, `bcdDevice` for USB devices.
+    ///
+    /// This is 0 if the backend can not find out the release number, e.g. for Bluetooth, I2C and
+    /// SPI devices on Linux that don't have an input device.
     pub fn release_number(&self) -> u16 {
         self.release_number
     }
@@ -405,33 +1169,43 @@ impl DeviceInfo {
     /// Try to call `manufacturer_string_raw()`, if None is returned.
     pub fn manufacturer_string(&self) -> Option<&str> {
         match self.manufacturer_string {
-            WcharString::String(ref s) => Some(s),
+            HidString::String(ref s) => Some(s),
             _ => None,
         }
     }
 
     pub fn manufacturer_string_raw(&self) -> Option<&[wchar_t]> {
         match self.manufacturer_string {
-            WcharString::Raw(ref s) => Some(s),
+            HidString::Raw(ref s) => Some(s),
             _ => None,
         }
     }
 
+    /// The manufacturer string whether or not it is valid Unicode, see [`HidString`].
+    pub fn manufacturer_string_hid(&self) -> &HidString {
+        &self.manufacturer_string
+    }
+
     /// Try to call `product_string_raw()`, if None is returned.
     pub fn product_string(&self) -> Option<&str> {
         match self.product_string {
-            WcharString::String(ref s) => Some(s),
+            HidString::String(ref s) => Some(s),
             _ => None,
         }
     }
 
     pub fn product_string_raw(&self) -> Option<&[wchar_t]> {
         match self.product_string {
-            WcharString::Raw(ref s) => Some(s),
+            HidString::Raw(ref s) => Some(s),
             _ => None,
         }
     }
 
+    /// The product string whether or not it is valid Unicode, see [`HidString`].
+    pub fn product_string_hid(&self) -> &HidString {
+        &self.product_string
+    }
+
     /// Usage page is not available on linux libusb backends
     #[cfg(not(all(libusb, target_os = "linux")))]
     pub fn usage_page(&self) -> u16 {
@@ -444,6 +1218,15 @@ impl DeviceInfo {
         self.usage
     }
 
+    /// Get the names of the usage page and usage of the device, if they are known.
+    #[cfg(all(feature = "usage-tables", not(all(libusb, target_os = "linux"))))]
+    pub fn usage_names(&self) -> (Option<&'static str>, Option<&'static str>) {
+        (
+            usage_page_name(self.usage_page),
+            usage_name(self.usage_page, self.usage),
+        )
+    }
+
     pub fn interface_number(&self) -> i32 {
         self.interface_number
     }
@@ -452,6 +1235,39 @@ impl DeviceInfo {
         self.bus_type
     }
 
+    /// Get the address of a Bluetooth device.
+    ///
+    /// Uses the address reported by the backend, or parses the serial number, which contains the
+    /// address for Bluetooth devices on most platforms. Returns `None` if the device is not
+    /// connected through Bluetooth or the address is not available.
+    pub fn bluetooth_address(&self) -> Option<[u8; 6]> {
+        if !matches!(self.bus_type, BusType::Bluetooth) {
+            return None;
+        }
+        self.bluetooth_address
+            .or_else(|| self.serial_number().and_then(parse_bluetooth_address))
+    }
+
+    /// Get an identifier shared by all interfaces of the same physical device.
+    ///
+    /// A composite device, e.g. a keyboard with a separate interface for its media keys, is listed
+    /// once per interface. Entries with the same group ID belong to the same physical device, so
+    /// they can be shown as a single logical device. Returns `None` if the backend could not
+    /// determine the group.
+    pub fn group_id(&self) -> Option<&GroupId> {
+        self.group_id.as_ref()
+    }
+
+    /// Get the location ID of the device.
+    ///
+    /// The location ID describes where the device is plugged in, so it can be used to find a
+    /// previously seen device again without opening every candidate. Returns `None` if the
+    /// location ID could not be read during enumeration.
+    #[cfg(target_os = "macos")]
+    pub fn location_id(&self) -> Option<u32> {
+        self.location_id
+    }
+
     /// Use the information contained in `DeviceInfo` to open
     /// and return a handle to a [HidDevice](struct.HidDevice.html).
     ///
@@ -461,9 +1277,17 @@ impl DeviceInfo {
     /// fail with [HidError::OpenHidDeviceWithDeviceInfoError](enum.HidError.html#variant.OpenHidDeviceWithDeviceInfoError).
     ///
     /// Note, that opening a device could still be done using [HidApi::open()](struct.HidApi.html#method.open) directly.
+    ///
+    /// If the path no longer refers to a device, e.g. because it was reconnected since the
+    /// `DeviceInfo` was enumerated, the devices are enumerated again and the one with the same
+    /// vid, pid, serial number, interface and usage is opened instead. This fails with
+    /// [`HidError::DevicePathGone`] if the device has no serial number to identify it by, and with
+    /// [`HidError::NoMatchingDevice`] if no such device is connected anymore.
     pub fn open_device(&self) -> HidResult<HidDevice> {
         if !self.path.as_bytes().is_empty() {
-            HidApi::open_path(self.path.as_c_str())
+            open_with_fallback(self, HidApi::open_path, || {
+                HidApiBackend::get_hid_device_info_vector(self.vendor_id, self.product_id)
+            })
         } else if let Some(sn) = self.serial_number() {
             HidApi::open_serial(self.vendor_id, self.product_id, sn)
         } else {
@@ -474,6 +1298,111 @@ impl DeviceInfo {
     }
 }
 
+impl DeviceInfo {
+    fn has_serial_number(&self) -> bool {
+        self.serial_number().is_some_and(|sn| !sn.is_empty())
+    }
+
+    /// Check if `other` describes the same interface of the same physical device, possibly
+    /// under a different path.
+    fn is_same_device(&self, other: &DeviceInfo) -> bool {
+        self.has_serial_number()
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.serial_number == other.serial_number
+            && self.interface_number == other.interface_number
+            && self.usage_page == other.usage_page
+            && self.usage == other.usage
+    }
+
+    /// Check if `other` could be this device after it was reconnected: it has the same path or
+    /// serial number, or the same interface in the same group, i.e. plugged into the same port.
+    fn is_reconnected_device(&self, other: &DeviceInfo) -> bool {
+        let same_group = self.group_id.is_some()
+            && self.group_id == other.group_id
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.interface_number == other.interface_number
+            && self.usage_page == other.usage_page
+            && self.usage == other.usage;
+        self.path == other.path || self.is_same_device(other) || same_group
+    }
+}
+
+/// Open the path of `info`, looking the device up again in the output of `enumerate` if the
+/// path does not refer to a device anymore.
+fn open_with_fallback(
+    info: &DeviceInfo,
+    open_path: impl Fn(&CStr) -> HidResult<HidDevice>,
+    enumerate: impl FnOnce() -> HidResult<Vec<DeviceInfo>>,
+) -> HidResult<HidDevice> {
+    let error = match open_path(&info.path) {
+        Ok(device) => return Ok(device),
+        Err(e) => e,
+    };
+
+    let devices = enumerate()?;
+    if devices.iter().any(|d| d.path == info.path) {
+        // The path is still valid, so the device could not be opened for another reason
+        return Err(error);
+    }
+    if !info.has_serial_number() {
+        return Err(HidError::DevicePathGone {
+            device_info: Box::new(info.clone()),
+        });
+    }
+    match devices.iter().find(|d| info.is_same_device(d)) {
+        Some(device) => open_path(&device.path),
+        None => Err(HidError::NoMatchingDevice {
+            device_info: Box::new(info.clone()),
+        }),
+    }
+}
+
+/// Formats the device as `vid:pid manufacturer product (bus) path`, leaving out strings that are
+/// not available.
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vendor_id, self.product_id)?;
+        for s in [self.manufacturer_string(), self.product_string()]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+        {
+            write!(f, " {s}")?;
+        }
+        write!(f, " ({}) {}", self.bus_type, self.path.to_string_lossy())
+    }
+}
+
+/// Two [`DeviceInfo`]s are equal if they describe the same interface of the same device, so this
+/// only compares the path, vendor and product ID, serial number, interface number and usage.
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && self.vendor_id == other.vendor_id
+            && self.product_id == other.product_id
+            && self.serial_number == other.serial_number
+            && self.interface_number == other.interface_number
+            && self.usage_page == other.usage_page
+            && self.usage == other.usage
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl std::hash::Hash for DeviceInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        self.vendor_id.hash(state);
+        self.product_id.hash(state);
+        self.serial_number.hash(state);
+        self.interface_number.hash(state);
+        self.usage_page.hash(state);
+        self.usage.hash(state);
+    }
+}
+
 impl fmt::Debug for DeviceInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HidDeviceInfo")
@@ -490,16 +1419,67 @@ trait HidDeviceBackendBase: Send + Sync + 'static {
     fn write(&self, data: &[u8]) -> HidResult<usize>;
     fn read(&self, buf: &mut [u8]) -> HidResult<usize>;
     fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize>;
-    fn send_feature_report(&self, data: &[u8]) -> HidResult<()>;
-    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize>;
-    fn send_output_report(&self, data: &[u8]) -> HidResult<()>;
-    #[cfg(any(hidapi, target_os = "linux"))]
-    fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize>;
-    fn set_blocking_mode(&self, blocking: bool) -> HidResult<()>;
+
+    /// Like `read_timeout`, but fails with [`HidError::Timeout`] if no report arrived.
+    ///
+    /// Backends that can't tell a timeout from an empty report treat both as a timeout.
+    fn read_timeout_strict(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        match self.read_timeout(buf, timeout)? {
+            0 => Err(HidError::Timeout),
+            len => Ok(len),
+        }
+    }
+
+    /// Like `read_timeout`, but into a buffer that may be uninitialized.
+    ///
+    /// Returns the number of bytes at the start of `buf` that were initialized with the report.
+    /// Backends that can't read into uninitialized memory zero the buffer first.
+    fn read_timeout_uninit(&self, buf: &mut [MaybeUninit<u8>], timeout: i32) -> HidResult<usize> {
+        buf.fill(MaybeUninit::new(0));
+        // SAFETY: Every byte of the buffer was just initialized
+        let buf = unsafe { &mut *(buf as *mut [MaybeUninit<u8>] as *mut [u8]) };
+        self.read_timeout(buf, timeout)
+    }
+
+    fn write_timeout(&self, data: &[u8], _timeout: Option<Duration>) -> HidResult<usize> {
+        self.write(data)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> HidResult<()>;
+    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize>;
+
+    fn get_feature_report_timeout(
+        &self,
+        buf: &mut [u8],
+        _timeout: Option<Duration>,
+    ) -> HidResult<usize> {
+        self.get_feature_report(buf)
+    }
+
+    fn send_output_report(&self, data: &[u8]) -> HidResult<()>;
+    #[cfg(any(hidapi, target_os = "linux"))]
+    fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize>;
+    fn set_blocking_mode(&self, blocking: bool) -> HidResult<()>;
     fn get_device_info(&self) -> HidResult<DeviceInfo>;
     fn get_manufacturer_string(&self) -> HidResult<Option<String>>;
     fn get_product_string(&self) -> HidResult<Option<String>>;
     fn get_serial_number_string(&self) -> HidResult<Option<String>>;
+
+    fn get_manufacturer_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        Ok(self.get_manufacturer_string()?.as_deref().map(str_to_wchar))
+    }
+
+    fn get_product_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        Ok(self.get_product_string()?.as_deref().map(str_to_wchar))
+    }
+
+    fn get_serial_number_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        Ok(self
+            .get_serial_number_string()?
+            .as_deref()
+            .map(str_to_wchar))
+    }
+
     fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize>;
 
     fn get_indexed_string(&self, _index: i32) -> HidResult<Option<String>> {
@@ -507,11 +1487,212 @@ trait HidDeviceBackendBase: Send + Sync + 'static {
             message: "get_indexed_string: not supported".to_string(),
         })
     }
-    fn close(&self) -> HidResult<()>;
+
+    fn get_indexed_string_lang(&self, _index: i32, _langid: u16) -> HidResult<Option<String>> {
+        Err(HidError::HidApiError {
+            message: "get_indexed_string_lang: not supported".to_string(),
+        })
+    }
+
+    fn supported_languages(&self) -> HidResult<Vec<u16>> {
+        Err(HidError::HidApiError {
+            message: "supported_languages: not supported".to_string(),
+        })
+    }
+
+    fn input_report_len(&self) -> HidResult<Option<usize>> {
+        report_len_from_descriptor(self, ReportType::Input)
+    }
+
+    fn output_report_len(&self) -> HidResult<Option<usize>> {
+        report_len_from_descriptor(self, ReportType::Output)
+    }
+
+    fn feature_report_len(&self) -> HidResult<Option<usize>> {
+        report_len_from_descriptor(self, ReportType::Feature)
+    }
+
+    fn refresh_strings(&self) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "refresh_strings: not supported".to_string(),
+        })
+    }
+
+    fn refresh_device_info(&self) -> HidResult<DeviceInfo> {
+        self.refresh_strings()?;
+        self.get_device_info()
+    }
+
+    fn kernel_driver_active(&self) -> HidResult<bool> {
+        Err(HidError::HidApiError {
+            message: "kernel_driver_active: not supported".to_string(),
+        })
+    }
+
+    fn detach_kernel_driver(&self) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "detach_kernel_driver: not supported".to_string(),
+        })
+    }
+
+    fn reattach_kernel_driver(&self) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "reattach_kernel_driver: not supported".to_string(),
+        })
+    }
+
+    /// Release the device early, reporting errors that dropping it would ignore.
+    ///
+    /// Only called once, the backend is dropped right after it.
+    fn close(&self) -> HidResult<()> {
+        Ok(())
+    }
+
+    fn clear_input_buffer(&self) -> HidResult<()> {
+        let mut buf = [0u8; MAX_REPORT_LEN];
+        while self.read_timeout(&mut buf, 0)? > 0 {}
+        Ok(())
+    }
+
+    /// The file descriptor used to access the device, if the backend has one
+    #[cfg(unix)]
+    fn raw_fd(&self) -> Option<std::os::fd::RawFd> {
+        None
+    }
+
+    /// The error of the last failed operation, `None` if the backend doesn't keep track of it
+    fn last_error(&self) -> Option<HidError> {
+        None
+    }
+
+    fn set_protocol(&self, _protocol: HidProtocol) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "set_protocol: not supported".to_string(),
+        })
+    }
+
+    fn get_protocol(&self) -> HidResult<HidProtocol> {
+        Err(HidError::HidApiError {
+            message: "get_protocol: not supported".to_string(),
+        })
+    }
+
+    fn set_idle(&self, _report_id: u8, _rate: u8) -> HidResult<()> {
+        Err(HidError::HidApiError {
+            message: "set_idle: not supported".to_string(),
+        })
+    }
+
+    fn get_idle(&self, _report_id: u8) -> HidResult<u8> {
+        Err(HidError::HidApiError {
+            message: "get_idle: not supported".to_string(),
+        })
+    }
 }
 
+#[cfg(all(feature = "windows-native", target_os = "windows"))]
+fn enumerate_filtered(filter: &DeviceFilter) -> HidResult<Vec<DeviceInfo>> {
+    HidApiBackend::get_hid_device_info_vector_filtered(filter)
+}
+
+/// Enumerate the devices with the vendor and product ID of the filter, and check the rest of it
+/// afterwards.
+#[cfg(not(all(feature = "windows-native", target_os = "windows")))]
+fn enumerate_filtered(filter: &DeviceFilter) -> HidResult<Vec<DeviceInfo>> {
+    let (vid, pid) = filter.vid_pid();
+    let mut devices = HidApiBackend::get_hid_device_info_vector(vid, pid)?;
+    devices.retain(|info| filter.matches(info));
+    Ok(devices)
+}
+
+/// Derive a report length from the report descriptor of a device, for backends
+/// which have no cheaper way of getting it.
+fn report_len_from_descriptor<B: HidDeviceBackendBase + ?Sized>(
+    backend: &B,
+    report_type: ReportType,
+) -> HidResult<Option<usize>> {
+    let mut buf = [0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+    let len = match backend.get_report_descriptor(&mut buf) {
+        Ok(len) => len,
+        // Not every backend can provide the report descriptor
+        Err(HidError::ReportDescriptorUnavailable) => return Ok(None),
+        Err(HidError::HidApiError { message }) if message.ends_with("not supported") => {
+            return Ok(None)
+        }
+        Err(e) => return Err(e),
+    };
+    let descriptor = ReportDescriptor::parse(&buf[..len])?;
+    Ok(Some(descriptor.max_report_len(report_type)))
+}
+
+/// Parse a Bluetooth device address in either `01:23:45:67:89:ab` or `0123456789ab` format
+fn parse_bluetooth_address(s: &str) -> Option<[u8; 6]> {
+    let octets: Vec<&str> = if s.contains(':') {
+        s.split(':').collect()
+    } else {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| s.get(i..i + 2))
+            .collect::<Option<_>>()?
+    };
+    if octets.len() != 6 {
+        return None;
+    }
+
+    let mut address = [0u8; 6];
+    for (byte, octet) in address.iter_mut().zip(octets) {
+        if octet.len() != 2 || !octet.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        *byte = u8::from_str_radix(octet, 16).ok()?;
+    }
+    Some(address)
+}
+
+/// Convert a timeout to the milliseconds used by the backends, where -1 means forever
+fn timeout_millis(timeout: Option<Duration>) -> i32 {
+    match timeout {
+        None => -1,
+        Some(timeout) => {
+            let millis = timeout.as_nanos().div_ceil(1_000_000);
+            i32::try_from(millis).unwrap_or(i32::MAX)
+        }
+    }
+}
+
+/// The unit of the idle rate of the Set_Idle and Get_Idle requests
+const IDLE_RATE_UNIT_MS: u128 = 4;
+
+/// Convert an idle duration to the idle rate of the Set_Idle request, rounded to the nearest
+/// multiple of 4 ms. Zero stays zero, other durations are rounded up to at least 4 ms.
+fn idle_rate(duration: Duration) -> HidResult<u8> {
+    let nanos = duration.as_nanos();
+    let unit = IDLE_RATE_UNIT_MS * 1_000_000;
+    let rate = match (nanos + unit / 2) / unit {
+        0 if nanos > 0 => 1,
+        rate => rate,
+    };
+    u8::try_from(rate).map_err(|_| HidError::HidApiError {
+        message: format!("idle duration {:?} is longer than 1020 ms", duration),
+    })
+}
+
+/// Convert an idle rate of the Get_Idle request to a duration
+fn idle_duration(rate: u8) -> Duration {
+    Duration::from_millis(u64::from(rate) * IDLE_RATE_UNIT_MS as u64)
+}
+
+/// Object for accessing a HID device.
+///
+/// A `HidDevice` is `Send` and `Sync`, so it can be shared between threads, e.g. with one thread
+/// reading input reports while another writes output reports. The backends keep separate state
+/// for reading, writing and feature reports, so a blocking read does not hold up a concurrent
+/// write. Concurrent calls of the same kind are serialized.
 pub struct HidDevice {
     inner: Box<dyn HidDeviceBackend>,
+    #[cfg(feature = "stats")]
+    stats: stats::Counters,
+    _context: ContextGuard,
 }
 
 impl Debug for HidDevice {
@@ -522,7 +1703,34 @@ impl Debug for HidDevice {
 
 impl HidDevice {
     fn from_backend(inner: Box<dyn HidDeviceBackend>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            #[cfg(feature = "stats")]
+            stats: stats::Counters::default(),
+            _context: ContextGuard::acquire(),
+        }
+    }
+
+    /// Count a read for [`HidDevice::stats`].
+    #[inline]
+    fn count_read(&self, res: HidResult<usize>) -> HidResult<usize> {
+        #[cfg(feature = "stats")]
+        self.stats.count_read(&res);
+        res
+    }
+
+    /// Count a write for [`HidDevice::stats`].
+    #[inline]
+    fn count_write<T>(&self, res: HidResult<T>) -> HidResult<T> {
+        #[cfg(feature = "stats")]
+        self.stats.count_write(&res);
+        res
+    }
+
+    fn report_descriptor(&self) -> HidResult<ReportDescriptor> {
+        let mut buf = [0u8; MAX_REPORT_DESCRIPTOR_SIZE];
+        let len = self.get_report_descriptor(&mut buf)?;
+        ReportDescriptor::parse(&buf[..len])
     }
 }
 
@@ -556,9 +1764,93 @@ impl HidDevice {
     /// one exists. If it does not, it will send the data through
     /// the Control Endpoint (Endpoint 0).
     ///
+    /// On Windows, writes are padded to the length of the longest Output report of the device.
+    /// With `windows-native`, `data` that is longer than that fails with
+    /// [`HidError::IncompleteSendError`] instead of being truncated. The same applies to
+    /// [`HidDevice::send_output_report`] and, with the longest Feature report, to
+    /// [`HidDevice::send_feature_report`].
+    ///
     /// If successful, returns the actual number of bytes written.
     pub fn write(&self, data: &[u8]) -> HidResult<usize> {
-        self.inner.write(data)
+        self.count_write(self.inner.write(data))
+    }
+
+    /// Write an Output report without any data to a device that doesn't use numbered reports.
+    ///
+    /// [`write()`](Self::write) rejects an empty slice with [`HidError::InvalidZeroSizeData`],
+    /// as every report needs at least the report ID byte. This writes only the `0x00` report ID
+    /// of unnumbered reports, which Linux and macOS send as a report without data. It is only
+    /// valid for devices that declare an Output report of length zero, e.g. to trigger an action.
+    ///
+    /// Windows pads every write to the length of the longest Output report, so there the
+    /// device receives a report of zeros instead.
+    pub fn write_empty(&self) -> HidResult<usize> {
+        self.write(&[0x00])
+    }
+
+    /// The same as [`write()`](Self::write), but waits at most `timeout` for the write to
+    /// complete, or forever if it is `None`.
+    ///
+    /// Only the `windows-native` backend supports a timeout, where a write that does not complete
+    /// in time is cancelled and fails with an error of kind [`HidErrorKind::Timeout`]. Plain
+    /// [`write()`](Self::write) waits for one second there. The other backends ignore the
+    /// timeout and rely on the timeout of the operating system.
+    pub fn write_timeout(&self, data: &[u8], timeout: Option<Duration>) -> HidResult<usize> {
+        self.count_write(self.inner.write_timeout(data, timeout))
+    }
+
+    /// Write an Output report without having to know how the device frames its reports.
+    ///
+    /// Builds the buffer for [`write()`](Self::write) from the report descriptor: `report_id`
+    /// must be `Some` exactly if the device uses numbered reports, otherwise a leading `0x0` is
+    /// inserted. `data` must not be longer than the declared size of the report and is padded
+    /// with zeros to that size.
+    ///
+    /// Returns the number of bytes written, including the report ID byte.
+    pub fn write_report(&self, report_id: Option<u8>, data: &[u8]) -> HidResult<usize> {
+        let buf = self
+            .report_descriptor()?
+            .frame_report(ReportType::Output, report_id, data)?;
+        self.write(&buf)
+    }
+
+    /// Write a payload that is larger than a single Output report, e.g. a firmware image.
+    ///
+    /// `payload` is split into chunks that fit into the longest Output report of the device, see
+    /// [`output_report_len()`](Self::output_report_len). Each chunk is written with `report_id`
+    /// in front of it, the last one is padded with zeros to the full report length.
+    ///
+    /// Returns the number of payload bytes written, which is always the length of `payload`.
+    /// Writing stops at the first report that is not sent completely, which fails with
+    /// [`HidError::IncompleteSendError`] counting the payload bytes sent before it.
+    pub fn write_chunked(&self, report_id: u8, payload: &[u8]) -> HidResult<usize> {
+        if payload.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        let report_len = match self.output_report_len()? {
+            Some(len) if len > 1 => len,
+            _ => {
+                return Err(HidError::HidApiError {
+                    message: "write_chunked: the output report length is not known".to_string(),
+                })
+            }
+        };
+
+        let mut frame = vec![0u8; report_len];
+        let mut sent = 0;
+        for chunk in payload.chunks(report_len - 1) {
+            frame[0] = report_id;
+            frame[1..=chunk.len()].copy_from_slice(chunk);
+            frame[chunk.len() + 1..].fill(0);
+            if self.write(&frame)? < report_len {
+                return Err(HidError::IncompleteSendError {
+                    sent,
+                    all: payload.len(),
+                });
+            }
+            sent += chunk.len();
+        }
+        Ok(sent)
     }
 
     /// Read an Input report from a HID device.
@@ -567,9 +1859,50 @@ impl HidDevice {
     /// endpoint. The first byte will contain the Report number if the device
     /// uses numbered reports.
     ///
+    /// The framing is the same with every backend. Reports of devices without numbered reports
+    /// start right with the data: Windows puts a `0x00` report ID in front of them, which the
+    /// Windows backends remove, while hidraw, libusb and macOS never add one. So a report whose
+    /// data starts with `0x00` is returned unchanged everywhere. Use
+    /// [`HidDevice::read_report_timeout`] to get the report ID separately for both kinds of
+    /// devices.
+    ///
     /// If successful, returns the actual number of bytes read.
     pub fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
-        self.inner.read(buf)
+        self.count_read(self.inner.read(buf))
+    }
+
+    /// Read an Input report from a HID device into a newly allocated buffer.
+    ///
+    /// Same as [`HidDevice::read`], with the returned `Vec` truncated to the number of bytes
+    /// read. Reports longer than `max_len` are truncated.
+    pub fn read_vec(&self, max_len: usize) -> HidResult<Vec<u8>> {
+        let mut buf = vec![0u8; max_len];
+        let len = self.read(&mut buf)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Read an Input report from a HID device with timeout into a newly allocated buffer.
+    ///
+    /// Same as [`HidDevice::read_timeout`], with the returned `Vec` truncated to the number of
+    /// bytes read. An empty `Vec` is returned if the timeout expired.
+    pub fn read_timeout_vec(&self, max_len: usize, timeout: i32) -> HidResult<Vec<u8>> {
+        let mut buf = vec![0u8; max_len];
+        let len = self.read_timeout(&mut buf, timeout)?;
+        buf.truncate(len);
+        Ok(buf)
+    }
+
+    /// Read an Input report from a HID device, sized to fit the longest input report.
+    ///
+    /// Uses [`HidDevice::input_report_len`] as buffer size, falling back to
+    /// [`MAX_REPORT_LEN`] if the length is unknown.
+    pub fn read_report(&self) -> HidResult<Vec<u8>> {
+        let max_len = self
+            .input_report_len()?
+            .filter(|&len| len > 0)
+            .unwrap_or(MAX_REPORT_LEN);
+        self.read_vec(max_len)
     }
 
     /// Read an Input report from a HID device with timeout.
@@ -579,9 +1912,162 @@ impl HidDevice {
     /// uses numbered reports. Timeout measured in milliseconds, set -1 for
     /// blocking wait.
     ///
-    /// If successful, returns the actual number of bytes read.
+    /// If successful, returns the actual number of bytes read, or 0 if the
+    /// timeout expired. Use [`HidDevice::read_timeout_strict`] to tell this
+    /// apart from a report without data.
     pub fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
-        self.inner.read_timeout(buf, timeout)
+        let timeout = u64::try_from(timeout).ok().map(Duration::from_millis);
+        self.read_timeout_dur(buf, timeout)
+    }
+
+    /// Read an Input report from a HID device with timeout, failing with [`HidError::Timeout`]
+    /// if the timeout expired.
+    ///
+    /// [`HidDevice::read_timeout`] returns `Ok(0)` both when the timeout expired and when the
+    /// device sent a report without any data. This tells them apart, so `Ok(0)` is only returned
+    /// for a zero-length report. The hidapi C library and the Windows native backend can't tell
+    /// them apart either, with these an empty report is reported as a timeout.
+    pub fn read_timeout_strict(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        self.count_read(self.inner.read_timeout_strict(buf, timeout))
+    }
+
+    /// Read an Input report from a HID device with timeout.
+    ///
+    /// Same as [`HidDevice::read_timeout`], but `None` waits forever and `Some(Duration::ZERO)`
+    /// only checks for a report that is already available. The timeout is rounded up to whole
+    /// milliseconds and saturates at `i32::MAX` milliseconds.
+    ///
+    /// If successful, returns the actual number of bytes read.
+    pub fn read_timeout_dur(&self, buf: &mut [u8], timeout: Option<Duration>) -> HidResult<usize> {
+        self.count_read(self.inner.read_timeout(buf, timeout_millis(timeout)))
+    }
+
+    /// Read an Input report into a buffer that doesn't have to be initialized.
+    ///
+    /// Same as [`HidDevice::read_timeout_dur`], but saves zeroing the buffer in loops that read
+    /// many reports. Returns the part of `buf` that holds the report, which is empty if the
+    /// timeout expired. The mock backend still zeroes the buffer before reading into it.
+    pub fn read_uninit<'a>(
+        &self,
+        buf: &'a mut [MaybeUninit<u8>],
+        timeout: Option<Duration>,
+    ) -> HidResult<&'a mut [u8]> {
+        let len = self.count_read(self.inner.read_timeout_uninit(buf, timeout_millis(timeout)))?;
+        let len = len.min(buf.len());
+        let report = &mut buf[..len];
+        // SAFETY: The backend initialized the `len` bytes of the report it read
+        Ok(unsafe { &mut *(report as *mut [MaybeUninit<u8>] as *mut [u8]) })
+    }
+
+    /// Read an Input report and split it into its report ID and payload.
+    ///
+    /// The report ID is only the first byte of a report read from a device with numbered reports,
+    /// e.g. Windows strips the leading 0 of unnumbered reports like the other platforms. This
+    /// checks the report descriptor to tell both cases apart, the report ID is 0 for devices that
+    /// don't use numbered reports. Returns `None` if the timeout expired.
+    ///
+    /// The report descriptor is read on every call, so this fails on backends that can't provide
+    /// one.
+    pub fn read_report_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> HidResult<Option<(u8, Vec<u8>)>> {
+        let numbered = self.uses_report_ids()?;
+        let max_len = self
+            .input_report_len()?
+            .filter(|&len| len > 1)
+            .unwrap_or(MAX_REPORT_LEN);
+        let mut buf = vec![0u8; max_len];
+        let len = self.read_timeout_dur(&mut buf, timeout)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        buf.truncate(len);
+        let report_id = if numbered { buf.remove(0) } else { 0 };
+        Ok(Some((report_id, buf)))
+    }
+
+    /// Read up to `max` Input reports, appending each to `out`, and return the number of reports
+    /// read.
+    ///
+    /// Waits up to `timeout` for the first report, `None` waits forever. The reports that are
+    /// already queued after it are read without waiting, so a busy device doesn't need one
+    /// call per report. Returns 0 if the timeout expired. If an error occurs after some reports
+    /// were read, these are still appended to `out`.
+    pub fn read_reports(
+        &self,
+        out: &mut Vec<Vec<u8>>,
+        max: usize,
+        timeout: Option<Duration>,
+    ) -> HidResult<usize> {
+        let max_len = self
+            .input_report_len()?
+            .filter(|&len| len > 1)
+            .unwrap_or(MAX_REPORT_LEN);
+        let mut buf = vec![0u8; max_len];
+        let mut timeout = timeout_millis(timeout);
+        let mut count = 0;
+        while count < max {
+            let len = match self.read_timeout_strict(&mut buf, timeout) {
+                Ok(len) => len,
+                Err(HidError::Timeout) => break,
+                Err(e) => return Err(e),
+            };
+            out.push(buf[..len].to_vec());
+            count += 1;
+            timeout = 0;
+        }
+        Ok(count)
+    }
+
+    /// Read Input reports into `buf` one after another until at least `min` bytes were read, and
+    /// return the number of bytes read.
+    ///
+    /// For protocols that split a message across several reports. Reports are never split, so
+    /// more than `min` bytes may be read, and `buf` needs room for the last report beyond `min`.
+    /// Fails if a report doesn't fit into the rest of `buf`, which drops that report.
+    ///
+    /// `timeout` limits the whole call, `None` waits forever. Fails with [`HidError::Timeout`]
+    /// if it expires before `min` bytes were read, the reports read until then are in `buf`.
+    pub fn read_at_least(
+        &self,
+        buf: &mut [u8],
+        min: usize,
+        timeout: Option<Duration>,
+    ) -> HidResult<usize> {
+        if min > buf.len() {
+            return Err(HidError::HidApiError {
+                message: format!(
+                    "read_at_least: buffer of {} bytes is shorter than {} bytes",
+                    buf.len(),
+                    min
+                ),
+            });
+        }
+        let max_len = self
+            .input_report_len()?
+            .filter(|&len| len > 1)
+            .unwrap_or(MAX_REPORT_LEN);
+        let mut report = vec![0u8; max_len];
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut total = 0;
+        while total < min {
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let len = self.read_timeout_strict(&mut report, timeout_millis(remaining))?;
+            let rest = &mut buf[total..];
+            if len > rest.len() {
+                return Err(HidError::HidApiError {
+                    message: format!(
+                        "read_at_least: report of {} bytes doesn't fit into the {} bytes left",
+                        len,
+                        rest.len()
+                    ),
+                });
+            }
+            rest[..len].copy_from_slice(&report[..len]);
+            total += len;
+        }
+        Ok(total)
     }
 
     /// Send a Feature report to the device.
@@ -596,10 +2082,29 @@ impl HidDevice {
     /// `send_feature_report()`: 'the Report ID' (or 0x0, for devices which
     /// do not use numbered reports), followed by the report data (16 bytes).
     /// In this example, the length passed in would be 17.
+    ///
+    /// Fails with [`HidError::IncompleteSendError`] if not all of `data` could be sent.
+    ///
+    /// The linux-native backend checks the report ID against the report descriptor of the
+    /// device and pads shorter reports with zeros, instead of leaving the ioctl to fail.
     pub fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
         self.inner.send_feature_report(data)
     }
 
+    /// Send a [`FeatureReport`] to the device.
+    ///
+    /// Same as [`HidDevice::send_feature_report`], with the report ID in front of the data.
+    pub fn send_feature(&self, report: &FeatureReport) -> HidResult<()> {
+        self.send_feature_report(&report.to_bytes())
+    }
+
+    /// Get the [`FeatureReport`] with the given ID, 0 for devices that don't use numbered reports.
+    ///
+    /// See [`HidDevice::get_feature_report_vec`] for the size of the buffer.
+    pub fn get_feature(&self, id: u8) -> HidResult<FeatureReport> {
+        Ok(FeatureReport::new(id, self.get_feature_report_vec(id)?))
+    }
+
     /// Get a feature report from a HID device.
     ///
     /// Set the first byte of `buf` to the 'Report ID' of the report to be read.
@@ -612,6 +2117,45 @@ impl HidDevice {
         self.inner.get_feature_report(buf)
     }
 
+    /// The same as [`get_feature_report()`](Self::get_feature_report), but waits at most
+    /// `timeout` for the report, or forever if it is `None`.
+    ///
+    /// Only the `windows-native` backend supports a timeout, where a request that does not
+    /// complete in time is cancelled and fails with an error of kind [`HidErrorKind::Timeout`].
+    /// Plain [`get_feature_report()`](Self::get_feature_report) waits forever there. The other
+    /// backends ignore the timeout and rely on the timeout of the operating system.
+    pub fn get_feature_report_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> HidResult<usize> {
+        self.inner.get_feature_report_timeout(buf, timeout)
+    }
+
+    /// Get a feature report from a HID device into a newly allocated buffer.
+    ///
+    /// Same as [`HidDevice::get_feature_report`], but the buffer is sized to fit the longest
+    /// feature report, falling back to [`MAX_REPORT_LEN`] if the length is unknown. Use 0 as
+    /// `report_id` for devices that don't use numbered reports.
+    ///
+    /// The returned `Vec` only contains the report data, without the report ID.
+    pub fn get_feature_report_vec(&self, report_id: u8) -> HidResult<Vec<u8>> {
+        let max_len = self
+            .feature_report_len()?
+            .filter(|&len| len > 1)
+            .unwrap_or(MAX_REPORT_LEN);
+        let mut buf = vec![0u8; max_len];
+        buf[0] = report_id;
+        // Every backend counts the report ID byte, even for unnumbered reports where it is not
+        // transferred
+        let len = self.get_feature_report(&mut buf)?;
+        buf.truncate(len);
+        if !buf.is_empty() {
+            buf.remove(0);
+        }
+        Ok(buf)
+    }
+
     /// Send a Output report to the device.
     ///
     /// Output reports are sent over the Control endpoint as a Set_Report
@@ -625,8 +2169,12 @@ impl HidDevice {
     /// which do not use numbered reports), followed by the report
     /// data (16 bytes). In this example, the length passed in
     /// would be 17.
+    ///
+    /// Unlike [`HidDevice::write`], this never uses the Interrupt OUT endpoint.
+    /// The exception is the Linux native backend on kernels older than 5.11,
+    /// which have no way to send output reports other than a write.
     pub fn send_output_report(&self, data: &[u8]) -> HidResult<()> {
-        self.inner.send_output_report(data)
+        self.count_write(self.inner.send_output_report(data))
     }
 
     /// Get a input report from a HID device
@@ -647,6 +2195,9 @@ impl HidDevice {
     /// slice if there is no data to be read. In blocking mode, `read()` will
     /// wait (block) until there is data to read before returning.
     /// Modes can be changed at any time.
+    ///
+    /// Devices start in blocking mode with every backend, use [`HidApi::open_nonblocking`] or
+    /// [`HidApi::open_path_nonblocking`] to get a device in non-blocking mode right away.
     pub fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
         self.inner.set_blocking_mode(blocking)
     }
@@ -666,27 +2217,1570 @@ impl HidDevice {
         self.inner.get_serial_number_string()
     }
 
+    /// Get the Manufacturer String as the characters reported by the device.
+    ///
+    /// Unlike [`HidDevice::get_manufacturer_string`], this also works for strings that are not
+    /// valid Unicode, which only the hidapi C library passes on. The other backends convert
+    /// their strings back, so on Windows these are the UTF-16 code units of the string.
+    pub fn get_manufacturer_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.inner.get_manufacturer_string_raw()
+    }
+
+    /// Get the Product String as the characters reported by the device.
+    ///
+    /// See [`HidDevice::get_manufacturer_string_raw`].
+    pub fn get_product_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.inner.get_product_string_raw()
+    }
+
+    /// Get the Serial Number String as the characters reported by the device.
+    ///
+    /// See [`HidDevice::get_manufacturer_string_raw`].
+    pub fn get_serial_number_string_raw(&self) -> HidResult<Option<Vec<wchar_t>>> {
+        self.inner.get_serial_number_string_raw()
+    }
+
+    /// Read the manufacturer, product and serial number strings from the device again.
+    ///
+    /// The strings are read once when the device is opened, or on first use. Devices that change
+    /// them while open, e.g. when switching into a firmware update mode, need to be refreshed for
+    /// the getters to return the new strings.
+    ///
+    /// Supported by the `linux-native`, `windows-native` and libusb backends. The other backends
+    /// of the C library can't reload the strings and return an error.
+    pub fn refresh_strings(&self) -> HidResult<()> {
+        self.inner.refresh_strings()
+    }
+
+    /// Read the device info again and return it, including the strings and the usage.
+    ///
+    /// [`HidDevice::get_device_info`] returns the information from when the device was opened.
+    /// A device that switches into another mode while open may change its report descriptor, and
+    /// with it the usage page and usage. This reloads the preparsed data on Windows and the
+    /// device attributes on Linux, so the following calls of `get_device_info` return the new
+    /// information as well.
+    ///
+    /// Supported by the same backends as [`HidDevice::refresh_strings`].
+    pub fn refresh_device_info(&self) -> HidResult<DeviceInfo> {
+        self.inner.refresh_device_info()
+    }
+
+    /// Check if a kernel driver is bound to the USB interface of the device.
+    ///
+    /// Only supported by the libusb backends, which open the device a second time for this, as
+    /// the C library keeps its libusb handle to itself. On Linux the C library detaches the
+    /// kernel driver while the device is open, so this returns `false` there unless the driver was
+    /// attached again in the meantime. The other backends go through the kernel driver and return
+    /// an error.
+    pub fn kernel_driver_active(&self) -> HidResult<bool> {
+        self.inner.kernel_driver_active()
+    }
+
+    /// Detach the kernel driver from the USB interface of the device.
+    ///
+    /// Only supported by the libusb backends, like [`HidDevice::kernel_driver_active`]. Needs
+    /// write access to the USB device node. Succeeds if no kernel driver is bound to the
+    /// interface, which is usually the case on Linux, as the C library already detached it when
+    /// opening the device. The other backends return an error.
+    pub fn detach_kernel_driver(&self) -> HidResult<()> {
+        self.inner.detach_kernel_driver()
+    }
+
+    /// Attach the kernel driver to the USB interface of the device again.
+    ///
+    /// Only supported by the libusb backends, with the same requirements as
+    /// [`HidDevice::detach_kernel_driver`]. libusb fails with an error while the interface is
+    /// claimed, which the C library does for as long as the device is open, so this is mostly
+    /// useful after a device was closed without giving the driver back. The other backends return
+    /// an error.
+    pub fn reattach_kernel_driver(&self) -> HidResult<()> {
+        self.inner.reattach_kernel_driver()
+    }
+
     /// Get a string from a HID device, based on its string index.
     pub fn get_indexed_string(&self, index: i32) -> HidResult<Option<String>> {
         self.inner.get_indexed_string(index)
     }
 
+    /// Get a string from a HID device in the language `langid`, see
+    /// [`HidDevice::supported_languages`].
+    ///
+    /// Only the `linux-native` backend supports this, by reading the string descriptor from the
+    /// USB device through usbfs, which needs write access to the device node in `/dev/bus/usb`.
+    /// `HidD_GetIndexedString` on Windows has no language parameter, and the C library picks the
+    /// language itself with libusb. These backends return an error, as do devices on other buses.
+    pub fn get_indexed_string_lang(&self, index: i32, langid: u16) -> HidResult<Option<String>> {
+        self.inner.get_indexed_string_lang(index, langid)
+    }
+
+    /// Get the language IDs (LANGIDs) the device provides its strings in, from the USB string
+    /// descriptor at index 0, e.g. `0x0409` for English (United States).
+    ///
+    /// This needs a backend that can read any string descriptor, which are the C library with
+    /// libusb or on Windows, `windows-native`, and `linux-native` with write access to the USB
+    /// device node, see [`HidDevice::get_indexed_string_lang`]. The other backends return an
+    /// error.
+    pub fn supported_languages(&self) -> HidResult<Vec<u16>> {
+        self.inner.supported_languages()
+    }
+
     /// Get a report descriptor from a HID device
     ///
     /// User has to provide a preallocated buffer where the descriptor will be copied to.
     /// It is recommended to use a preallocated buffer of [`MAX_REPORT_DESCRIPTOR_SIZE`] size.
     ///
     /// On success returns the number of bytes actually filled into `buf`
+    ///
+    /// The hidraw backends (including `linux-native`), macOS and Windows always provide the
+    /// descriptor, on Windows it is reconstructed from the parsed data of the driver. The libusb
+    /// backends request it from the device and return [`HidError::ReportDescriptorUnavailable`]
+    /// if that fails.
     pub fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
         self.inner.get_report_descriptor(buf)
     }
 
+    /// Check if the device uses numbered reports.
+    ///
+    /// If it does, every report starts with its report ID. Otherwise reports are sent with a
+    /// leading `0x0` that is not transferred, and read without one. This parses the report
+    /// descriptor, so it fails on backends that can't provide one.
+    pub fn uses_report_ids(&self) -> HidResult<bool> {
+        Ok(self.report_descriptor()?.uses_report_ids())
+    }
+
+    /// Get the (usage_page, usage) pairs of all top-level collections of the device.
+    ///
+    /// A device with multiple collections is listed once per usage by
+    /// [`HidApi::device_list`] on some backends, but opening any of them opens the whole device.
+    /// This parses the report descriptor, so it fails on backends that can't provide one.
+    pub fn usages(&self) -> HidResult<Vec<(u16, u16)>> {
+        Ok(self.report_descriptor()?.usages().to_vec())
+    }
+
+    /// Get the (usage_page, usage) pair of the first top-level collection of the device.
+    ///
+    /// This is the usage that classifies a device, e.g. `(0x01, 0x06)` for a keyboard. Fails if
+    /// the report descriptor is not available or declares no collection.
+    pub fn primary_usage(&self) -> HidResult<(u16, u16)> {
+        self.report_descriptor()?
+            .primary_usage()
+            .ok_or_else(|| HidError::HidApiError {
+                message: "the report descriptor has no collection".into(),
+            })
+    }
+
+    /// Get the (report ID, type) pairs of all reports the device declares.
+    ///
+    /// The report ID is 0 for devices that don't use numbered reports. This parses the report
+    /// descriptor, so it fails on backends that can't provide one.
+    pub fn report_ids(&self) -> HidResult<Vec<(u8, ReportType)>> {
+        Ok(self.report_descriptor()?.report_ids())
+    }
+
+    /// Get the length in bytes of the longest input report of the device,
+    /// including the leading report ID byte.
+    ///
+    /// A buffer of this size is large enough for every call to [`HidDevice::read`].
+    /// Returns `None` if the backend can not determine the length, e.g. when the
+    /// report descriptor is not available.
+    pub fn input_report_len(&self) -> HidResult<Option<usize>> {
+        self.inner.input_report_len()
+    }
+
+    /// Get the length in bytes of the longest output report of the device,
+    /// including the leading report ID byte.
+    ///
+    /// Returns `None` if the backend can not determine the length.
+    pub fn output_report_len(&self) -> HidResult<Option<usize>> {
+        self.inner.output_report_len()
+    }
+
+    /// Get the length in bytes of the longest feature report of the device,
+    /// including the leading report ID byte.
+    ///
+    /// Returns `None` if the backend can not determine the length.
+    pub fn feature_report_len(&self) -> HidResult<Option<usize>> {
+        self.inner.feature_report_len()
+    }
+
     /// Get [`DeviceInfo`] from a HID device.
     pub fn get_device_info(&self) -> HidResult<DeviceInfo> {
         self.inner.get_device_info()
     }
 
-    pub fn close(&self) -> HidResult<()> {
+    /// Close the device, returning the errors that dropping it would ignore.
+    ///
+    /// The device can't be used after closing it:
+    ///
+    /// ```compile_fail
+    /// # fn closed(device: hidapi::HidDevice) {
+    /// device.close().unwrap();
+    /// device.read(&mut [0u8; 8]).unwrap();
+    /// # }
+    /// ```
+    pub fn close(self) -> HidResult<()> {
         self.inner.close()
     }
+
+    /// Discard all input reports that have been received but not read yet.
+    ///
+    /// Useful to get rid of stale reports that a device queued, e.g. right after it powered up.
+    /// This does not change the blocking mode of the device.
+    pub fn clear_input_buffer(&self) -> HidResult<()> {
+        self.inner.clear_input_buffer()
+    }
+
+    /// Get the error of the most recent operation on this device, if it failed.
+    ///
+    /// This works like `hid_error` of the C library: the error is kept until the next successful
+    /// operation clears it. The returned error is a copy, the failed method already returned the
+    /// original one.
+    ///
+    /// The `linux-native` backend and the C library, except for its libusb backend, keep track of
+    /// errors. The other backends always return `None`.
+    pub fn last_error(&self) -> Option<HidError> {
+        self.inner.last_error()
+    }
+
+    /// Get the number of reports this device read and wrote so far, and how many reads and
+    /// writes timed out or failed.
+    ///
+    /// The counters are atomics that are updated on every read and write, the overhead is gone
+    /// completely without the `stats` feature.
+    #[cfg(feature = "stats")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "stats")))]
+    pub fn stats(&self) -> HidStats {
+        self.stats.snapshot()
+    }
+
+    /// Switch a device that supports the boot interface between the boot and the report
+    /// protocol, using the Set_Protocol request of the USB HID class.
+    ///
+    /// Only supported by the libusb backends on other platforms than Linux, which open the device
+    /// a second time for this, as the C library keeps its libusb handle to itself. Linux only
+    /// accepts the request from the handle that claimed the interface, so the libusb backends
+    /// return an error there. hidraw, Windows and macOS don't offer an interface for it, as the
+    /// system driver manages the protocol. These return an error, as do devices on other buses
+    /// than USB.
+    pub fn set_protocol(&self, protocol: HidProtocol) -> HidResult<()> {
+        self.inner.set_protocol(protocol)
+    }
+
+    /// Get the protocol the device currently uses, using the Get_Protocol request of the USB HID
+    /// class. See [`HidDevice::set_protocol`] for the supported backends.
+    pub fn get_protocol(&self) -> HidResult<HidProtocol> {
+        self.inner.get_protocol()
+    }
+
+    /// Set how often the device repeats an input report that didn't change, using the Set_Idle
+    /// request of the USB HID class. Report ID 0 applies the duration to all reports.
+    ///
+    /// The request counts in units of 4 ms, so the duration is rounded to the nearest multiple of
+    /// 4 ms, up to 1020 ms. `Duration::ZERO` makes the device only send reports that changed, any
+    /// other duration is rounded up to at least 4 ms. Longer durations return an error.
+    ///
+    /// Supported by the same backends as [`HidDevice::set_protocol`], with the same restriction
+    /// on Linux. The other backends return an error.
+    pub fn set_idle(&self, report_id: u8, duration: Duration) -> HidResult<()> {
+        self.inner.set_idle(report_id, idle_rate(duration)?)
+    }
+
+    /// Get the idle duration of a report, using the Get_Idle request of the USB HID class.
+    /// `Duration::ZERO` means the device only sends reports that changed.
+    ///
+    /// See [`HidDevice::set_idle`] for the supported backends.
+    pub fn get_idle(&self, report_id: u8) -> HidResult<Duration> {
+        self.inner.get_idle(report_id).map(idle_duration)
+    }
+
+    /// Get the file descriptor of the device, e.g. to wait for input reports with `poll` or
+    /// `epoll` in a custom event loop.
+    ///
+    /// The descriptor stays owned by the device. Only the `linux-native` backend accesses devices
+    /// through a file descriptor, this returns `None` for all other backends.
+    #[cfg(unix)]
+    pub fn as_raw_fd(&self) -> Option<std::os::fd::RawFd> {
+        self.inner.raw_fd()
+    }
+
+    /// Borrow the file descriptor of the device, see [`HidDevice::as_raw_fd`].
+    ///
+    /// Devices without a descriptor, e.g. those created with `HidDevice::from_mock`, return
+    /// `None`, so `HidDevice` doesn't implement [`AsFd`](std::os::fd::AsFd).
+    #[cfg(unix)]
+    pub fn as_fd(&self) -> Option<std::os::fd::BorrowedFd<'_>> {
+        // SAFETY: The descriptor is owned by the backend, which lives as long as `self`
+        self.as_raw_fd()
+            .map(|fd| unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) })
+    }
+}
+
+/// Reads a single input report per call, see [`HidDevice::read`].
+///
+/// A return value of `Ok(0)` means that no report was available, which only happens in
+/// non-blocking mode. Callers that treat `Ok(0)` as end of stream (e.g. [`std::io::Read::read_to_end`])
+/// should keep the device in blocking mode.
+impl std::io::Read for &HidDevice {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        HidDevice::read(self, buf).map_err(Into::into)
+    }
+}
+
+/// Writes a single output report per call, see [`HidDevice::write`].
+///
+/// The first byte of each buffer must be the report ID (or `0x0` for devices that only
+/// support a single report); it is not added automatically.
+impl std::io::Write for &HidDevice {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        HidDevice::write(self, buf).map_err(Into::into)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_io_read() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.push_input(vec![0x00, 0x01, 0x02]);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(Read::read(&mut &device, &mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], &[0x00, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_io_write_all_keeps_report_byte() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let mut writer = &device;
+        writer.write_all(&[0x05, 0xAA]).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(mock.take_writes(), vec![vec![0x05, 0xAA]]);
+    }
+
+    #[test]
+    fn test_string_raw() {
+        let mock = MockDevice::new(0, 0);
+        mock.set_strings(Some("Ä"), None, Some("01"));
+        let device = HidDevice::from_mock(mock);
+        device.refresh_strings().unwrap();
+
+        assert_eq!(
+            device.get_manufacturer_string_raw().unwrap(),
+            Some(vec![0xC4 as wchar_t])
+        );
+        assert_eq!(device.get_product_string_raw().unwrap(), None);
+        assert_eq!(
+            device.get_serial_number_string_raw().unwrap(),
+            Some(vec![b'0' as wchar_t, b'1' as wchar_t])
+        );
+    }
+
+    #[test]
+    fn test_hid_string() {
+        let valid = HidString::String("Keyboard".into());
+        assert_eq!(valid.as_str(), Some("Keyboard"));
+        assert_eq!(valid.as_wide(), None);
+        assert!(matches!(
+            valid.to_string_lossy(),
+            Some(Cow::Borrowed("Keyboard"))
+        ));
+
+        // An unpaired surrogate, and a value beyond U+10FFFF where wchar_t is 32 bits wide
+        #[cfg(windows)]
+        let wide: Vec<wchar_t> = vec![0x41, 0xD800, 0x42];
+        #[cfg(not(windows))]
+        let wide: Vec<wchar_t> = vec![0x41, 0x11_0000, 0x42];
+        let raw = HidString::Raw(wide.clone());
+        assert_eq!(raw.as_str(), None);
+        assert_eq!(raw.as_wide(), Some(&wide[..]));
+        assert_eq!(raw.to_string_lossy().unwrap(), "A\u{FFFD}B");
+
+        assert_eq!(HidString::None.as_str(), None);
+        assert_eq!(HidString::None.as_wide(), None);
+        assert_eq!(HidString::None.to_string_lossy(), None);
+
+        let mut info = serial_device_info("/dev/hidraw0", "0001");
+        info.product_string = HidString::Raw(wide);
+        assert_eq!(info.serial_number_hid().as_str(), Some("0001"));
+        assert_eq!(info.product_string_hid(), &raw);
+        assert_eq!(info.manufacturer_string_hid(), &HidString::None);
+    }
+
+    #[test]
+    fn test_language_ids() {
+        // String descriptor 0 of a device supporting English (US) and German
+        const DESCRIPTOR: [u8; 6] = [0x06, 0x03, 0x09, 0x04, 0x07, 0x04];
+        // The backends skip the header and decode the rest as UTF-16
+        let utf16: Vec<u16> = DESCRIPTOR[2..]
+            .chunks(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        assert_eq!(language_ids(&utf16).unwrap(), vec![0x0409, 0x0407]);
+        let wide: Vec<i32> = utf16.iter().map(|&c| i32::from(c)).collect();
+        assert_eq!(language_ids(&wide).unwrap(), vec![0x0409, 0x0407]);
+
+        assert!(language_ids::<u16>(&[]).unwrap().is_empty());
+        assert!(language_ids(&[0x1_0000_i32]).is_err());
+    }
+
+    #[test]
+    fn test_read_string_growing() {
+        let string = (0..300).map(|i| b'a' as u16 + i % 26).collect::<Vec<_>>();
+        let mut calls = 0;
+        let read = read_string_growing(|buf: &mut [u16]| {
+            // Truncate like the C library does
+            calls += 1;
+            let len = string.len().min(buf.len() - 1);
+            buf[..len].copy_from_slice(&string[..len]);
+            buf[len] = 0;
+            Ok(())
+        });
+        assert_eq!(read.unwrap(), string);
+        assert_eq!(calls, 3);
+
+        let read = read_string_growing(|buf: &mut [u16]| {
+            buf[..3].copy_from_slice(&[0x48, 0x69, 0]);
+            Ok(())
+        });
+        assert_eq!(read.unwrap(), [0x48, 0x69]);
+
+        // Strings without end are cut off at the maximum length
+        let read = read_string_growing(|buf: &mut [u16]| {
+            buf.fill(0x41);
+            Ok(())
+        });
+        assert_eq!(read.unwrap().len(), MAX_STRING_BUF_LEN);
+    }
+
+    #[test]
+    fn test_long_strings() {
+        let long = "x".repeat(300);
+        let mock = MockDevice::new(0, 0);
+        mock.set_strings(Some(&long), Some(&long), None);
+        let device = HidDevice::from_mock(mock);
+        device.refresh_strings().unwrap();
+
+        assert_eq!(
+            device.get_manufacturer_string().unwrap(),
+            Some(long.clone())
+        );
+        assert_eq!(device.get_product_string_raw().unwrap().unwrap().len(), 300);
+    }
+
+    #[test]
+    fn test_write_empty() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        assert!(matches!(
+            device.write(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+        assert_eq!(device.write_empty().unwrap(), 1);
+        assert_eq!(mock.take_writes(), vec![vec![0x00]]);
+    }
+
+    #[test]
+    fn test_send_output_report_is_not_a_write() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        device.send_output_report(&[0x02, 0xAA]).unwrap();
+        device.write(&[0x02, 0xBB]).unwrap();
+
+        assert_eq!(mock.take_output_reports(), vec![vec![0x02, 0xAA]]);
+        assert_eq!(mock.take_writes(), vec![vec![0x02, 0xBB]]);
+        assert!(matches!(
+            device.send_output_report(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut info = HidDevice::from_mock(MockDevice::new(0x046d, 0xc52b))
+            .get_device_info()
+            .unwrap();
+        assert_eq!(info.to_string(), "046d:c52b (Unknown) mock");
+
+        info.manufacturer_string = HidString::String("Logitech".into());
+        info.product_string = HidString::String("USB Receiver".into());
+        info.bus_type = BusType::Usb;
+        info.path = CString::new("/dev/hidraw0").unwrap();
+        assert_eq!(
+            info.to_string(),
+            "046d:c52b Logitech USB Receiver (USB) /dev/hidraw0"
+        );
+
+        info.manufacturer_string = HidString::String(String::new());
+        info.bus_type = BusType::I2c;
+        assert_eq!(
+            info.to_string(),
+            "046d:c52b USB Receiver (I2C) /dev/hidraw0"
+        );
+
+        assert_eq!(BusType::Bluetooth.to_string(), "Bluetooth");
+        assert_eq!(BusType::Spi.to_string(), "SPI");
+    }
+
+    #[test]
+    fn test_idle_rate() {
+        assert_eq!(idle_rate(Duration::ZERO).unwrap(), 0);
+        assert_eq!(idle_rate(Duration::from_micros(1)).unwrap(), 1);
+        assert_eq!(idle_rate(Duration::from_millis(4)).unwrap(), 1);
+        assert_eq!(idle_rate(Duration::from_millis(5)).unwrap(), 1);
+        assert_eq!(idle_rate(Duration::from_millis(6)).unwrap(), 2);
+        assert_eq!(idle_rate(Duration::from_millis(500)).unwrap(), 125);
+        assert_eq!(idle_rate(Duration::from_millis(1020)).unwrap(), 255);
+        assert_eq!(idle_rate(Duration::from_millis(1021)).unwrap(), 255);
+        assert!(idle_rate(Duration::from_millis(1022)).is_err());
+        assert!(idle_rate(Duration::from_secs(u64::MAX)).is_err());
+
+        assert_eq!(idle_duration(0), Duration::ZERO);
+        assert_eq!(idle_duration(125), Duration::from_millis(500));
+        assert_eq!(idle_duration(255), Duration::from_millis(1020));
+    }
+
+    #[test]
+    fn test_bus_type_values() {
+        for bus_type in [
+            BusType::Unknown,
+            BusType::Usb,
+            BusType::Bluetooth,
+            BusType::I2c,
+            BusType::Spi,
+        ] {
+            assert_eq!(BusType::try_from(bus_type.as_u8()).unwrap(), bus_type);
+        }
+        assert_eq!(BusType::Usb.as_u8(), 0x01);
+        assert_eq!(BusType::Spi.as_u8(), 0x04);
+        assert!(matches!(
+            BusType::try_from(0x05),
+            Err(HidError::HidApiError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_protocol_values() {
+        assert_eq!(HidProtocol::Boot as u8, 0);
+        assert_eq!(HidProtocol::Report as u8, 1);
+        assert_eq!(HidProtocol::try_from(0).unwrap(), HidProtocol::Boot);
+        assert_eq!(HidProtocol::try_from(1).unwrap(), HidProtocol::Report);
+        assert!(matches!(
+            HidProtocol::try_from(2),
+            Err(HidError::HidApiError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_device_info_eq_hash() {
+        use std::collections::HashSet;
+
+        let info = HidDevice::from_mock(MockDevice::new(0x1234, 0x5678))
+            .get_device_info()
+            .unwrap();
+        let mut other = info.clone();
+        other.release_number = 0x0100;
+        assert_eq!(info, other);
+
+        let mut set = HashSet::new();
+        assert!(set.insert(info.clone()));
+        assert!(!set.insert(other));
+
+        let mut raw = info.clone();
+        raw.serial_number = HidString::Raw(vec![]);
+        assert_ne!(info, raw);
+        assert!(set.insert(raw.clone()));
+        assert!(!set.insert(raw));
+
+        let mut usage = info;
+        usage.usage = 1;
+        assert!(set.insert(usage));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_bluetooth_address() {
+        let address = Some([0x01, 0x23, 0x45, 0x67, 0x89, 0xAB]);
+        assert_eq!(parse_bluetooth_address("01:23:45:67:89:ab"), address);
+        assert_eq!(parse_bluetooth_address("01:23:45:67:89:AB"), address);
+        assert_eq!(parse_bluetooth_address("0123456789ab"), address);
+
+        assert_eq!(parse_bluetooth_address(""), None);
+        assert_eq!(parse_bluetooth_address("01:23:45:67:89"), None);
+        assert_eq!(parse_bluetooth_address("01:23:45:67:89:ab:cd"), None);
+        assert_eq!(parse_bluetooth_address("0123456789a"), None);
+        assert_eq!(parse_bluetooth_address("1:23:45:67:89:ab"), None);
+        assert_eq!(parse_bluetooth_address("+1:23:45:67:89:ab"), None);
+        assert_eq!(parse_bluetooth_address("0123456789xy"), None);
+    }
+
+    #[test]
+    fn test_bluetooth_address() {
+        let mut info = HidDevice::from_mock(MockDevice::new(0, 0))
+            .get_device_info()
+            .unwrap();
+        info.serial_number = HidString::String("01:23:45:67:89:ab".into());
+        assert_eq!(info.bluetooth_address(), None);
+
+        info.bus_type = BusType::Bluetooth;
+        assert_eq!(
+            info.bluetooth_address(),
+            Some([0x01, 0x23, 0x45, 0x67, 0x89, 0xAB])
+        );
+
+        info.bluetooth_address = Some([1, 2, 3, 4, 5, 6]);
+        assert_eq!(info.bluetooth_address(), Some([1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_timeout_millis() {
+        assert_eq!(timeout_millis(None), -1);
+        assert_eq!(timeout_millis(Some(Duration::ZERO)), 0);
+        assert_eq!(timeout_millis(Some(Duration::from_micros(1))), 1);
+        assert_eq!(timeout_millis(Some(Duration::from_millis(1500))), 1500);
+        assert_eq!(
+            timeout_millis(Some(Duration::from_millis(i32::MAX as u64 + 1))),
+            i32::MAX
+        );
+        assert_eq!(timeout_millis(Some(Duration::MAX)), i32::MAX);
+    }
+
+    #[test]
+    fn test_read_vec() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        mock.push_input(vec![0x01, 0x02, 0x03]);
+        assert_eq!(device.read_vec(64).unwrap(), vec![0x01, 0x02, 0x03]);
+
+        mock.push_input(vec![0x01, 0x02, 0x03]);
+        assert_eq!(device.read_vec(2).unwrap(), vec![0x01, 0x02]);
+
+        assert!(device.read_timeout_vec(64, 0).unwrap().is_empty());
+        mock.push_input(vec![0x04]);
+        assert_eq!(device.read_timeout_vec(64, 10).unwrap(), vec![0x04]);
+    }
+
+    #[test]
+    fn test_read_report() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        // Without a descriptor the length is unknown
+        mock.push_input(vec![0x01; 100]);
+        assert_eq!(device.read_report().unwrap().len(), 100);
+
+        // An input report with 8 bits of data and no report ID
+        mock.set_report_descriptor(vec![
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x01, //   Report Count (1)
+            0x81, 0x02, //   Input (Data,Var,Abs)
+            0xC0, // End Collection
+        ]);
+        assert_eq!(device.input_report_len().unwrap(), Some(2));
+        mock.push_input(vec![0x00, 0x2A, 0xFF]);
+        assert_eq!(device.read_report().unwrap(), vec![0x00, 0x2A]);
+    }
+
+    #[test]
+    fn test_report_len_of_disconnected_device() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.disconnect();
+        assert!(matches!(
+            device.input_report_len(),
+            Err(HidError::Disconnected)
+        ));
+        assert!(matches!(
+            device.feature_report_len(),
+            Err(HidError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_write_report() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(vec![
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x85, 0x05, //   Report ID (5)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x03, //   Report Count (3)
+            0x91, 0x02, //   Output (Data,Var,Abs)
+            0xC0, // End Collection
+        ]);
+
+        assert_eq!(device.write_report(Some(5), &[0xAA]).unwrap(), 4);
+        assert_eq!(mock.take_writes(), vec![vec![0x05, 0xAA, 0x00, 0x00]]);
+        assert!(device.write_report(None, &[0xAA]).is_err());
+        assert!(device.write_report(Some(5), &[0; 4]).is_err());
+        assert!(mock.take_writes().is_empty());
+    }
+
+    #[test]
+    fn test_uses_report_ids() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let descriptor = |report_id: &[u8]| {
+            [
+                &[
+                    0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+                    0x09, 0x01, // Usage (0x01)
+                    0xA1, 0x01, // Collection (Application)
+                ][..],
+                report_id,
+                &[
+                    0x75, 0x08, //   Report Size (8)
+                    0x95, 0x02, //   Report Count (2)
+                    0x81, 0x02, //   Input (Data,Var,Abs)
+                    0xC0, // End Collection
+                ],
+            ]
+            .concat()
+        };
+
+        mock.set_report_descriptor(descriptor(&[0x85, 0x01])); // Report ID (1)
+        assert!(device.uses_report_ids().unwrap());
+        mock.set_report_descriptor(descriptor(&[]));
+        assert!(!device.uses_report_ids().unwrap());
+    }
+
+    #[test]
+    fn test_usages() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(&include_bytes!("../tests/assets/mouse2.data")[..]);
+        assert_eq!(
+            device.usages().unwrap(),
+            vec![(1, 2), (1, 1), (1, 128), (12, 1), (65280, 14)]
+        );
+    }
+
+    #[test]
+    fn test_primary_usage() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(&include_bytes!("../tests/assets/mouse2.data")[..]);
+        assert_eq!(device.primary_usage().unwrap(), (1, 2));
+
+        mock.set_report_descriptor(vec![]);
+        assert!(device.primary_usage().is_err());
+    }
+
+    #[test]
+    fn test_read_report_timeout() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let descriptor = |report_id: &[u8]| {
+            [
+                &[
+                    0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+                    0x09, 0x01, // Usage (0x01)
+                    0xA1, 0x01, // Collection (Application)
+                ][..],
+                report_id,
+                &[
+                    0x75, 0x08, //   Report Size (8)
+                    0x95, 0x02, //   Report Count (2)
+                    0x81, 0x02, //   Input (Data,Var,Abs)
+                    0xC0, // End Collection
+                ],
+            ]
+            .concat()
+        };
+        let timeout = Some(Duration::ZERO);
+
+        mock.set_report_descriptor(descriptor(&[0x85, 0x03])); // Report ID (3)
+        mock.push_input(vec![0x03, 0x10, 0x20]);
+        assert_eq!(
+            device.read_report_timeout(timeout).unwrap(),
+            Some((3, vec![0x10, 0x20]))
+        );
+        assert_eq!(device.read_report_timeout(timeout).unwrap(), None);
+
+        // Unnumbered reports come without the leading 0, the payload may start with 0 itself
+        mock.set_report_descriptor(descriptor(&[]));
+        mock.push_input(vec![0x00, 0x20]);
+        assert_eq!(
+            device.read_report_timeout(timeout).unwrap(),
+            Some((0, vec![0x00, 0x20]))
+        );
+        mock.push_input(vec![0x10, 0x20]);
+        assert_eq!(
+            device.read_report_timeout(timeout).unwrap(),
+            Some((0, vec![0x10, 0x20]))
+        );
+    }
+
+    #[test]
+    fn test_get_feature_report_vec() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(vec![
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x85, 0x02, //   Report ID (2)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x03, //   Report Count (3)
+            0xB1, 0x02, //   Feature (Data,Var,Abs)
+            0xC0, // End Collection
+        ]);
+        mock.set_feature_report(vec![0x02, 0x10, 0x20, 0x30]);
+        assert_eq!(
+            device.get_feature_report_vec(2).unwrap(),
+            vec![0x10, 0x20, 0x30]
+        );
+        assert!(device.get_feature_report_vec(3).is_err());
+
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(vec![
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x02, //   Report Count (2)
+            0xB1, 0x02, //   Feature (Data,Var,Abs)
+            0xC0, // End Collection
+        ]);
+        mock.set_feature_report(vec![0x00, 0xAA, 0xBB]);
+        assert_eq!(device.get_feature_report_vec(0).unwrap(), vec![0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_write_chunked() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_report_descriptor(vec![
+            0x06, 0x00, 0xFF, // Usage Page (Vendor Defined 0xFF00)
+            0x09, 0x01, // Usage (0x01)
+            0xA1, 0x01, // Collection (Application)
+            0x85, 0x05, //   Report ID (5)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x03, //   Report Count (3)
+            0x91, 0x02, //   Output (Data,Var,Abs)
+            0xC0, // End Collection
+        ]);
+
+        let payload = [1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(device.write_chunked(5, &payload).unwrap(), payload.len());
+        let writes = mock.take_writes();
+        assert_eq!(
+            writes,
+            vec![vec![5, 1, 2, 3], vec![5, 4, 5, 6], vec![5, 7, 0, 0]]
+        );
+        let reassembled = writes
+            .iter()
+            .flat_map(|frame| &frame[1..])
+            .take(payload.len())
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(reassembled, payload);
+
+        assert!(device.write_chunked(5, &[]).is_err());
+        assert!(mock.take_writes().is_empty());
+    }
+
+    #[test]
+    fn test_read_uninit() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        let mut buf = [MaybeUninit::<u8>::uninit(); 8];
+        mock.push_input(vec![0x01, 0x02, 0x03]);
+        let report = device.read_uninit(&mut buf, None).unwrap();
+        assert_eq!(report, &[0x01, 0x02, 0x03]);
+
+        let report = device.read_uninit(&mut buf, Some(Duration::ZERO)).unwrap();
+        assert!(report.is_empty());
+
+        let mut short = [MaybeUninit::<u8>::uninit(); 2];
+        mock.push_input(vec![0x04, 0x05, 0x06]);
+        let report = device.read_uninit(&mut short, None).unwrap();
+        assert_eq!(report, &[0x04, 0x05]);
+    }
+
+    #[test]
+    fn test_read_timeout_dur() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            device
+                .read_timeout_dur(&mut buf, Some(Duration::ZERO))
+                .unwrap(),
+            0
+        );
+
+        mock.push_input(vec![0x01]);
+        assert_eq!(device.read_timeout_dur(&mut buf, None).unwrap(), 1);
+
+        mock.push_input(vec![0x01, 0x02]);
+        assert_eq!(device.read_timeout(&mut buf, -1).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_read_timeout_strict() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let mut buf = [0u8; 4];
+
+        assert!(matches!(
+            device.read_timeout_strict(&mut buf, 0),
+            Err(HidError::Timeout)
+        ));
+        assert!(matches!(
+            device.read_timeout_strict(&mut buf, 10),
+            Err(HidError::Timeout)
+        ));
+
+        mock.push_input(vec![]);
+        assert_eq!(device.read_timeout_strict(&mut buf, 0).unwrap(), 0);
+        mock.push_input(vec![0x01, 0x02]);
+        assert_eq!(device.read_timeout_strict(&mut buf, -1).unwrap(), 2);
+
+        // The lenient read can't tell a timeout from an empty report
+        assert_eq!(device.read_timeout(&mut buf, 0).unwrap(), 0);
+        mock.push_input(vec![]);
+        assert_eq!(device.read_timeout(&mut buf, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_reports() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let mut reports = Vec::new();
+
+        assert_eq!(
+            device
+                .read_reports(&mut reports, 8, Some(Duration::ZERO))
+                .unwrap(),
+            0
+        );
+        assert!(reports.is_empty());
+
+        for i in 1..=5u8 {
+            mock.push_input(vec![i; i as usize]);
+        }
+        assert_eq!(device.read_reports(&mut reports, 3, None).unwrap(), 3);
+        assert_eq!(reports, vec![vec![1], vec![2, 2], vec![3, 3, 3]]);
+
+        // The rest is read without waiting and appended
+        assert_eq!(
+            device
+                .read_reports(&mut reports, 8, Some(Duration::from_millis(10)))
+                .unwrap(),
+            2
+        );
+        assert_eq!(reports.len(), 5);
+        assert_eq!(reports[4], vec![5; 5]);
+        assert_eq!(device.read_reports(&mut reports, 0, None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_at_least() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        let mut buf = [0u8; 8];
+
+        mock.push_input(vec![1, 2]);
+        mock.push_input(vec![3]);
+        mock.push_input(vec![4, 5, 6]);
+        mock.push_input(vec![7]);
+        // The third report is read whole, even though 4 bytes would be enough
+        assert_eq!(device.read_at_least(&mut buf, 4, None).unwrap(), 6);
+        assert_eq!(buf[..6], [1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            device
+                .read_at_least(&mut buf, 1, Some(Duration::ZERO))
+                .unwrap(),
+            1
+        );
+        assert_eq!(buf[0], 7);
+
+        // Chunks arriving while waiting
+        let sender = {
+            let mock = mock.clone();
+            std::thread::spawn(move || {
+                for chunk in [vec![1, 2, 3], vec![4, 5, 6]] {
+                    std::thread::sleep(Duration::from_millis(10));
+                    mock.push_input(chunk);
+                }
+            })
+        };
+        assert_eq!(
+            device
+                .read_at_least(&mut buf, 5, Some(Duration::from_secs(10)))
+                .unwrap(),
+            6
+        );
+        assert_eq!(buf[..6], [1, 2, 3, 4, 5, 6]);
+        sender.join().unwrap();
+
+        mock.push_input(vec![1]);
+        assert!(matches!(
+            device.read_at_least(&mut buf, 2, Some(Duration::from_millis(10))),
+            Err(HidError::Timeout)
+        ));
+        assert_eq!(buf[0], 1);
+
+        mock.push_input(vec![1; 6]);
+        mock.push_input(vec![2; 6]);
+        assert!(device.read_at_least(&mut buf, 7, None).is_err());
+        assert!(device.read_at_least(&mut buf, 9, None).is_err());
+    }
+
+    #[test]
+    fn test_device_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<HidDevice>();
+    }
+
+    #[test]
+    fn test_concurrent_read_write() {
+        const REPORTS: usize = 1000;
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let mut buf = [0u8; 4];
+                for i in 0..REPORTS {
+                    assert_eq!(device.read(&mut buf).unwrap(), 3);
+                    assert_eq!(buf[..3], [0x01, (i >> 8) as u8, i as u8]);
+                }
+            });
+            s.spawn(|| {
+                for i in 0..REPORTS {
+                    device.write(&[0x02, (i >> 8) as u8, i as u8]).unwrap();
+                }
+            });
+            s.spawn(|| {
+                for i in 0..REPORTS {
+                    mock.push_input(vec![0x01, (i >> 8) as u8, i as u8]);
+                }
+            });
+        });
+
+        let writes = mock.take_writes();
+        assert_eq!(writes.len(), REPORTS);
+        for (i, report) in writes.iter().enumerate() {
+            assert_eq!(report[..], [0x02, (i >> 8) as u8, i as u8]);
+        }
+    }
+
+    #[test]
+    fn test_clear_input_buffer() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        for i in 0..5 {
+            mock.push_input(vec![0x01, i]);
+        }
+
+        device.clear_input_buffer().unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(device.read_timeout(&mut buf, 0).unwrap(), 0);
+
+        // Still blocking afterwards
+        mock.push_input(vec![0x01, 0xff]);
+        assert_eq!(device.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[1], 0xff);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mock_has_no_fd() {
+        let device = HidDevice::from_mock(MockDevice::new(0, 0));
+        assert_eq!(device.as_raw_fd(), None);
+        assert!(device.as_fd().is_none());
+    }
+
+    #[test]
+    fn test_kernel_driver_needs_libusb() {
+        let device = HidDevice::from_mock(MockDevice::new(0, 0));
+        assert!(matches!(
+            device.detach_kernel_driver(),
+            Err(HidError::HidApiError { message }) if message == "detach_kernel_driver: not supported"
+        ));
+        assert!(matches!(
+            device.reattach_kernel_driver(),
+            Err(HidError::HidApiError { message }) if message == "reattach_kernel_driver: not supported"
+        ));
+    }
+
+    #[test]
+    fn test_io_error_mapping() {
+        let device = HidDevice::from_mock(MockDevice::new(0, 0));
+        let err = Write::write(&mut &device, &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+        assert!(matches!(
+            err.into_inner().unwrap().downcast::<HidError>().as_deref(),
+            Ok(HidError::InvalidZeroSizeData)
+        ));
+
+        let err: std::io::Error = HidError::IoError {
+            error: std::io::ErrorKind::TimedOut.into(),
+        }
+        .into();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    fn serial_device_info(path: &str, serial: &str) -> DeviceInfo {
+        let mut info = HidDevice::from_mock(MockDevice::new(0x1234, 0x5678))
+            .get_device_info()
+            .unwrap();
+        info.path = CString::new(path).unwrap();
+        info.serial_number = HidString::String(serial.into());
+        info
+    }
+
+    #[test]
+    fn test_retry() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let mut calls = 0;
+        let device = retry(5, Duration::ZERO, || {
+            calls += 1;
+            match calls {
+                1 => Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied).into()),
+                2 => Err(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
+                _ => Ok(HidDevice::from_mock(mock.clone())),
+            }
+        });
+        assert_eq!(device.unwrap().get_device_info().unwrap().vendor_id, 0x1234);
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: HidResult<()> = retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound).into())
+        });
+        assert_eq!(result.unwrap_err().kind(), HidErrorKind::NotFound);
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: HidResult<()> = retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(HidError::InvalidZeroSizeData)
+        });
+        assert!(matches!(result, Err(HidError::InvalidZeroSizeData)));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "usage-tables")]
+    fn test_usage_names() {
+        let mut info = serial_device_info("/dev/hidraw0", "A");
+        info.usage_page = 0x01;
+        info.usage = 0x06;
+        assert_eq!(
+            info.usage_names(),
+            (Some("Generic Desktop"), Some("Keyboard"))
+        );
+        info.usage_page = 0xFF42;
+        assert_eq!(info.usage_names(), (Some("Vendor-defined"), None));
+    }
+
+    #[test]
+    fn test_is_reconnected_device() {
+        let info = serial_device_info("/dev/hidraw3", "A");
+        assert!(info.is_reconnected_device(&serial_device_info("/dev/hidraw3", "B")));
+        assert!(info.is_reconnected_device(&serial_device_info("/dev/hidraw5", "A")));
+        assert!(!info.is_reconnected_device(&serial_device_info("/dev/hidraw5", "B")));
+
+        let mut info = serial_device_info("/dev/hidraw3", "");
+        let mut other = serial_device_info("/dev/hidraw5", "");
+        assert!(!info.is_reconnected_device(&other));
+        info.group_id = Some(GroupId::new("/sys/devices/usb1/1-2"));
+        other.group_id = info.group_id.clone();
+        assert!(info.is_reconnected_device(&other));
+        other.interface_number = 1;
+        assert!(!info.is_reconnected_device(&other));
+    }
+
+    #[test]
+    fn test_find_first() {
+        let mut keyboard = serial_device_info("/dev/hidraw3", "A");
+        keyboard.product_id = 0x0001;
+        let api = api_with(vec![
+            keyboard,
+            serial_device_info("/dev/hidraw4", "B"),
+            serial_device_info("/dev/hidraw5", "C"),
+        ]);
+
+        let filter = DeviceFilter::new().vendor_id(0x1234).product_id(0x5678);
+        for _ in 0..3 {
+            assert_eq!(api.find_first(&filter).unwrap().serial_number(), Some("B"));
+        }
+        let filter = DeviceFilter::new().vendor_id(0x1234);
+        assert_eq!(api.find_first(&filter).unwrap().serial_number(), Some("A"));
+        assert!(matches!(
+            api.find_first(&DeviceFilter::new().vendor_id(0x4321)),
+            Err(HidError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_filter_openable() {
+        let devices = [
+            serial_device_info("/dev/hidraw0", "A"),
+            serial_device_info("/dev/hidraw1", "B"),
+            serial_device_info("/dev/hidraw2", "C"),
+        ];
+        let openable: Vec<_> = filter_openable(devices.iter(), |path| match path.to_bytes() {
+            b"/dev/hidraw1" => Err(HidError::PermissionDenied {
+                path: "/dev/hidraw1".into(),
+            }),
+            _ => Ok(HidDevice::from_mock(MockDevice::new(0x1234, 0x5678))),
+        })
+        .map(|d| d.serial_number().unwrap())
+        .collect();
+        assert_eq!(openable, vec!["A", "C"]);
+    }
+
+    #[test]
+    fn test_verify() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        mock.set_strings(None, None, Some("0001"));
+        let open = || {
+            let device = HidDevice::from_mock(mock.clone());
+            device.refresh_strings().unwrap();
+            device
+        };
+
+        assert!(verify(open(), 0x1234, 0x5678, None).is_ok());
+        assert!(verify(open(), 0x1234, 0x5678, Some("0001")).is_ok());
+        // The path now belongs to another device, or another one of the same kind
+        for (vid, pid, sn) in [
+            (0x1234, 0x9999, None),
+            (0x4321, 0x5678, None),
+            (0x1234, 0x5678, Some("0002")),
+        ] {
+            match verify(open(), vid, pid, sn) {
+                Err(HidError::Mismatch { device_info }) => {
+                    assert_eq!(device_info.vendor_id(), 0x1234);
+                    assert_eq!(device_info.serial_number(), Some("0001"));
+                }
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nonblocking() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+        assert!(mock.is_blocking());
+
+        let device = nonblocking(device).unwrap();
+        assert!(!mock.is_blocking());
+        assert_eq!(device.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_open_interface() {
+        let interface = |path: &str, interface_number| {
+            let mut info = serial_device_info(path, "A");
+            info.interface_number = interface_number;
+            info
+        };
+        let api = api_with(vec![
+            interface("/dev/hidraw3", 0),
+            interface("/dev/hidraw4", 1),
+            interface("/dev/hidraw5", 2),
+            interface("/dev/hidraw6", 1),
+        ]);
+
+        let filter = DeviceFilter::new()
+            .vendor_id(0x1234)
+            .product_id(0x5678)
+            .interface_number(1);
+        assert_eq!(api.find_first(&filter).unwrap().path(), c"/dev/hidraw4");
+        assert!(matches!(
+            api.open_interface(0x1234, 0x5678, 3),
+            Err(HidError::NotFound)
+        ));
+        assert!(matches!(
+            api.open_interface(0x1234, 0x0001, 1),
+            Err(HidError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_open_usage() {
+        let collection = |path: &str, usage_page, usage| {
+            let mut info = serial_device_info(path, "A");
+            info.usage_page = usage_page;
+            info.usage = usage;
+            info
+        };
+        let usage_filter = |usage_page, usage| {
+            DeviceFilter::new()
+                .vendor_id(0x1234)
+                .product_id(0x5678)
+                .usage_page(usage_page)
+                .usage(usage)
+        };
+
+        // Windows lists every top-level collection under its own path
+        let api = api_with(vec![
+            collection(r"\\?\hid#vid_1234&pid_5678&col01", 0x01, 0x06),
+            collection(r"\\?\hid#vid_1234&pid_5678&col02", 0x0C, 0x01),
+            collection(r"\\?\hid#vid_1234&pid_5678&col03", 0xFF00, 0x01),
+        ]);
+        let found = api.find_first(&usage_filter(0xFF00, 0x01)).unwrap();
+        assert_eq!(found.path_str(), Some(r"\\?\hid#vid_1234&pid_5678&col03"));
+        assert!(matches!(
+            api.open_usage(0x1234, 0x5678, 0xFF00, 0x02),
+            Err(HidError::NotFound)
+        ));
+
+        // hidraw lists the collections of an interface under the same path
+        let api = api_with(vec![
+            collection("/dev/hidraw3", 0x01, 0x06),
+            collection("/dev/hidraw4", 0x01, 0x02),
+            collection("/dev/hidraw4", 0x0C, 0x01),
+        ]);
+        let found = api.find_first(&usage_filter(0x0C, 0x01)).unwrap();
+        assert_eq!(found.path_str(), Some("/dev/hidraw4"));
+        assert_eq!((found.usage_page, found.usage), (0x0C, 0x01));
+        assert!(matches!(
+            api.open_usage(0x1234, 0x5678, 0x0C, 0x02),
+            Err(HidError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_device_list_owned() {
+        let api = api_with(vec![
+            serial_device_info("/dev/hidraw3", "A"),
+            serial_device_info("/dev/hidraw4", "B"),
+        ]);
+        let devices = api.device_list_owned();
+        drop(api);
+
+        let serials = devices
+            .iter()
+            .map(|d| d.serial_number().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(serials, ["A", "B"]);
+    }
+
+    #[test]
+    fn test_refresh_devices_delta() {
+        let mut api = api_with(vec![
+            serial_device_info("/dev/hidraw3", "A"),
+            serial_device_info("/dev/hidraw4", "B"),
+        ]);
+
+        let delta = api
+            .refresh_devices_delta_with(|| {
+                Ok(vec![
+                    serial_device_info("/dev/hidraw4", "B"),
+                    serial_device_info("/dev/hidraw5", "C"),
+                ])
+            })
+            .unwrap();
+        let paths = |devices: &[DeviceInfo]| {
+            devices
+                .iter()
+                .map(|d| d.path_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&delta.added), ["/dev/hidraw5"]);
+        assert_eq!(paths(&delta.removed), ["/dev/hidraw3"]);
+        assert_eq!(
+            paths(&api.device_list_owned()),
+            ["/dev/hidraw4", "/dev/hidraw5"]
+        );
+
+        let devices = api.device_list_owned();
+        let delta = api.refresh_devices_delta_with(|| Ok(devices)).unwrap();
+        assert!(delta.is_empty());
+
+        let result = api.refresh_devices_delta_with(|| Err(HidError::HidApiErrorEmpty));
+        assert!(result.is_err());
+        assert_eq!(api.device_list().count(), 2);
+    }
+
+    #[test]
+    fn test_path_str() {
+        let info = serial_device_info("/dev/hidraw3", "A");
+        assert_eq!(info.path_str(), Some("/dev/hidraw3"));
+        assert_eq!(info.path_str().unwrap().as_bytes(), info.path().to_bytes());
+
+        let mut info = info;
+        info.path = CString::new(b"/dev/\xffhidraw".to_vec()).unwrap();
+        assert_eq!(info.path_str(), None);
+    }
+
+    /// Opens a mock device for `path`, if it is in `connected`
+    fn open_connected(connected: &[DeviceInfo], path: &CStr) -> HidResult<HidDevice> {
+        match connected.iter().find(|d| d.path.as_c_str() == path) {
+            Some(d) => Ok(HidDevice::from_mock(MockDevice::new(
+                d.vendor_id,
+                d.product_id,
+            ))),
+            None => Err(HidError::HidApiError {
+                message: "no such device".into(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_open_with_fallback() {
+        let connected = vec![
+            serial_device_info("/dev/hidraw3", "A"),
+            serial_device_info("/dev/hidraw4", "B"),
+        ];
+        let open = |path: &CStr| open_connected(&connected, path);
+        let enumerate = || Ok(connected.clone());
+
+        // The path is still valid
+        let info = serial_device_info("/dev/hidraw3", "A");
+        assert!(open_with_fallback(&info, open, enumerate).is_ok());
+
+        // Reconnected under a different path
+        let info = serial_device_info("/dev/hidraw0", "B");
+        assert!(open_with_fallback(&info, open, enumerate).is_ok());
+
+        let info = serial_device_info("/dev/hidraw0", "C");
+        assert!(matches!(
+            open_with_fallback(&info, open, enumerate),
+            Err(HidError::NoMatchingDevice { .. })
+        ));
+
+        let info = serial_device_info("/dev/hidraw0", "");
+        assert!(matches!(
+            open_with_fallback(&info, open, enumerate),
+            Err(HidError::DevicePathGone { .. })
+        ));
+
+        // Only the interface of the stale path may be opened instead
+        let mut info = serial_device_info("/dev/hidraw0", "B");
+        info.interface_number = 1;
+        assert!(matches!(
+            open_with_fallback(&info, open, enumerate),
+            Err(HidError::NoMatchingDevice { .. })
+        ));
+    }
+
+    #[test]
+    fn test_open_with_fallback_keeps_error_for_valid_path() {
+        let connected = vec![serial_device_info("/dev/hidraw3", "A")];
+        let info = serial_device_info("/dev/hidraw3", "A");
+        let result = open_with_fallback(
+            &info,
+            |_| {
+                Err(HidError::HidApiError {
+                    message: "permission denied".into(),
+                })
+            },
+            || Ok(connected.clone()),
+        );
+        assert!(
+            matches!(result, Err(HidError::HidApiError { message }) if message == "permission denied")
+        );
+    }
+
+    #[test]
+    fn test_find_indexed() {
+        let api = api_with(vec![
+            serial_device_info("/dev/hidraw3", "A"),
+            serial_device_info("/dev/hidraw4", "B"),
+        ]);
+
+        let info = serial_device_info("/dev/hidraw4", "");
+        assert_eq!(api.find_indexed(&info).unwrap().path, info.path);
+
+        let info = serial_device_info("/dev/hidraw0", "A");
+        let found = api.find_indexed(&info).unwrap();
+        assert_eq!(found.path.as_bytes(), b"/dev/hidraw3");
+
+        let info = serial_device_info("/dev/hidraw0", "");
+        assert!(api.find_indexed(&info).is_none());
+    }
+
+    #[test]
+    fn test_disable_device_discovery() {
+        let mut state = ContextState {
+            device_discovery: true,
+            init_state: InitState::NotInit,
+            users: 0,
+            shutdown: false,
+        };
+        assert!(state.disable_device_discovery());
+        assert!(!state.device_discovery);
+
+        // Initialized without discovery, disabling it again is fine
+        state.init_state = InitState::Init;
+        assert!(state.disable_device_discovery());
+
+        // Too late once initialized with discovery
+        state.device_discovery = true;
+        assert!(!state.disable_device_discovery());
+        assert!(state.device_discovery);
+    }
+
+    #[test]
+    fn test_context_release() {
+        let mut state = ContextState {
+            device_discovery: true,
+            init_state: InitState::Init,
+            users: 2,
+            shutdown: false,
+        };
+        assert!(!state.release());
+        assert!(matches!(state.init_state, InitState::Init));
+
+        state.users = 2;
+        state.shutdown = true;
+        assert!(!state.release());
+        assert!(state.release());
+        assert!(matches!(state.init_state, InitState::NotInit));
+        assert!(!state.shutdown);
+    }
+
+    #[test]
+    #[ignore = "needs a backend that can enumerate devices, e.g. hidraw with udev"]
+    fn test_add_devices_filtered() {
+        let mut api = HidApi::new().unwrap();
+        let filter = DeviceFilter::new().usage_page(0x0001);
+        let expected = api
+            .device_list_filtered(filter.clone())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        api.reset_devices().unwrap();
+        api.add_devices_filtered(filter).unwrap();
+        assert_eq!(api.device_list_owned(), expected);
+    }
+
+    #[test]
+    #[ignore = "needs a backend that can enumerate devices, e.g. hidraw with udev"]
+    fn test_shutdown_and_reinitialize() {
+        let api = HidApi::new().unwrap();
+        api.shutdown();
+
+        let api = HidApi::new().unwrap();
+        api.device_list().for_each(drop);
+        api.shutdown();
+    }
 }
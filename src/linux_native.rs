@@ -1,33 +1,52 @@
 //! This backend uses libudev to discover devices and then talks to hidraw directly
 
+#[cfg(not(feature = "linux-native-basic-udev"))]
+mod hotplug;
 mod ioctl;
 
 #[cfg(feature = "linux-native-basic-udev")]
 use basic_udev as udev;
 
+#[cfg(not(feature = "linux-native-basic-udev"))]
+pub use hotplug::{HotplugEvent, HotplugWatcher};
+
 use std::{
-    cell::{Cell, Ref, RefCell},
     ffi::{CStr, CString, OsStr, OsString},
-    fs::{File, OpenOptions},
-    io::{Cursor, Read, Seek, SeekFrom},
+    fs::{self, File, OpenOptions},
+    io::Read,
+    mem::MaybeUninit,
     os::{
-        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
-        unix::{ffi::OsStringExt, fs::OpenOptionsExt},
+        fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd},
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            fs::{FileTypeExt, OpenOptionsExt},
+        },
     },
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
 };
 
 use nix::{
     errno::Errno,
+    fcntl::{fcntl, FcntlArg, OFlag},
     poll::{poll, PollFd, PollFlags},
     sys::stat::{fstat, major, minor},
-    unistd::{read, write},
+    unistd::write,
 };
 
-use super::{BusType, DeviceInfo, HidDeviceBackendBase, HidError, HidResult, WcharString};
+use super::{
+    AccessMode, BusType, DeviceInfo, GroupId, HidDeviceBackendBase, HidError, HidResult, HidString,
+    MAX_REPORT_DESCRIPTOR_SIZE,
+};
+use crate::report_descriptor::{
+    check_descriptor_size, ReportDescriptor, ReportType, UsageIterator,
+};
 use ioctl::{
     hidraw_ioc_get_feature, hidraw_ioc_get_input, hidraw_ioc_grdescsize, hidraw_ioc_set_feature,
-    hidraw_ioc_set_output,
+    hidraw_ioc_set_output, usbdevfs_control, UsbCtrlTransfer,
 };
 
 // Bus values from linux/input.h
@@ -40,20 +59,9 @@ pub struct HidApiBackend;
 
 impl HidApiBackend {
     pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
-        // The C version assumes these can't fail, and they should only fail in case
-        // of memory allocation issues, at which point maybe we should panic
-        let mut enumerator = match udev::Enumerator::new() {
-            Ok(e) => e,
-            Err(_) => return Ok(Vec::new()),
-        };
-        enumerator.match_subsystem("hidraw").unwrap();
-        let scan = match enumerator.scan_devices() {
-            Ok(s) => s,
-            Err(_) => return Ok(Vec::new()),
-        };
-
-        let devices = scan
-            .filter_map(|device| device_to_hid_device_info(&device))
+        let devices = scan_hidraw(udev::Enumerator::new)?
+            .iter()
+            .filter_map(device_to_hid_device_info)
             .flatten()
             .filter(|device| vid == 0 || device.vendor_id == vid)
             .filter(|device| pid == 0 || device.product_id == pid)
@@ -73,11 +81,30 @@ impl HidApiBackend {
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         HidDevice::open_path(device_path)
     }
+
+    pub fn open_path_with(device_path: &CStr, access: AccessMode) -> HidResult<HidDevice> {
+        HidDevice::open_path_with(device_path, access)
+    }
+
+    pub fn is_present(device_path: &CStr) -> bool {
+        let path = Path::new(OsStr::from_bytes(device_path.to_bytes()));
+        fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_char_device())
+    }
 }
 
-fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo>> {
-    let mut infos = Vec::new();
+/// Find all hidraw devices, using an enumerator created by `new_enumerator`.
+///
+/// Failing to use udev is an error instead of an empty list, as udev is often not available in
+/// containers.
+fn scan_hidraw(
+    new_enumerator: impl FnOnce() -> std::io::Result<udev::Enumerator>,
+) -> HidResult<Vec<udev::Device>> {
+    let mut enumerator = new_enumerator()?;
+    enumerator.match_subsystem("hidraw")?;
+    Ok(enumerator.scan_devices()?.collect())
+}
 
+fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo>> {
     // We're given the hidraw device, but we actually want to go and check out
     // the info for the parent hid device.
     let device = match raw_device.parent_with_subsystem("hid") {
@@ -85,14 +112,11 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         _ => return None,
     };
 
-    let (bus, vid, pid) = match device
+    let (bus, vid, pid) = device
         .property_value("HID_ID")
         .and_then(|s| s.to_str())
-        .and_then(parse_hid_vid_pid)
-    {
-        Some(t) => t,
-        None => return None,
-    };
+        .and_then(parse_hid_vid_pid)?;
+    // The kernel numbers the buses differently than `BusType`
     let bus_type = match bus {
         BUS_USB => BusType::Usb,
         BUS_BLUETOOTH => BusType::Bluetooth,
@@ -100,14 +124,8 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         BUS_SPI => BusType::Spi,
         _ => return None,
     };
-    let name = match device.property_value("HID_NAME") {
-        Some(name) => name,
-        None => return None,
-    };
-    let serial = match device.property_value("HID_UNIQ") {
-        Some(serial) => serial,
-        None => return None,
-    };
+    let name = device.property_value("HID_NAME")?;
+    let serial = device.property_value("HID_UNIQ")?;
     let path = match raw_device
         .devnode()
         .map(|p| p.as_os_str().to_os_string().into_vec())
@@ -124,52 +142,56 @@ fn device_to_hid_device_info(raw_device: &udev::Device) -> Option<Vec<DeviceInfo
         product_id: pid,
         serial_number: osstring_to_string(serial.into()),
         release_number: 0,
-        manufacturer_string: WcharString::None,
-        product_string: WcharString::None,
+        manufacturer_string: HidString::None,
+        product_string: HidString::None,
         usage_page: 0,
         usage: 0,
         interface_number: -1,
         bus_type,
+        location_id: None,
+        bluetooth_address: None,
+        group_id: Some(group_id(&device)),
     };
 
     // USB has a bunch more information but everything else gets the same empty
     // manufacturer and the product we read from the property above.
     let info = match bus_type {
         BusType::Usb => fill_in_usb(raw_device, info, name),
-        _ => DeviceInfo {
-            manufacturer_string: WcharString::String("".into()),
-            product_string: osstring_to_string(name.into()),
-            ..info
-        },
+        _ => fill_in_non_usb(&device, info, name),
     };
 
-    if let Ok(descriptor) = HidrawReportDescriptor::from_syspath(raw_device.syspath()) {
-        let mut usages = descriptor.usages();
-
-        // Get the first usage page and usage for our current DeviceInfo
-        if let Some((usage_page, usage)) = usages.next() {
-            infos.push(DeviceInfo {
-                usage_page,
-                usage,
-                ..info
-            });
+    let infos = match HidrawReportDescriptor::from_syspath(raw_device.syspath()) {
+        Ok(descriptor) => split_by_usage(info, descriptor.usages()),
+        Err(_) => vec![info],
+    };
 
-            // Now we can create DeviceInfo for all the other usages
-            for (usage_page, usage) in usages {
-                let prev = infos.last().unwrap();
+    Some(infos)
+}
 
-                infos.push(DeviceInfo {
-                    usage_page,
-                    usage,
-                    ..prev.clone()
-                })
-            }
+/// Create one [`DeviceInfo`] per top-level usage of a device, like the other backends do.
+///
+/// All of them have the same path, so opening any of them opens the same hidraw node. Repeated
+/// usages are skipped, which keeps the path together with the usage page and usage unique.
+fn split_by_usage(info: DeviceInfo, usages: impl Iterator<Item = (u16, u16)>) -> Vec<DeviceInfo> {
+    let mut infos: Vec<DeviceInfo> = Vec::new();
+    for (usage_page, usage) in usages {
+        if infos
+            .iter()
+            .any(|i| (i.usage_page, i.usage) == (usage_page, usage))
+        {
+            continue;
         }
-    } else {
-        infos.push(info);
+        infos.push(DeviceInfo {
+            usage_page,
+            usage,
+            ..info.clone()
+        });
     }
 
-    Some(infos)
+    if infos.is_empty() {
+        infos.push(info);
+    }
+    infos
 }
 
 /// Fill in the extra information that's available for a USB device.
@@ -178,7 +200,7 @@ fn fill_in_usb(device: &udev::Device, info: DeviceInfo, name: &OsStr) -> DeviceI
         Ok(Some(dev)) => dev,
         Ok(None) | Err(_) => {
             return DeviceInfo {
-                manufacturer_string: WcharString::String("".into()),
+                manufacturer_string: HidString::String("".into()),
                 product_string: osstring_to_string(name.into()),
                 ..info
             }
@@ -203,168 +225,236 @@ fn fill_in_usb(device: &udev::Device, info: DeviceInfo, name: &OsStr) -> DeviceI
     }
 }
 
-#[derive(Default)]
-struct HidrawReportDescriptor(Vec<u8>);
-
-impl HidrawReportDescriptor {
-    /// Open and parse given the "base" sysfs of the device
-    pub fn from_syspath(syspath: &Path) -> HidResult<Self> {
-        let path = syspath.join("device/report_descriptor");
-        let mut f = File::open(path)?;
-        let mut buf = Vec::new();
-        f.read_to_end(&mut buf)?;
+/// Fill in the information of a Bluetooth, I2C or SPI device.
+///
+/// These buses have no descriptor with a release number in sysfs, but the kernel copies the
+/// version of the HID device into the input devices it creates for it. Devices that only have
+/// vendor defined collections get no input device, their release number stays 0.
+fn fill_in_non_usb(device: &udev::Device, info: DeviceInfo, name: &OsStr) -> DeviceInfo {
+    let release_number = fs::read_dir(device.syspath().join("input"))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|input| fs::read_to_string(input.path().join("uevent")).ok())
+        .find_map(|uevent| parse_input_version(&uevent))
+        .unwrap_or(0);
 
-        Ok(HidrawReportDescriptor(buf))
+    DeviceInfo {
+        release_number,
+        manufacturer_string: HidString::String("".into()),
+        product_string: osstring_to_string(name.into()),
+        ..info
     }
+}
 
-    /// Create a descriptor from a slice
-    ///
-    /// It returns an error if the value slice is too large for it to be a HID
-    /// descriptor
-    #[cfg_attr(not(test), allow(dead_code))]
-    pub fn from_slice(value: &[u8]) -> HidResult<Self> {
-        Ok(HidrawReportDescriptor(value.to_vec()))
+/// Parse the version out of the `PRODUCT=bus/vendor/product/version` property of an input device
+fn parse_input_version(uevent: &str) -> Option<u16> {
+    let product = uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("PRODUCT="))?;
+    let version = product.split('/').nth(3)?;
+    u16::from_str_radix(version.trim(), 16).ok()
+}
+
+/// Find a USB string descriptor by its index in the attributes of the USB device.
+///
+/// sysfs can't read arbitrary string descriptors, but it has the strings referenced by the
+/// descriptors of the device: manufacturer, product, serial number, configuration and interface
+/// names. Any other index is treated as not existing.
+fn usb_indexed_string(usb_device: &Path, index: u8) -> Option<String> {
+    if index == 0 {
+        // Index 0 holds the supported languages, not a string
+        return None;
     }
 
-    pub fn usages(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
-        UsageIterator {
-            usage_page: 0,
-            cursor: Cursor::new(&self.0),
+    let descriptors = fs::read(usb_device.join("descriptors")).ok()?;
+    let attribute = usb_string_attribute(&descriptors, index)?;
+    let attribute = match attribute {
+        UsbStringAttribute::Device(name) => usb_device.join(name),
+        UsbStringAttribute::Interface(config, interface) => {
+            let name = usb_device.file_name()?.to_string_lossy();
+            usb_device
+                .join(format!("{name}:{config}.{interface}"))
+                .join("interface")
         }
-    }
+    };
+    let string = fs::read_to_string(attribute).ok()?;
+    Some(string.trim_end_matches('\n').to_string())
 }
 
-/// Iterates over the values in a HidrawReportDescriptor
-struct UsageIterator<'a> {
-    usage_page: u16,
-    cursor: Cursor<&'a Vec<u8>>,
+#[derive(Debug, PartialEq)]
+enum UsbStringAttribute {
+    /// An attribute of the USB device
+    Device(&'static str),
+    /// The name of an interface, given by configuration value and interface number
+    Interface(u8, u8),
 }
 
-impl<'a> Iterator for UsageIterator<'a> {
-    type Item = (u16, u16);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let (usage_page, page) = match next_hid_usage(&mut self.cursor, self.usage_page) {
-            Some(n) => n,
-            None => return None,
-        };
+/// Find the sysfs attribute holding the string with `index`, from the raw descriptors of a device
+fn usb_string_attribute(descriptors: &[u8], index: u8) -> Option<UsbStringAttribute> {
+    const DESCRIPTOR_CONFIGURATION: u8 = 2;
+    const DESCRIPTOR_INTERFACE: u8 = 4;
 
-        self.usage_page = usage_page;
-        Some((usage_page, page))
+    let device = descriptors.get(..18)?;
+    for (offset, name) in [(14, "manufacturer"), (15, "product"), (16, "serial")] {
+        if device[offset] == index {
+            return Some(UsbStringAttribute::Device(name));
+        }
     }
-}
-
-// This comes from hidapi which apparently comes from Apple's implementation of
-// this
-fn next_hid_usage(cursor: &mut Cursor<&Vec<u8>>, mut usage_page: u16) -> Option<(u16, u16)> {
-    let mut usage = None;
-    let mut usage_pair = None;
-    let initial = cursor.position() == 0;
-
-    while let Some(Ok(key)) = cursor.bytes().next() {
-        // The amount to skip is calculated based off of the start of the
-        // iteration so we need to keep track of that.
-        let position = cursor.position() - 1;
-        let key_cmd = key & 0xfc;
-
-        let (data_len, key_size) = match hid_item_size(key, cursor) {
-            Some(v) => v,
-            None => return None,
-        };
 
-        match key_cmd {
-            // Usage Page 6.2.2.7 (Global)
-            0x4 => {
-                usage_page = match hid_report_bytes(cursor, data_len) {
-                    Ok(v) => v as u16,
-                    Err(_) => break,
-                }
-            }
-            // Usage 6.2.2.8 (Local)
-            0x8 => {
-                usage = match hid_report_bytes(cursor, data_len) {
-                    Ok(v) => Some(v as u16),
-                    Err(_) => break,
-                }
-            }
-            // Collection 6.2.2.4 (Main)
-            0xa0 => {
-                // Usage is a Local Item, unset it
-                if let Some(u) = usage.take() {
-                    usage_pair = Some((usage_page, u))
+    let mut config = 0;
+    let mut rest = &descriptors[18..];
+    while let [len, kind, ..] = *rest {
+        if len < 2 {
+            return None;
+        }
+        let descriptor = rest.get(..len as usize)?;
+        match (kind, descriptor) {
+            (DESCRIPTOR_CONFIGURATION, [_, _, _, _, _, value, string, ..]) => {
+                config = *value;
+                if *string == index {
+                    return Some(UsbStringAttribute::Device("configuration"));
                 }
             }
-            // Input 6.2.2.4 (Main)
-		        0x80 |
-            // Output 6.2.2.4 (Main)
-		        0x90 |
-            // Feature 6.2.2.4 (Main)
-		        0xb0 |
-            // End Collection 6.2.2.4 (Main)
-	    0xc0  =>  {
-		// Usage is a Local Item, unset it
-                usage.take();
+            (DESCRIPTOR_INTERFACE, [_, _, number, _, _, _, _, _, string, ..])
+                if *string == index =>
+            {
+                return Some(UsbStringAttribute::Interface(config, *number));
             }
             _ => {}
         }
+        rest = &rest[len as usize..];
+    }
+    None
+}
 
-        if cursor
-            .seek(SeekFrom::Start(position + (data_len + key_size) as u64))
-            .is_err()
-        {
-            return None;
-        }
+/// Find the device node of a device from its directory in sysfs, e.g. `/sys/class/hidraw/hidraw0`
+/// or `/sys/dev/char/<major>:<minor>`
+fn devnode_from_syspath(syspath: &Path) -> HidResult<PathBuf> {
+    let uevent = fs::read_to_string(syspath.join("uevent")).map_err(|e| HidError::HidApiError {
+        message: format!("failed to read the sysfs device {}: {e}", syspath.display()),
+    })?;
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("DEVNAME="))
+        .map(|name| Path::new("/dev").join(name))
+        .ok_or_else(|| HidError::HidApiError {
+            message: format!("the sysfs device {} has no device node", syspath.display()),
+        })
+}
 
-        if let Some((usage_page, usage)) = usage_pair {
-            return Some((usage_page, usage));
-        }
+/// Convert the error of opening the device node at `path`
+fn open_error(path: &str, e: std::io::Error) -> HidError {
+    match Errno::from_i32(e.raw_os_error().unwrap_or(0)) {
+        Errno::EACCES | Errno::EPERM => HidError::PermissionDenied {
+            path: path.to_string(),
+        },
+        _ => HidError::HidApiError {
+            message: format!("failed to open device with path {path}: {e}"),
+        },
     }
+}
 
-    if let (true, Some(usage)) = (initial, usage) {
-        return Some((usage_page, usage));
+/// Timeout of the control transfers for string descriptors, in milliseconds
+const USB_CONTROL_TIMEOUT: u32 = 1000;
+
+/// Set up a GET_DESCRIPTOR request for the string descriptor `index` in the language `langid`,
+/// reading into `buf`.
+fn string_descriptor_request(index: u8, langid: u16, buf: &mut [u8]) -> UsbCtrlTransfer {
+    const USB_DIR_IN: u8 = 0x80;
+    const USB_REQ_GET_DESCRIPTOR: u8 = 0x06;
+    const USB_DT_STRING: u16 = 0x03;
+
+    UsbCtrlTransfer {
+        request_type: USB_DIR_IN,
+        request: USB_REQ_GET_DESCRIPTOR,
+        value: (USB_DT_STRING << 8) | u16::from(index),
+        index: langid,
+        length: u16::try_from(buf.len()).unwrap_or(u16::MAX),
+        timeout: USB_CONTROL_TIMEOUT,
+        data: buf.as_mut_ptr().cast(),
     }
+}
 
-    None
+/// Get the UTF-16 code units of a string descriptor, which are the language IDs for index 0
+fn string_descriptor_units(descriptor: &[u8]) -> Option<Vec<u16>> {
+    const USB_DT_STRING: u8 = 0x03;
+
+    match *descriptor {
+        [len, USB_DT_STRING, ..] if len >= 2 => {
+            let end = usize::from(len).min(descriptor.len());
+            Some(
+                descriptor[2..end]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
 }
 
-/// Gets the size of the HID item at the given position
+/// Identify the physical device by its sysfs path, which is the USB device for USB interfaces.
 ///
-/// Returns data_len and key_size when successful
-fn hid_item_size(key: u8, cursor: &mut Cursor<&Vec<u8>>) -> Option<(usize, usize)> {
-    // Long Item. Next byte contains the length of the data section.
-    if (key & 0xf0) == 0xf0 {
-        if let Some(Ok(len)) = cursor.bytes().next() {
-            return Some((len.into(), 3));
-        }
+/// Devices on other buses have a single HID device each.
+fn group_id(hid_device: &udev::Device) -> GroupId {
+    let syspath = match hid_device.parent_with_subsystem_devtype("usb", "usb_device") {
+        Ok(Some(usb_dev)) => usb_dev.syspath().to_owned(),
+        Ok(None) | Err(_) => hid_device.syspath().to_owned(),
+    };
+    GroupId::new(syspath.to_string_lossy())
+}
 
-        // Malformed report
-        return None;
+#[derive(Default)]
+struct HidrawReportDescriptor(Vec<u8>);
+
+impl HidrawReportDescriptor {
+    /// Open and parse given the "base" sysfs of the device
+    pub fn from_syspath(syspath: &Path) -> HidResult<Self> {
+        let path = syspath.join("device/report_descriptor");
+        let f = File::open(path)?;
+        // Read one byte more than allowed to detect oversized descriptors without reading
+        // everything a broken device offers
+        let mut buf = Vec::new();
+        f.take(MAX_REPORT_DESCRIPTOR_SIZE as u64 + 1)
+            .read_to_end(&mut buf)?;
+        check_descriptor_size(buf.len())?;
+
+        Ok(HidrawReportDescriptor(buf))
+    }
+
+    /// Create a descriptor from a slice
+    ///
+    /// It returns an error if the value slice is too large for it to be a HID
+    /// descriptor
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn from_slice(value: &[u8]) -> HidResult<Self> {
+        check_descriptor_size(value.len())?;
+        Ok(HidrawReportDescriptor(value.to_vec()))
     }
 
-    // Short Item. Bottom two bits contains the size code
-    match key & 0x03 {
-        v @ 0..=2 => Some((v.into(), 1)),
-        3 => Some((4, 1)),
-        _ => unreachable!(), // & 0x03 means this can't happen
+    pub fn usages(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        UsageIterator::new(&self.0)
     }
 }
 
-/// Get the bytes from a HID report descriptor
-///
-/// Must only be called with `num_bytes` 0, 1, 2 or 4.
-fn hid_report_bytes(cursor: &mut Cursor<&Vec<u8>>, num_bytes: usize) -> HidResult<u32> {
-    let mut bytes: [u8; 4] = [0; 4];
-    cursor.read_exact(&mut bytes[..num_bytes])?;
-
-    Ok(u32::from_le_bytes(bytes))
+/// Pad a report with zeros to `len` bytes in `scratch`, longer reports are left as they are.
+fn pad_report<'a>(data: &'a [u8], len: usize, scratch: &'a mut Vec<u8>) -> &'a [u8] {
+    if data.len() >= len {
+        return data;
+    }
+    scratch.clear();
+    scratch.extend_from_slice(data);
+    scratch.resize(len, 0);
+    scratch
 }
 
-/// Get the attribute from the device and convert it into a [`WcharString`].
-fn attribute_as_wchar(dev: &udev::Device, attr: &str) -> WcharString {
+/// Get the attribute from the device and convert it into a [`HidString`].
+fn attribute_as_wchar(dev: &udev::Device, attr: &str) -> HidString {
     dev.attribute_value(attr)
         .map(Into::into)
         .map(osstring_to_string)
-        .unwrap_or(WcharString::None)
+        .unwrap_or(HidString::None)
 }
 
 /// Get the attribute from the device and convert it into a i32
@@ -385,10 +475,10 @@ fn attribute_as_u16(dev: &udev::Device, attr: &str) -> Option<u16> {
         .and_then(|v| u16::from_str_radix(v, 16).ok())
 }
 
-/// Convert a [`OsString`] into a [`WcharString`]
-fn osstring_to_string(s: OsString) -> WcharString {
+/// Convert a [`OsString`] into a [`HidString`]
+fn osstring_to_string(s: OsString) -> HidString {
     match s.into_string() {
-        Ok(s) => WcharString::String(s),
+        Ok(s) => HidString::String(s),
         Err(_) => panic!("udev strings should always be utf8"),
     }
 }
@@ -407,51 +497,98 @@ fn parse_hid_vid_pid(s: &str) -> Option<(u16, u16, u16)> {
     Some((devtype, vendor, product))
 }
 
+/// The read and write flags to open a device with
+fn access_flags(access: AccessMode) -> (bool, bool) {
+    match access {
+        AccessMode::ReadWrite => (true, true),
+        AccessMode::ReadOnly => (true, false),
+        AccessMode::WriteOnly => (false, true),
+    }
+}
+
 /// Object for accessing the HID device
 pub struct HidDevice {
-    blocking: Cell<bool>,
+    blocking: AtomicBool,
     fd: OwnedFd,
-    info: RefCell<Option<DeviceInfo>>,
+    info: Mutex<Option<DeviceInfo>>,
+    report_descriptor: Mutex<Option<ReportDescriptor>>,
+    /// The error of the last failed operation, cleared by the next successful one
+    last_error: Mutex<Option<HidError>>,
+    /// Whether `last_error` is set, so successful operations only lock it to clear it
+    has_error: AtomicBool,
+    /// Reused to pad feature reports, so sending one doesn't allocate every time
+    scratch: Mutex<Vec<u8>>,
 }
 
-unsafe impl Send for HidDevice {}
-
 // API for the library to call us, or for internal uses
 impl HidDevice {
     pub(crate) fn open(vid: u16, pid: u16, sn: Option<&str>) -> HidResult<Self> {
-        for device in HidApiBackend::get_hid_device_info_vector(0, 0)?
-            .iter()
-            .filter(|device| device.vendor_id == vid && device.product_id == pid)
-        {
-            match (sn, &device.serial_number) {
-                (None, _) => return Self::open_path(&device.path),
-                (Some(sn), WcharString::String(serial_number)) if sn == serial_number => {
-                    return Self::open_path(&device.path)
-                }
-                _ => continue,
-            };
-        }
+        Self::open_matching(
+            vid,
+            pid,
+            sn,
+            HidApiBackend::get_hid_device_info_vector,
+            Self::open_path,
+        )
+    }
 
-        Err(HidError::HidApiError {
-            message: "device not found".into(),
-        })
+    /// Open the first enumerated device with the given IDs and serial number.
+    ///
+    /// The device keeps the [`DeviceInfo`] found by the enumeration, so reading its strings does
+    /// not have to enumerate the devices again.
+    fn open_matching(
+        vid: u16,
+        pid: u16,
+        sn: Option<&str>,
+        enumerate: impl FnOnce(u16, u16) -> HidResult<Vec<DeviceInfo>>,
+        open_path: impl FnOnce(&CStr) -> HidResult<HidDevice>,
+    ) -> HidResult<Self> {
+        let info = enumerate(vid, pid)?
+            .into_iter()
+            .filter(|device| device.vendor_id == vid && device.product_id == pid)
+            .find(|device| match (sn, &device.serial_number) {
+                (None, _) => true,
+                (Some(sn), HidString::String(serial_number)) => sn == serial_number,
+                _ => false,
+            })
+            .ok_or_else(|| HidError::HidApiError {
+                message: "device not found".into(),
+            })?;
+
+        let mut device = open_path(&info.path)?;
+        *device.info.get_mut().unwrap() = Some(info);
+        Ok(device)
     }
 
     pub(crate) fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
+        Self::open_path_with(device_path, AccessMode::ReadWrite)
+    }
+
+    pub(crate) fn open_path_with(device_path: &CStr, access: AccessMode) -> HidResult<HidDevice> {
         // Paths on Linux can be anything but devnode paths are going to be ASCII
         let path = device_path.to_str().expect("path must be utf-8");
+        let devnode;
+        let path = match Path::new(path) {
+            p if p.starts_with("/dev") => path,
+            p if p.starts_with("/sys") => {
+                devnode = devnode_from_syspath(p)?;
+                devnode.to_str().expect("path must be utf-8")
+            }
+            _ => {
+                return Err(HidError::HidApiError {
+                    message: format!("{path} is neither a device node in /dev nor a sysfs path"),
+                })
+            }
+        };
+        let (read, write) = access_flags(access);
         let fd: OwnedFd = match OpenOptions::new()
-            .read(true)
-            .write(true)
+            .read(read)
+            .write(write)
             .custom_flags(libc::O_CLOEXEC | libc::O_NONBLOCK)
             .open(path)
         {
             Ok(f) => f.into(),
-            Err(e) => {
-                return Err(HidError::HidApiError {
-                    message: format!("failed to open device with path {path}: {e}"),
-                });
-            }
+            Err(e) => return Err(open_error(path, e)),
         };
 
         let mut size = 0_i32;
@@ -461,161 +598,310 @@ impl HidDevice {
             });
         }
 
-        Ok(Self {
-            blocking: Cell::new(true),
-            fd,
-            info: RefCell::new(None),
-        })
+        Ok(Self::from_hidraw_fd(fd))
     }
 
-    fn info(&self) -> HidResult<Ref<DeviceInfo>> {
-        if self.info.borrow().is_none() {
-            let info = self.get_device_info()?;
-            self.info.replace(Some(info));
+    /// Use a `hidraw` file descriptor that was opened elsewhere.
+    pub(crate) fn open_fd(fd: OwnedFd) -> HidResult<HidDevice> {
+        let mut size = 0_i32;
+        if let Err(e) = unsafe { hidraw_ioc_grdescsize(fd.as_raw_fd(), &mut size) } {
+            return Err(HidError::HidApiError {
+                message: format!("ioctl(GRDESCSIZE) error, not a HIDRAW device?: {e}"),
+            });
         }
 
-        let info = self.info.borrow();
-        Ok(Ref::map(info, |i: &Option<DeviceInfo>| i.as_ref().unwrap()))
-    }
-}
+        // Reads poll first, but must not block if another reader took the report
+        let flags = OFlag::from_bits_retain(fcntl(fd.as_raw_fd(), FcntlArg::F_GETFL)?);
+        fcntl(fd.as_raw_fd(), FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))?;
 
-impl AsFd for HidDevice {
-    fn as_fd(&self) -> BorrowedFd {
-        self.fd.as_fd()
+        Ok(Self::from_hidraw_fd(fd))
     }
-}
 
-impl HidDeviceBackendBase for HidDevice {
-    fn write(&self, data: &[u8]) -> HidResult<usize> {
-        if data.is_empty() {
-            return Err(HidError::InvalidZeroSizeData);
+    pub(crate) fn from_hidraw_fd(fd: OwnedFd) -> Self {
+        Self {
+            blocking: AtomicBool::new(true),
+            fd,
+            info: Mutex::new(None),
+            report_descriptor: Mutex::new(None),
+            last_error: Mutex::new(None),
+            has_error: AtomicBool::new(false),
+            scratch: Mutex::new(Vec::new()),
         }
+    }
 
-        Ok(write(self.fd.as_raw_fd(), data)?)
+    /// The sysfs path of the hidraw device
+    fn syspath(&self) -> HidResult<PathBuf> {
+        // What we have is a descriptor to a file in /dev but we need a syspath
+        // so we get the major/minor from there and generate our syspath
+        let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
+        Ok(format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into())
     }
 
-    fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
-        // If the caller asked for blocking, -1 makes us wait forever
-        let timeout = if self.blocking.get() { -1 } else { 0 };
-        self.read_timeout(buf, timeout)
+    /// Read a string descriptor from the USB device through usbfs, which needs write access to
+    /// the device node in /dev/bus/usb. Returns `None` if the device has no such string.
+    fn usb_string_descriptor(&self, index: u8, langid: u16) -> HidResult<Option<Vec<u16>>> {
+        let device = udev::Device::from_syspath(&self.syspath()?)?;
+        let devnode = device
+            .parent_with_subsystem_devtype("usb", "usb_device")?
+            .and_then(|usb_dev| usb_dev.devnode().map(Path::to_path_buf))
+            .ok_or_else(|| HidError::HidApiError {
+                message: "string descriptors are only available for USB devices".to_string(),
+            })?;
+        let usb_dev = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_CLOEXEC)
+            .open(devnode)?;
+
+        let mut buf = [0u8; 255];
+        let mut request = string_descriptor_request(index, langid, &mut buf);
+        match unsafe { usbdevfs_control(usb_dev.as_raw_fd(), &mut request) } {
+            Ok(len) => Ok(string_descriptor_units(&buf[..len as usize])),
+            // Devices stall the request for strings they don't have
+            Err(Errno::EPIPE) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
-        let pollfd = PollFd::new(&self.fd, PollFlags::POLLIN);
-        let res = poll(&mut [pollfd], timeout)?;
+    /// Wait for a report and read it, returns `None` if the timeout expired.
+    fn read_report(&self, buf: &mut [MaybeUninit<u8>], timeout: i32) -> HidResult<Option<usize>> {
+        // `PollFd` is `Copy`, so the events have to be read from the polled array
+        let mut pollfds = [PollFd::new(&self.fd, PollFlags::POLLIN)];
+        let res = poll(&mut pollfds, timeout)?;
 
         if res == 0 {
-            return Ok(0);
+            return Ok(None);
         }
 
-        let events = pollfd
+        let events = pollfds[0]
             .revents()
             .map(|e| e.intersects(PollFlags::POLLERR | PollFlags::POLLHUP | PollFlags::POLLNVAL));
 
         if events.is_none() || events == Some(true) {
-            return Err(HidError::HidApiError {
-                message: "unexpected poll error (device disconnected)".into(),
-            });
+            return Err(HidError::Disconnected);
         }
 
-        match read(self.fd.as_raw_fd(), buf) {
-            Ok(w) => Ok(w),
-            Err(Errno::EAGAIN) | Err(Errno::EINPROGRESS) => Ok(0),
+        // `nix::unistd::read` only takes initialized buffers
+        let res = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        match Errno::result(res) {
+            Ok(w) => Ok(Some(w as usize)),
+            Err(Errno::EAGAIN) | Err(Errno::EINPROGRESS) => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 
-    fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
-        if data.is_empty() {
-            return Err(HidError::InvalidZeroSizeData);
+    /// Run an operation on the device, remembering its error or clearing the last one.
+    fn track<T>(&self, op: impl FnOnce() -> HidResult<T>) -> HidResult<T> {
+        let res = op();
+        match &res {
+            Err(e) => {
+                let mut last_error = self.last_error.lock().unwrap();
+                *last_error = Some(e.duplicate());
+                self.has_error.store(true, Ordering::Relaxed);
+            }
+            Ok(_) if self.has_error.swap(false, Ordering::Relaxed) => {
+                *self.last_error.lock().unwrap() = None;
+            }
+            Ok(_) => {}
         }
+        res
+    }
 
-        let res = match unsafe { hidraw_ioc_set_feature(self.fd.as_raw_fd(), data) } {
-            Ok(n) => n as usize,
-            Err(e) => {
-                return Err(HidError::HidApiError {
-                    message: format!("ioctl (GFEATURE): {e}"),
-                })
+    /// Check the report ID of a feature report against the report descriptor, which is only
+    /// parsed once, and get the length of the report including the report ID.
+    ///
+    /// Returns `None` without checking anything if the descriptor can't be read or parsed.
+    fn feature_report_len(&self, report_id: u8) -> HidResult<Option<usize>> {
+        let mut descriptor = self.report_descriptor.lock().unwrap();
+        if descriptor.is_none() {
+            *descriptor = self
+                .syspath()
+                .and_then(|syspath| HidrawReportDescriptor::from_syspath(&syspath))
+                .and_then(|raw| ReportDescriptor::parse(&raw.0))
+                .ok();
+        }
+        match descriptor.as_ref() {
+            Some(descriptor) => {
+                let report = descriptor.check_report_id(ReportType::Feature, report_id)?;
+                Ok(Some(report.byte_len() + 1))
             }
-        };
+            None => Ok(None),
+        }
+    }
 
-        if res != data.len() {
-            return Err(HidError::IncompleteSendError {
-                sent: res,
-                all: data.len(),
-            });
+    /// Read a string from the [`DeviceInfo`], which is only looked up once.
+    fn info_string(
+        &self,
+        string: impl FnOnce(&DeviceInfo) -> Option<&str>,
+    ) -> HidResult<Option<String>> {
+        let mut info = self.info.lock().unwrap();
+        if info.is_none() {
+            *info = Some(self.get_device_info()?);
         }
+        Ok(info.as_ref().and_then(string).map(str::to_string))
+    }
+}
 
-        Ok(())
+impl AsFd for HidDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
     }
+}
 
-    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
-        let res = match unsafe { hidraw_ioc_get_feature(self.fd.as_raw_fd(), buf) } {
-            Ok(n) => n as usize,
-            Err(e) => {
-                return Err(HidError::HidApiError {
-                    message: format!("ioctl (GFEATURE): {e}"),
-                })
+impl HidDeviceBackendBase for HidDevice {
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        self.track(|| {
+            if data.is_empty() {
+                return Err(HidError::InvalidZeroSizeData);
             }
+
+            Ok(write(self.fd.as_raw_fd(), data)?)
+        })
+    }
+
+    fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
+        // If the caller asked for blocking, -1 makes us wait forever
+        let timeout = if self.blocking.load(Ordering::Relaxed) {
+            -1
+        } else {
+            0
         };
+        self.read_timeout(buf, timeout)
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        // SAFETY: Only the report read from the device is written into the buffer
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.read_timeout_uninit(buf, timeout)
+    }
 
-        Ok(res)
+    fn read_timeout_strict(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        // SAFETY: Only the report read from the device is written into the buffer
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.track(|| self.read_report(buf, timeout)?.ok_or(HidError::Timeout))
     }
 
-    fn send_output_report(&self, buf: &[u8]) -> HidResult<()> {
-        let res = match unsafe { hidraw_ioc_set_output(self.fd.as_raw_fd(), buf) } {
-            Ok(n) => n,
-            Err(e) => {
-                return Err(HidError::HidApiError {
-                    message: format!("ioctl (SOUTPUT): {e}"),
+    fn read_timeout_uninit(&self, buf: &mut [MaybeUninit<u8>], timeout: i32) -> HidResult<usize> {
+        self.track(|| Ok(self.read_report(buf, timeout)?.unwrap_or(0)))
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
+        self.track(|| {
+            if data.is_empty() {
+                return Err(HidError::InvalidZeroSizeData);
+            }
+
+            // hidraw sends the report as given, so a report ID the device doesn't know or a
+            // missing leading 0 for unnumbered reports would only fail in the device
+            let len = self.feature_report_len(data[0])?.unwrap_or(0);
+            let mut scratch = self.scratch.lock().unwrap();
+            let data = pad_report(data, len, &mut scratch);
+            let res = match unsafe { hidraw_ioc_set_feature(self.fd.as_raw_fd(), data) } {
+                Ok(n) => n as usize,
+                Err(e) => {
+                    return Err(HidError::HidApiError {
+                        message: format!("ioctl (SFEATURE): {e}"),
+                    })
+                }
+            };
+
+            if res != data.len() {
+                return Err(HidError::IncompleteSendError {
+                    sent: res,
+                    all: data.len(),
                 });
             }
-        };
 
-        if res as usize != buf.len() {
-            return Err(HidError::IncompleteSendError {
-                sent: res as usize,
-                all: buf.len(),
-            });
-        }
+            Ok(())
+        })
+    }
 
-        Ok(())
+    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        self.track(|| {
+            if buf.is_empty() {
+                return Err(HidError::InvalidZeroSizeData);
+            }
+
+            self.feature_report_len(buf[0])?;
+            let res = match unsafe { hidraw_ioc_get_feature(self.fd.as_raw_fd(), buf) } {
+                Ok(n) => n as usize,
+                Err(e) => {
+                    return Err(HidError::HidApiError {
+                        message: format!("ioctl (GFEATURE): {e}"),
+                    })
+                }
+            };
+
+            Ok(res)
+        })
+    }
+
+    fn send_output_report(&self, buf: &[u8]) -> HidResult<()> {
+        self.track(|| {
+            let res = match unsafe { hidraw_ioc_set_output(self.fd.as_raw_fd(), buf) } {
+                Ok(n) => n,
+                // HIDIOCSOUTPUT was added in Linux 5.11, older kernels only send output reports
+                // with a write, which uses the interrupt OUT endpoint if the device has one
+                Err(Errno::ENOTTY) => self.write(buf)? as _,
+                Err(e) => {
+                    return Err(HidError::HidApiError {
+                        message: format!("ioctl (SOUTPUT): {e}"),
+                    });
+                }
+            };
+
+            if res as usize != buf.len() {
+                return Err(HidError::IncompleteSendError {
+                    sent: res as usize,
+                    all: buf.len(),
+                });
+            }
+
+            Ok(())
+        })
     }
 
     fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize> {
-        match unsafe { hidraw_ioc_get_input(self.fd.as_raw_fd(), data) } {
-            Ok(n) => Ok(n as usize),
-            Err(e) => Err(HidError::HidApiError {
-                message: format!("ioctl (GINPUT): {e}"),
-            }),
-        }
+        self.track(|| {
+            // The report ID in the first byte selects the report
+            if data.is_empty() {
+                return Err(HidError::InvalidZeroSizeData);
+            }
+
+            match unsafe { hidraw_ioc_get_input(self.fd.as_raw_fd(), data) } {
+                Ok(n) => Ok(n as usize),
+                Err(e) => Err(HidError::HidApiError {
+                    message: format!("ioctl (GINPUT): {e}"),
+                }),
+            }
+        })
     }
 
     fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
-        self.blocking.set(blocking);
+        self.blocking.store(blocking, Ordering::Relaxed);
         Ok(())
     }
 
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
-        let info = self.info()?;
-        Ok(info.manufacturer_string().map(str::to_string))
+        self.info_string(DeviceInfo::manufacturer_string)
     }
 
     fn get_product_string(&self) -> HidResult<Option<String>> {
-        let info = self.info()?;
-        Ok(info.product_string().map(str::to_string))
+        self.info_string(DeviceInfo::product_string)
     }
 
     fn get_serial_number_string(&self) -> HidResult<Option<String>> {
-        let info = self.info()?;
-        Ok(info.serial_number().map(str::to_string))
+        self.info_string(DeviceInfo::serial_number)
+    }
+
+    fn refresh_strings(&self) -> HidResult<()> {
+        let info = self.get_device_info()?;
+        *self.info.lock().unwrap() = Some(info);
+        Ok(())
     }
 
     fn get_device_info(&self) -> HidResult<DeviceInfo> {
-        // What we have is a descriptor to a file in /dev but we need a syspath
-        // so we get the major/minor from there and generate our syspath
-        let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
-        let syspath: PathBuf = format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into();
+        let syspath = self.syspath()?;
 
         // The clone is a bit silly but we can't implement Copy. Maybe it's not
         // much worse than doing the conversion to Rust from interacting with C.
@@ -628,14 +914,61 @@ impl HidDeviceBackendBase for HidDevice {
         }
     }
 
+    fn get_indexed_string(&self, index: i32) -> HidResult<Option<String>> {
+        self.track(|| {
+            let device = udev::Device::from_syspath(&self.syspath()?)?;
+            let usb_dev = match device.parent_with_subsystem_devtype("usb", "usb_device")? {
+                Some(dev) => dev,
+                None => {
+                    return Err(HidError::HidApiError {
+                        message: "get_indexed_string: only supported for USB devices".to_string(),
+                    })
+                }
+            };
+            Ok(u8::try_from(index)
+                .ok()
+                .and_then(|index| usb_indexed_string(usb_dev.syspath(), index)))
+        })
+    }
+
+    fn get_indexed_string_lang(&self, index: i32, langid: u16) -> HidResult<Option<String>> {
+        self.track(|| {
+            // Index 0 holds the supported languages, not a string
+            let index = match u8::try_from(index) {
+                Ok(index) if index > 0 => index,
+                _ => return Ok(None),
+            };
+            Ok(self
+                .usb_string_descriptor(index, langid)?
+                .map(|units| String::from_utf16_lossy(&units)))
+        })
+    }
+
+    fn supported_languages(&self) -> HidResult<Vec<u16>> {
+        self.track(|| Ok(self.usb_string_descriptor(0, 0)?.unwrap_or_default()))
+    }
+
     fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
-        let devnum = fstat(self.fd.as_raw_fd())?.st_rdev;
-        let syspath: PathBuf = format!("/sys/dev/char/{}:{}", major(devnum), minor(devnum)).into();
+        self.track(|| {
+            let syspath = self.syspath()?;
+
+            let descriptor = HidrawReportDescriptor::from_syspath(&syspath)?;
+            let min_size = buf.len().min(descriptor.0.len());
+            buf[..min_size].copy_from_slice(&descriptor.0[..min_size]);
+            Ok(min_size)
+        })
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.fd.as_raw_fd())
+    }
 
-        let descriptor = HidrawReportDescriptor::from_syspath(&syspath)?;
-        let min_size = buf.len().min(descriptor.0.len());
-        buf[..min_size].copy_from_slice(&descriptor.0[..min_size]);
-        Ok(min_size)
+    fn last_error(&self) -> Option<HidError> {
+        self.last_error
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(HidError::duplicate)
     }
 }
 
@@ -650,6 +983,18 @@ mod test {
         assert_eq!(Some((0x11, 0x17, 0x18)), parse_hid_vid_pid("11:0017:00018"));
     }
 
+    #[test]
+    fn test_parse_input_version() {
+        let bluetooth = "PRODUCT=5/46d/b01a/11b\nNAME=\"MX Master\"\nPHYS=\"00:11:22:33:44:55\"\n";
+        assert_eq!(parse_input_version(bluetooth), Some(0x011b));
+        let i2c = "PRODUCT=18/6cb/cd7e/100\nPROP=5\n";
+        assert_eq!(parse_input_version(i2c), Some(0x0100));
+
+        assert_eq!(parse_input_version("NAME=\"no product\"\n"), None);
+        assert_eq!(parse_input_version("PRODUCT=5/46d/b01a\n"), None);
+        assert_eq!(parse_input_version("PRODUCT=5/46d/b01a/xyz\n"), None);
+    }
+
     #[test]
     fn test_hidraw_report_descriptor_1() {
         let data = include_bytes!("../tests/assets/mouse1.data");
@@ -668,4 +1013,450 @@ mod test {
         let expected = vec![(1, 2), (1, 1), (1, 128), (12, 1), (65280, 14)];
         assert_eq!(expected, values);
     }
+
+    #[test]
+    fn test_hidraw_report_descriptor_size() {
+        let data = vec![0u8; MAX_REPORT_DESCRIPTOR_SIZE + 1];
+        assert!(HidrawReportDescriptor::from_slice(&data[..MAX_REPORT_DESCRIPTOR_SIZE]).is_ok());
+        assert!(matches!(
+            HidrawReportDescriptor::from_slice(&data),
+            Err(HidError::ReportDescriptorTooLarge { .. })
+        ));
+
+        let syspath = std::env::temp_dir().join(format!("hidapi-desc-{}", std::process::id()));
+        fs::create_dir_all(syspath.join("device")).unwrap();
+        let path = syspath.join("device/report_descriptor");
+        fs::write(&path, &data[..MAX_REPORT_DESCRIPTOR_SIZE]).unwrap();
+        let desc = HidrawReportDescriptor::from_syspath(&syspath).unwrap();
+        assert_eq!(desc.0.len(), MAX_REPORT_DESCRIPTOR_SIZE);
+        fs::write(&path, vec![0u8; 2 * MAX_REPORT_DESCRIPTOR_SIZE]).unwrap();
+        assert!(matches!(
+            HidrawReportDescriptor::from_syspath(&syspath),
+            Err(HidError::ReportDescriptorTooLarge { size }) if size == MAX_REPORT_DESCRIPTOR_SIZE + 1
+        ));
+        fs::remove_dir_all(syspath).unwrap();
+    }
+
+    #[test]
+    fn test_split_by_usage() {
+        let data = include_bytes!("../tests/assets/mouse2.data");
+        let desc = HidrawReportDescriptor::from_slice(&data[..]).expect("descriptor");
+        let info = DeviceInfo {
+            path: CString::new("/dev/hidraw0").unwrap(),
+            vendor_id: 0x046d,
+            product_id: 0xc077,
+            serial_number: HidString::None,
+            release_number: 0,
+            manufacturer_string: HidString::None,
+            product_string: HidString::None,
+            usage_page: 0,
+            usage: 0,
+            interface_number: 0,
+            bus_type: BusType::Usb,
+            location_id: None,
+            bluetooth_address: None,
+            group_id: None,
+        };
+
+        let infos = split_by_usage(info.clone(), desc.usages());
+        let usages = infos
+            .iter()
+            .map(|i| (i.usage_page, i.usage))
+            .collect::<Vec<_>>();
+        assert_eq!(usages, vec![(1, 2), (1, 1), (1, 128), (12, 1), (65280, 14)]);
+        assert!(infos.iter().all(|i| i.path == info.path));
+
+        let repeated = [(1, 2), (12, 1), (1, 2)].into_iter();
+        assert_eq!(split_by_usage(info.clone(), repeated).len(), 2);
+        assert_eq!(split_by_usage(info, std::iter::empty()).len(), 1);
+    }
+
+    #[test]
+    fn test_enumerator_failure() {
+        let result = scan_hidraw(|| Err(std::io::ErrorKind::NotFound.into()));
+        assert!(matches!(
+            result,
+            Err(HidError::IoError { error }) if error.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_is_present() {
+        let path = |path: &str| CString::new(path).unwrap();
+        assert!(HidApiBackend::is_present(&path("/dev/null")));
+        assert!(!HidApiBackend::is_present(&path("/dev/hidraw-bogus")));
+        assert!(!HidApiBackend::is_present(&path("/dev")));
+    }
+
+    #[test]
+    fn test_access_flags() {
+        assert_eq!(access_flags(AccessMode::ReadWrite), (true, true));
+        assert_eq!(access_flags(AccessMode::ReadOnly), (true, false));
+        assert_eq!(access_flags(AccessMode::WriteOnly), (false, true));
+    }
+
+    #[test]
+    fn test_open_path_with_rejects_non_hidraw() {
+        let path = CString::new("/dev/null").unwrap();
+        for access in [AccessMode::ReadOnly, AccessMode::WriteOnly] {
+            assert!(matches!(
+                HidDevice::open_path_with(&path, access),
+                Err(HidError::HidApiError { message }) if message.contains("not a HIDRAW device")
+            ));
+        }
+    }
+
+    #[test]
+    fn test_devnode_from_syspath() {
+        let root = std::env::temp_dir().join(format!("hidapi-sysfs-{}", std::process::id()));
+        let hidraw = root.join("hidraw0");
+        let hid = root.join("0003:046D:C52B.0001");
+        fs::create_dir_all(&hidraw).unwrap();
+        fs::create_dir_all(&hid).unwrap();
+        fs::write(
+            hidraw.join("uevent"),
+            "MAJOR=241\nMINOR=0\nDEVNAME=hidraw0\n",
+        )
+        .unwrap();
+        fs::write(
+            hid.join("uevent"),
+            "DRIVER=hid-generic\nHID_ID=0003:0000046D:0000C52B\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            devnode_from_syspath(&hidraw).unwrap(),
+            Path::new("/dev/hidraw0")
+        );
+        assert!(devnode_from_syspath(&hid).is_err());
+        assert!(devnode_from_syspath(&root.join("missing")).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_open_path_forms() {
+        // /dev/null is no hidraw device, but shows which path was opened
+        let open = |path: &str| HidDevice::open_path(&CString::new(path).unwrap());
+        assert!(matches!(
+            open("/dev/null"),
+            Err(HidError::HidApiError { message }) if message.contains("GRDESCSIZE")
+        ));
+        assert!(matches!(
+            open("hidraw0"),
+            Err(HidError::HidApiError { message }) if message.contains("neither")
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs sysfs, which containers may not have"]
+    fn test_open_sysfs_path() {
+        // The sysfs entry of /dev/null, resolving it is covered by `test_devnode_from_syspath`
+        let path = CString::new("/sys/dev/char/1:3").unwrap();
+        assert!(matches!(
+            HidDevice::open_path(&path),
+            Err(HidError::HidApiError { message })
+                if message.contains("GRDESCSIZE") && message.contains("/dev/null")
+        ));
+    }
+
+    #[test]
+    fn test_open_error() {
+        for errno in [libc::EACCES, libc::EPERM] {
+            assert!(matches!(
+                open_error("/dev/hidraw0", std::io::Error::from_raw_os_error(errno)),
+                HidError::PermissionDenied { path } if path == "/dev/hidraw0"
+            ));
+        }
+        assert!(matches!(
+            open_error("/dev/hidraw0", std::io::Error::from_raw_os_error(libc::ENOENT)),
+            HidError::HidApiError { message } if message.contains("/dev/hidraw0")
+        ));
+    }
+
+    #[test]
+    fn test_open_fd_rejects_non_hidraw() {
+        let file = File::open("/dev/null").unwrap();
+        assert!(matches!(
+            HidDevice::open_fd(file.into()),
+            Err(HidError::HidApiError { message }) if message.contains("not a HIDRAW device")
+        ));
+    }
+
+    /// Device descriptor with iManufacturer 1, iProduct 2, iSerialNumber 3, a configuration
+    /// with value 1 and iConfiguration 4, an interface 0 without a name and interface 1 with
+    /// iInterface 5.
+    const USB_DESCRIPTORS: &[u8] = &[
+        0x12, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x40, 0x6d, 0x04, 0x2b, 0xc5, 0x01, 0x12, 0x01,
+        0x02, 0x03, 0x01, // Device
+        0x09, 0x02, 0x3b, 0x00, 0x02, 0x01, 0x04, 0xa0, 0x31, // Configuration
+        0x09, 0x04, 0x00, 0x00, 0x01, 0x03, 0x01, 0x01, 0x00, // Interface 0
+        0x09, 0x21, 0x11, 0x01, 0x00, 0x01, 0x22, 0x3b, 0x00, // HID
+        0x07, 0x05, 0x81, 0x03, 0x08, 0x00, 0x08, // Endpoint
+        0x09, 0x04, 0x01, 0x00, 0x01, 0x03, 0x01, 0x02, 0x05, // Interface 1
+    ];
+
+    #[test]
+    fn test_usb_string_attribute() {
+        use UsbStringAttribute::*;
+
+        let attribute = |index| usb_string_attribute(USB_DESCRIPTORS, index);
+        assert_eq!(attribute(1), Some(Device("manufacturer")));
+        assert_eq!(attribute(2), Some(Device("product")));
+        assert_eq!(attribute(3), Some(Device("serial")));
+        assert_eq!(attribute(4), Some(Device("configuration")));
+        assert_eq!(attribute(5), Some(Interface(1, 1)));
+        assert_eq!(attribute(6), None);
+        assert_eq!(usb_string_attribute(&USB_DESCRIPTORS[..10], 1), None);
+    }
+
+    #[test]
+    fn test_string_descriptor_request() {
+        let mut buf = [0u8; 255];
+        let request = string_descriptor_request(2, 0x0407, &mut buf);
+        assert_eq!(request.request_type, 0x80);
+        assert_eq!(request.request, 0x06);
+        // The descriptor type in the high byte, the index in the low byte
+        assert_eq!(request.value, 0x0302);
+        assert_eq!(request.index, 0x0407);
+        assert_eq!(request.length, 255);
+        assert_eq!(request.data, buf.as_mut_ptr().cast());
+    }
+
+    #[test]
+    fn test_string_descriptor_units() {
+        assert_eq!(
+            string_descriptor_units(&[0x06, 0x03, 0x09, 0x04, 0x07, 0x04]),
+            Some(vec![0x0409, 0x0407])
+        );
+        let units = string_descriptor_units(&[0x08, 0x03, b'M', 0, b'u', 0, b'?', 0]).unwrap();
+        assert_eq!(String::from_utf16_lossy(&units), "Mu?");
+        // bLength limits the descriptor, a truncated one is used as far as it goes
+        assert_eq!(
+            string_descriptor_units(&[0x04, 0x03, 0x09, 0x04, 0xff, 0xff]),
+            Some(vec![0x0409])
+        );
+        assert_eq!(string_descriptor_units(&[0x06, 0x03, 0x09]), Some(vec![]));
+        assert_eq!(string_descriptor_units(&[0x04, 0x02, 0x09, 0x04]), None);
+        assert_eq!(string_descriptor_units(&[]), None);
+    }
+
+    #[test]
+    fn test_usb_indexed_string() {
+        let root = std::env::temp_dir().join(format!("hidapi-usb-strings-{}", std::process::id()));
+        let usb_device = root.join("1-1");
+        fs::create_dir_all(usb_device.join("1-1:1.1")).unwrap();
+        fs::write(usb_device.join("descriptors"), USB_DESCRIPTORS).unwrap();
+        fs::write(usb_device.join("manufacturer"), "Logitech\n").unwrap();
+        fs::write(usb_device.join("product"), "USB Receiver\n").unwrap();
+        fs::write(usb_device.join("1-1:1.1/interface"), "Vendor Interface\n").unwrap();
+
+        let string = |index| usb_indexed_string(&usb_device, index);
+        assert_eq!(string(0), None);
+        assert_eq!(string(1).as_deref(), Some("Logitech"));
+        assert_eq!(string(2).as_deref(), Some("USB Receiver"));
+        // Declared but missing attribute
+        assert_eq!(string(3), None);
+        assert_eq!(string(5).as_deref(), Some("Vendor Interface"));
+        assert_eq!(string(6), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_open_enumerates_once() {
+        let info = |path: &str, serial: &str| DeviceInfo {
+            path: CString::new(path).unwrap(),
+            vendor_id: 0x046d,
+            product_id: 0xc077,
+            serial_number: HidString::String(serial.into()),
+            release_number: 0,
+            manufacturer_string: HidString::String("Logitech".into()),
+            product_string: HidString::String("Mouse".into()),
+            usage_page: 1,
+            usage: 2,
+            interface_number: 0,
+            bus_type: BusType::Usb,
+            location_id: None,
+            bluetooth_address: None,
+            group_id: None,
+        };
+
+        let mut enumerations = 0;
+        let mut opened = Vec::new();
+        let device = HidDevice::open_matching(
+            0x046d,
+            0xc077,
+            Some("B"),
+            |_, _| {
+                enumerations += 1;
+                Ok(vec![info("/dev/hidraw0", "A"), info("/dev/hidraw1", "B")])
+            },
+            |path| {
+                opened.push(path.to_owned());
+                Ok(HidDevice::from_hidraw_fd(File::open("/dev/null")?.into()))
+            },
+        )
+        .unwrap();
+
+        assert_eq!(enumerations, 1);
+        assert_eq!(opened, vec![CString::new("/dev/hidraw1").unwrap()]);
+        // Served from the enumerated info, /dev/null has no sysfs entry to look it up
+        assert_eq!(
+            device.get_product_string().unwrap(),
+            Some("Mouse".to_string())
+        );
+        assert_eq!(
+            device.get_serial_number_string().unwrap(),
+            Some("B".to_string())
+        );
+    }
+
+    /// A device on `fd` whose report descriptor is already parsed
+    fn device_with_descriptor(fd: OwnedFd, descriptor: ReportDescriptor) -> HidDevice {
+        let device = HidDevice::from_hidraw_fd(fd);
+        *device.report_descriptor.lock().unwrap() = Some(descriptor);
+        device
+    }
+
+    #[test]
+    fn test_read_hangup_is_disconnected() {
+        use std::os::fd::FromRawFd;
+
+        // A pipe with a closed write end reports POLLHUP, like a hidraw node of an unplugged device
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        drop(unsafe { OwnedFd::from_raw_fd(write_end) });
+        let device = HidDevice::from_hidraw_fd(unsafe { OwnedFd::from_raw_fd(read_end) });
+
+        let mut buf = [0u8; 8];
+        assert!(matches!(
+            device.read_timeout(&mut buf, 100),
+            Err(HidError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_initial_blocking_mode() {
+        use std::os::fd::FromRawFd;
+
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let _write_end = unsafe { OwnedFd::from_raw_fd(write_end) };
+        let device = HidDevice::from_hidraw_fd(unsafe { OwnedFd::from_raw_fd(read_end) });
+        assert!(device.blocking.load(Ordering::Relaxed));
+
+        // Without input, a non-blocking read returns right away
+        device.set_blocking_mode(false).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(device.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_uninit() {
+        use std::os::fd::FromRawFd;
+
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let write_end = unsafe { OwnedFd::from_raw_fd(write_end) };
+        let device = HidDevice::from_hidraw_fd(unsafe { OwnedFd::from_raw_fd(read_end) });
+
+        write(write_end.as_raw_fd(), &[0x01, 0x02, 0x03]).unwrap();
+        let mut buf = [MaybeUninit::<u8>::uninit(); 8];
+        assert_eq!(device.read_timeout_uninit(&mut buf, 100).unwrap(), 3);
+        assert_eq!(unsafe { buf[2].assume_init() }, 0x03);
+        assert_eq!(device.read_timeout_uninit(&mut buf, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_send_output_report_falls_back_to_write() {
+        use std::os::fd::FromRawFd;
+
+        // A pipe does not support HIDIOCSOUTPUT either, like hidraw before Linux 5.11
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let read_end = unsafe { OwnedFd::from_raw_fd(read_end) };
+        let device = HidDevice::from_hidraw_fd(unsafe { OwnedFd::from_raw_fd(write_end) });
+
+        device.send_output_report(&[0x02, 0xaa, 0xbb]).unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(nix::unistd::read(read_end.as_raw_fd(), &mut buf), Ok(3));
+        assert_eq!(&buf[..3], &[0x02, 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn test_last_error_cleared_by_success() {
+        use std::os::fd::FromRawFd;
+
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let _read_end = unsafe { OwnedFd::from_raw_fd(read_end) };
+        let device = HidDevice::from_hidraw_fd(unsafe { OwnedFd::from_raw_fd(write_end) });
+        assert!(device.last_error().is_none());
+
+        // A pipe doesn't support the feature report ioctls
+        let mut buf = [0u8; 8];
+        let err = device.get_feature_report(&mut buf).unwrap_err();
+        let last_error = device.last_error().unwrap();
+        assert_eq!(last_error.to_string(), err.to_string());
+        // Reading the last error doesn't clear it
+        assert!(device.last_error().is_some());
+
+        assert!(matches!(
+            device.write(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+        assert!(matches!(
+            device.last_error(),
+            Some(HidError::InvalidZeroSizeData)
+        ));
+
+        device.write(&[0x00, 0x01]).unwrap();
+        assert!(device.last_error().is_none());
+    }
+
+    #[test]
+    fn test_pad_report() {
+        let mut scratch = Vec::new();
+        assert_eq!(
+            pad_report(&[0x01, 0x02], 4, &mut scratch),
+            &[0x01, 0x02, 0x00, 0x00]
+        );
+        // the scratch buffer is cleared, not just resized
+        assert_eq!(pad_report(&[0x03], 2, &mut scratch), &[0x03, 0x00]);
+        assert_eq!(pad_report(&[0x01, 0x02], 2, &mut scratch), &[0x01, 0x02]);
+        assert_eq!(
+            pad_report(&[0x01, 0x02, 0x03], 2, &mut scratch),
+            &[0x01, 0x02, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_feature_report_id_checked() {
+        use std::os::fd::FromRawFd;
+
+        // Feature reports 1 with 2 bytes and 2 with 4 bytes
+        let descriptor = ReportDescriptor::parse(&[
+            0x06, 0x00, 0xff, 0x09, 0x01, 0xa1, 0x01, 0x75,
+            0x08, // Vendor-defined, Collection
+            0x85, 0x01, 0x95, 0x02, 0xb1, 0x02, // Report ID 1, 2 bytes feature
+            0x85, 0x02, 0x95, 0x04, 0xb1, 0x02, 0xc0, // Report ID 2, 4 bytes feature
+        ])
+        .unwrap();
+        let (read_end, write_end) = nix::unistd::pipe().unwrap();
+        let _read_end = unsafe { OwnedFd::from_raw_fd(read_end) };
+        let device = device_with_descriptor(unsafe { OwnedFd::from_raw_fd(write_end) }, descriptor);
+
+        assert_eq!(device.feature_report_len(0x01).unwrap(), Some(3));
+        assert_eq!(device.feature_report_len(0x02).unwrap(), Some(5));
+        let err = device.send_feature_report(&[0x03, 0xaa]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "hidapi error: invalid report: no Feature report 3"
+        );
+        let err = device.get_feature_report(&mut [0x00; 8]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "hidapi error: invalid report: the device requires a report ID"
+        );
+        assert!(matches!(
+            device.get_feature_report(&mut []),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+    }
 }
@@ -0,0 +1,127 @@
+//! Hotplug notifications using a udev monitor on the `hidraw` subsystem
+
+use std::{
+    collections::VecDeque,
+    ffi::CString,
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::ffi::OsStringExt,
+    },
+};
+
+use nix::poll::{poll, PollFd, PollFlags};
+
+use super::device_to_hid_device_info;
+use crate::{BusType, DeviceInfo, HidResult, HidString};
+
+/// A change in the set of connected HID devices, see [`HotplugWatcher`].
+#[derive(Debug, Clone)]
+pub enum HotplugEvent {
+    /// A device was connected
+    Added(DeviceInfo),
+    /// A device was disconnected.
+    ///
+    /// The sysfs attributes of a device are gone by the time it is removed, so the [`DeviceInfo`]
+    /// may only carry the device path.
+    Removed(DeviceInfo),
+}
+
+/// Watches for HID devices being connected or disconnected.
+///
+/// Created by [`HidApi::watch`](crate::HidApi::watch).
+pub struct HotplugWatcher {
+    socket: udev::MonitorSocket,
+    pending: VecDeque<HotplugEvent>,
+}
+
+impl HotplugWatcher {
+    pub(crate) fn new() -> HidResult<Self> {
+        let socket = udev::MonitorBuilder::new()?
+            .match_subsystem("hidraw")?
+            .listen()?;
+
+        Ok(Self {
+            socket,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Wait for the next hotplug event.
+    ///
+    /// The timeout is in milliseconds, `-1` waits forever. Returns `None` if the timeout expired
+    /// without an event.
+    ///
+    /// A device with multiple top level collections produces one event per usage, the same way
+    /// it shows up multiple times in [`HidApi::device_list`](crate::HidApi::device_list).
+    pub fn next_event(&mut self, timeout: i32) -> HidResult<Option<HotplugEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            let pollfd = PollFd::new(&self.socket, PollFlags::POLLIN);
+            if poll(&mut [pollfd], timeout)? == 0 {
+                return Ok(None);
+            }
+
+            for event in self.socket.iter() {
+                match event.event_type() {
+                    udev::EventType::Add => {
+                        if let Some(infos) = device_to_hid_device_info(&event) {
+                            self.pending
+                                .extend(infos.into_iter().map(HotplugEvent::Added));
+                        }
+                    }
+                    udev::EventType::Remove => {
+                        if let Some(info) = removed_device_info(&event) {
+                            self.pending.push_back(HotplugEvent::Removed(info));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if self.pending.is_empty() && timeout >= 0 {
+                // Only uninteresting events, don't keep waiting past the timeout
+                return Ok(None);
+            }
+        }
+    }
+}
+
+impl AsFd for HotplugWatcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.socket.as_fd()
+    }
+}
+
+/// Build the [`DeviceInfo`] for a removed device, falling back to just the path.
+fn removed_device_info(device: &udev::Device) -> Option<DeviceInfo> {
+    if let Some(mut infos) = device_to_hid_device_info(device) {
+        if !infos.is_empty() {
+            return Some(infos.swap_remove(0));
+        }
+    }
+
+    let path = device
+        .devnode()
+        .map(|p| p.as_os_str().to_os_string().into_vec())
+        .and_then(|p| CString::new(p).ok())?;
+
+    Some(DeviceInfo {
+        path,
+        vendor_id: 0,
+        product_id: 0,
+        serial_number: HidString::None,
+        release_number: 0,
+        manufacturer_string: HidString::None,
+        product_string: HidString::None,
+        usage_page: 0,
+        usage: 0,
+        interface_number: -1,
+        bus_type: BusType::Unknown,
+        location_id: None,
+        bluetooth_address: None,
+        group_id: None,
+    })
+}
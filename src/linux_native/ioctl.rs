@@ -1,6 +1,6 @@
 //! The IOCTL calls we need for the native linux backend
 
-use nix::{ioctl_read, ioctl_read_buf, ioctl_write_buf};
+use nix::{ioctl_read, ioctl_readwrite, ioctl_readwrite_buf, ioctl_write_buf};
 
 // From linux/hidraw.h
 const HIDRAW_IOC_MAGIC: u8 = b'H';
@@ -10,6 +10,23 @@ const HIDRAW_GET_FEATURE: u8 = 0x07;
 const HIDRAW_SET_OUTPUT: u8 = 0x0b;
 const HIDRAW_GET_INPUT: u8 = 0x0a;
 
+// From linux/usbdevice_fs.h
+const USBDEVFS_IOC_MAGIC: u8 = b'U';
+const USBDEVFS_CONTROL: u8 = 0x00;
+
+/// `struct usbdevfs_ctrltransfer`, a control transfer on the default endpoint of a USB device
+#[repr(C)]
+pub struct UsbCtrlTransfer {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+    /// In milliseconds
+    pub timeout: u32,
+    pub data: *mut libc::c_void,
+}
+
 ioctl_read!(
     hidraw_ioc_grdescsize,
     HIDRAW_IOC_MAGIC,
@@ -23,7 +40,9 @@ ioctl_write_buf!(
     HIDRAW_SET_FEATURE,
     u8
 );
-ioctl_read_buf!(
+// The kernel reads the report ID from the buffer before filling it, so the "get" requests are
+// declared as read-write in linux/hidraw.h
+ioctl_readwrite_buf!(
     hidraw_ioc_get_feature,
     HIDRAW_IOC_MAGIC,
     HIDRAW_GET_FEATURE,
@@ -35,4 +54,50 @@ ioctl_write_buf!(
     HIDRAW_SET_OUTPUT,
     u8
 );
-ioctl_read_buf!(hidraw_ioc_get_input, HIDRAW_IOC_MAGIC, HIDRAW_GET_INPUT, u8);
+ioctl_readwrite_buf!(hidraw_ioc_get_input, HIDRAW_IOC_MAGIC, HIDRAW_GET_INPUT, u8);
+
+ioctl_readwrite!(
+    usbdevfs_control,
+    USBDEVFS_IOC_MAGIC,
+    USBDEVFS_CONTROL,
+    UsbCtrlTransfer
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nix::request_code_readwrite;
+
+    #[test]
+    #[cfg(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    ))]
+    fn test_request_codes() {
+        // HIDIOCGINPUT(64) and HIDIOCGFEATURE(64) from linux/hidraw.h
+        assert_eq!(
+            request_code_readwrite!(HIDRAW_IOC_MAGIC, HIDRAW_GET_INPUT, 64),
+            0xC040_480A
+        );
+        assert_eq!(
+            request_code_readwrite!(HIDRAW_IOC_MAGIC, HIDRAW_GET_FEATURE, 64),
+            0xC040_4807
+        );
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_usbdevfs_control_code() {
+        // USBDEVFS_CONTROL with the 24 bytes of the struct on 64 bit
+        assert_eq!(
+            request_code_readwrite!(
+                USBDEVFS_IOC_MAGIC,
+                USBDEVFS_CONTROL,
+                std::mem::size_of::<UsbCtrlTransfer>()
+            ),
+            0xC018_5500
+        );
+    }
+}
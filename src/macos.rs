@@ -1,7 +1,9 @@
+use std::ffi::CStr;
+
 use libc::c_int;
 
 use crate::ffi;
-use crate::{HidApi, HidDevice, HidResult};
+use crate::{HidApi, HidApiBackend, HidDevice, HidResult, CONTEXT_STATE};
 
 impl HidApi {
     /// Changes the behavior of all further calls that open a new [`HidDevice`]
@@ -12,6 +14,8 @@ impl HidApi {
     ///   * `false` - all further devices will be opened in non-exclusive mode.
     ///   * `true` all further devices will be opened in exclusive mode.
     pub fn set_open_exclusive(&self, exclusive: bool) {
+        // Don't interfere with a concurrent `open_path_exclusive`
+        let _state = CONTEXT_STATE.lock().unwrap();
         unsafe { ffi::macos::hid_darwin_set_open_exclusive(exclusive as c_int) }
     }
 
@@ -19,6 +23,28 @@ impl HidApi {
     pub fn get_open_exclusive(&self) -> bool {
         unsafe { ffi::macos::hid_darwin_get_open_exclusive() != 0 }
     }
+
+    /// Open a device by path in exclusive or shared mode, regardless of
+    /// [`HidApi::set_open_exclusive`].
+    ///
+    /// The mode is a global setting of the C library, so it is changed for the duration of the
+    /// open and restored afterwards. This is synchronized with other calls to this function and
+    /// [`HidApi::set_open_exclusive`], but devices opened concurrently through any other function
+    /// may still be opened in the temporary mode.
+    ///
+    /// Use [`HidDevice::is_open_exclusive`] to check the mode of an opened device.
+    pub fn open_path_exclusive(device_path: &CStr, exclusive: bool) -> HidResult<HidDevice> {
+        let device = {
+            let _state = CONTEXT_STATE.lock().unwrap();
+            let previous = unsafe { ffi::macos::hid_darwin_get_open_exclusive() };
+            unsafe { ffi::macos::hid_darwin_set_open_exclusive(exclusive as c_int) };
+            let device = HidApiBackend::open_path(device_path);
+            unsafe { ffi::macos::hid_darwin_set_open_exclusive(previous) };
+            device?
+        };
+        // Wrapping the device takes the lock too
+        Ok(HidDevice::from_backend(Box::new(device)))
+    }
 }
 
 impl HidDevice {
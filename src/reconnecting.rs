@@ -0,0 +1,214 @@
+//! A device handle that reopens the device after it was unplugged and plugged in again
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::{DeviceInfo, HidApi, HidApiBackend, HidDevice, HidError, HidErrorKind, HidResult};
+
+type Opener = dyn Fn(&DeviceInfo) -> HidResult<HidDevice> + Send + Sync;
+
+struct Shared {
+    info: DeviceInfo,
+    device: Mutex<Option<Arc<HidDevice>>>,
+    open: Box<Opener>,
+}
+
+/// A handle to a device that is reopened when the device comes back after it was disconnected.
+///
+/// An operation that fails because the device was unplugged returns [`HidError::Disconnected`]
+/// and closes the device. The next operation looks for the device and reopens it, and fails with
+/// [`HidError::Disconnected`] again as long as the device is not back. The device is recognized by
+/// its path, its serial number, or by its interface and usage within the same [`GroupId`], i.e. a
+/// device without serial number has to be plugged into the same port again.
+///
+/// Clones share the same device. A reopened device starts out like a newly opened one, so
+/// settings like the blocking mode have to be applied again, see [`ReconnectingDevice::with_device`].
+///
+/// [`GroupId`]: crate::GroupId
+#[derive(Clone)]
+pub struct ReconnectingDevice {
+    shared: Arc<Shared>,
+}
+
+impl ReconnectingDevice {
+    /// Open the device described by `info`.
+    pub fn new(info: DeviceInfo) -> HidResult<Self> {
+        Self::with_opener(info, Box::new(reopen))
+    }
+
+    fn with_opener(info: DeviceInfo, open: Box<Opener>) -> HidResult<Self> {
+        let device = open(&info)?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                info,
+                device: Mutex::new(Some(Arc::new(device))),
+                open,
+            }),
+        })
+    }
+
+    /// The information the device was opened with, which is used to find it again.
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.shared.info
+    }
+
+    /// Check if the device is open, i.e. no operation failed because it was disconnected since
+    /// it was last opened.
+    pub fn is_connected(&self) -> bool {
+        self.shared.device.lock().unwrap().is_some()
+    }
+
+    /// Run `f` with the device, reopening it first if it was disconnected.
+    ///
+    /// If `f` fails with an error of the kind [`HidErrorKind::Disconnected`], the device is
+    /// closed and reopened by the next call.
+    pub fn with_device<T>(&self, f: impl FnOnce(&HidDevice) -> HidResult<T>) -> HidResult<T> {
+        let device = self.device()?;
+        let res = f(&device);
+        if res
+            .as_ref()
+            .is_err_and(|e| e.kind() == HidErrorKind::Disconnected)
+        {
+            let mut current = self.shared.device.lock().unwrap();
+            // Another clone may have reopened the device already
+            if current.as_ref().is_some_and(|d| Arc::ptr_eq(d, &device)) {
+                *current = None;
+            }
+        }
+        res
+    }
+
+    /// See [`HidDevice::write`].
+    pub fn write(&self, data: &[u8]) -> HidResult<usize> {
+        self.with_device(|device| device.write(data))
+    }
+
+    /// See [`HidDevice::read`].
+    pub fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
+        self.with_device(|device| device.read(buf))
+    }
+
+    /// See [`HidDevice::read_timeout`].
+    pub fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        self.with_device(|device| device.read_timeout(buf, timeout))
+    }
+
+    /// See [`HidDevice::send_feature_report`].
+    pub fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
+        self.with_device(|device| device.send_feature_report(data))
+    }
+
+    /// See [`HidDevice::get_feature_report`].
+    pub fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        self.with_device(|device| device.get_feature_report(buf))
+    }
+
+    /// See [`HidDevice::send_output_report`].
+    pub fn send_output_report(&self, data: &[u8]) -> HidResult<()> {
+        self.with_device(|device| device.send_output_report(data))
+    }
+
+    /// Get the open device, or open it again if it was disconnected.
+    fn device(&self) -> HidResult<Arc<HidDevice>> {
+        let mut device = self.shared.device.lock().unwrap();
+        if let Some(device) = device.as_ref() {
+            return Ok(device.clone());
+        }
+
+        let reopened = (self.shared.open)(&self.shared.info).map_err(|e| match e.kind() {
+            HidErrorKind::NotFound | HidErrorKind::Disconnected => HidError::Disconnected,
+            _ => e,
+        })?;
+        Ok(device.insert(Arc::new(reopened)).clone())
+    }
+}
+
+impl fmt::Debug for ReconnectingDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectingDevice")
+            .field("info", &self.shared.info)
+            .field("connected", &self.is_connected())
+            .finish()
+    }
+}
+
+/// Look for the device among the connected devices with its vendor and product ID and open it.
+fn reopen(info: &DeviceInfo) -> HidResult<HidDevice> {
+    let devices = HidApiBackend::get_hid_device_info_vector(info.vendor_id, info.product_id)?;
+    match devices.iter().find(|d| info.is_reconnected_device(d)) {
+        Some(device) => HidApi::open_path(&device.path),
+        None => Err(HidError::NoMatchingDevice {
+            device_info: Box::new(info.clone()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_reopen_after_disconnect() {
+        let first = MockDevice::new(0x1234, 0x5678);
+        let second = MockDevice::new(0x1234, 0x5678);
+        let info = HidDevice::from_mock(first.clone())
+            .get_device_info()
+            .unwrap();
+        let opened = Mutex::new(VecDeque::from([
+            Ok(HidDevice::from_mock(first.clone())),
+            Err(HidError::NoMatchingDevice {
+                device_info: Box::new(info.clone()),
+            }),
+            Ok(HidDevice::from_mock(second.clone())),
+        ]));
+        let device = ReconnectingDevice::with_opener(
+            info,
+            Box::new(move |_| opened.lock().unwrap().pop_front().unwrap()),
+        )
+        .unwrap();
+
+        device.write(&[0x00, 0x01]).unwrap();
+        assert_eq!(first.take_writes(), vec![vec![0x00, 0x01]]);
+
+        first.disconnect();
+        let mut buf = [0u8; 4];
+        assert!(matches!(
+            device.read_timeout(&mut buf, 0),
+            Err(HidError::Disconnected)
+        ));
+        assert!(!device.is_connected());
+        // The device is not back yet
+        assert!(matches!(
+            device.clone().write(&[0x00, 0x02]),
+            Err(HidError::Disconnected)
+        ));
+        assert!(!device.is_connected());
+
+        second.push_input(vec![0x03]);
+        assert_eq!(device.read_timeout(&mut buf, 0).unwrap(), 1);
+        assert!(device.is_connected());
+        device.write(&[0x00, 0x04]).unwrap();
+        assert_eq!(second.take_writes(), vec![vec![0x00, 0x04]]);
+    }
+
+    #[test]
+    fn test_other_errors_keep_device() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let info = HidDevice::from_mock(mock.clone())
+            .get_device_info()
+            .unwrap();
+        let device = ReconnectingDevice::with_opener(
+            info,
+            Box::new(move |_| Ok(HidDevice::from_mock(mock.clone()))),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            device.write(&[]),
+            Err(HidError::InvalidZeroSizeData)
+        ));
+        assert!(device.is_connected());
+    }
+}
@@ -0,0 +1,705 @@
+//! A small parser for HID report descriptors, shared by all backends
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use crate::{HidError, HidResult, MAX_REPORT_DESCRIPTOR_SIZE};
+
+/// The type of a HID report.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReportType {
+    Input,
+    Output,
+    Feature,
+}
+
+/// A single report declared by a [`ReportDescriptor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    report_id: u8,
+    report_type: ReportType,
+    bit_len: usize,
+    usage_page: u16,
+    usage: u16,
+}
+
+impl Report {
+    /// The report ID, or 0 if the device does not use numbered reports.
+    pub fn report_id(&self) -> u8 {
+        self.report_id
+    }
+
+    pub fn report_type(&self) -> ReportType {
+        self.report_type
+    }
+
+    /// Length of the report data in bits, not including the report ID.
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+
+    /// Length of the report data in bytes, not including the report ID.
+    pub fn byte_len(&self) -> usize {
+        self.bit_len.div_ceil(8)
+    }
+
+    /// Usage page of the top-level collection containing this report.
+    pub fn usage_page(&self) -> u16 {
+        self.usage_page
+    }
+
+    /// Usage of the top-level collection containing this report.
+    pub fn usage(&self) -> u16 {
+        self.usage
+    }
+}
+
+/// A parsed HID report descriptor.
+///
+/// This only extracts the information needed to work with the reports of a
+/// device (report IDs, types, sizes and top-level usages), it is not a full
+/// implementation of the HID specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportDescriptor {
+    reports: Vec<Report>,
+    usages: Vec<(u16, u16)>,
+}
+
+/// Global items which are affected by Push and Pop
+#[derive(Clone, Copy, Default)]
+struct GlobalState {
+    usage_page: u16,
+    report_size: u32,
+    report_count: u32,
+    report_id: u8,
+}
+
+impl ReportDescriptor {
+    /// Parse a raw report descriptor, as returned by [`HidDevice::get_report_descriptor`].
+    ///
+    /// [`HidDevice::get_report_descriptor`]: crate::HidDevice::get_report_descriptor
+    pub fn parse(bytes: &[u8]) -> HidResult<Self> {
+        let mut cursor = Cursor::new(bytes);
+
+        let mut global = GlobalState::default();
+        let mut global_stack = Vec::new();
+        let mut usages: Vec<(u16, u16)> = Vec::new();
+        let mut top_level = (0, 0);
+        let mut depth = 0usize;
+        let mut reports: Vec<Report> = Vec::new();
+
+        while let Some(Ok(key)) = cursor.by_ref().bytes().next() {
+            let position = cursor.position() - 1;
+            let (data_len, key_size) = hid_item_size(key, &mut cursor).ok_or_else(malformed)?;
+
+            // Long items carry vendor defined data, skip them entirely
+            if key_size == 3 {
+                cursor.seek(SeekFrom::Start(position + (data_len + key_size) as u64))?;
+                if cursor.position() > bytes.len() as u64 {
+                    return Err(malformed());
+                }
+                continue;
+            }
+
+            let value = hid_report_bytes(&mut cursor, data_len).map_err(|_| malformed())?;
+
+            match key & 0xfc {
+                // Usage Page 6.2.2.7 (Global)
+                0x04 => global.usage_page = value as u16,
+                // Report Size 6.2.2.7 (Global)
+                0x74 => global.report_size = value,
+                // Report ID 6.2.2.7 (Global)
+                0x84 => global.report_id = value as u8,
+                // Report Count 6.2.2.7 (Global)
+                0x94 => global.report_count = value,
+                // Push 6.2.2.7 (Global)
+                0xa4 => global_stack.push(global),
+                // Pop 6.2.2.7 (Global)
+                0xb4 => global = global_stack.pop().ok_or_else(malformed)?,
+                // Usage 6.2.2.8 (Local)
+                0x08 => usages.push(extended_usage(global.usage_page, value, data_len)),
+                // Usage Minimum 6.2.2.8 (Local)
+                0x18 => usages.push(extended_usage(global.usage_page, value, data_len)),
+                // Collection 6.2.2.4 (Main)
+                0xa0 => {
+                    if depth == 0 {
+                        top_level = usages.first().copied().unwrap_or((global.usage_page, 0));
+                    }
+                    depth += 1;
+                    usages.clear();
+                }
+                // End Collection 6.2.2.4 (Main)
+                0xc0 => {
+                    depth = depth.checked_sub(1).ok_or_else(malformed)?;
+                    usages.clear();
+                }
+                // Input, Output and Feature 6.2.2.4 (Main)
+                key_cmd @ (0x80 | 0x90 | 0xb0) => {
+                    let report_type = match key_cmd {
+                        0x80 => ReportType::Input,
+                        0x90 => ReportType::Output,
+                        _ => ReportType::Feature,
+                    };
+                    let bits = usize::try_from(global.report_size)
+                        .ok()
+                        .zip(usize::try_from(global.report_count).ok())
+                        .and_then(|(size, count)| size.checked_mul(count))
+                        .ok_or_else(malformed)?;
+                    match reports
+                        .iter_mut()
+                        .find(|r| r.report_id == global.report_id && r.report_type == report_type)
+                    {
+                        Some(report) => {
+                            report.bit_len =
+                                report.bit_len.checked_add(bits).ok_or_else(malformed)?
+                        }
+                        None => reports.push(Report {
+                            report_id: global.report_id,
+                            report_type,
+                            bit_len: bits,
+                            usage_page: top_level.0,
+                            usage: top_level.1,
+                        }),
+                    }
+                    usages.clear();
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ReportDescriptor {
+            reports,
+            usages: UsageIterator::new(bytes).collect(),
+        })
+    }
+
+    /// All reports declared by the descriptor, in order of first appearance.
+    pub fn reports(&self) -> &[Report] {
+        &self.reports
+    }
+
+    /// Find the report with the given ID and type.
+    pub fn report(&self, report_id: u8, report_type: ReportType) -> Option<&Report> {
+        self.reports
+            .iter()
+            .find(|r| r.report_id == report_id && r.report_type == report_type)
+    }
+
+    /// The (report ID, type) pairs of the declared reports, in order of first appearance.
+    ///
+    /// The report ID is 0 for devices that don't use numbered reports.
+    pub fn report_ids(&self) -> Vec<(u8, ReportType)> {
+        self.reports
+            .iter()
+            .map(|r| (r.report_id, r.report_type))
+            .collect()
+    }
+
+    /// Length in bytes of the longest report of the given type, including one
+    /// byte for the report ID, or 0 if there are no reports of that type.
+    ///
+    /// This matches the report lengths Windows returns in `HIDP_CAPS` and is
+    /// the buffer size needed to read or write any report of that type.
+    pub fn max_report_len(&self, report_type: ReportType) -> usize {
+        self.reports
+            .iter()
+            .filter(|r| r.report_type == report_type)
+            .map(|r| r.byte_len() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Check if the reports are numbered, in which case every report starts with its report ID.
+    pub fn uses_report_ids(&self) -> bool {
+        self.reports.iter().any(|r| r.report_id != 0)
+    }
+
+    /// Build the buffer to send a report, starting with the report ID or 0 for unnumbered
+    /// reports, followed by `data` padded with zeros to the length of the report.
+    pub(crate) fn frame_report(
+        &self,
+        report_type: ReportType,
+        report_id: Option<u8>,
+        data: &[u8],
+    ) -> HidResult<Vec<u8>> {
+        let report_id = match (report_id, self.uses_report_ids()) {
+            (Some(id @ 1..), true) => id,
+            (None, false) => 0,
+            (Some(_), false) => return Err(invalid_report("the device does not use report IDs")),
+            (_, true) => return Err(invalid_report("the device requires a report ID")),
+        };
+        let report = self
+            .report(report_id, report_type)
+            .ok_or_else(|| invalid_report(&format!("no {report_type:?} report {report_id}")))?;
+        if data.len() > report.byte_len() {
+            return Err(invalid_report(&format!(
+                "{} bytes of data exceed the {} bytes of {report_type:?} report {report_id}",
+                data.len(),
+                report.byte_len()
+            )));
+        }
+
+        let mut buf = vec![0u8; report.byte_len() + 1];
+        buf[0] = report_id;
+        buf[1..=data.len()].copy_from_slice(data);
+        Ok(buf)
+    }
+
+    /// Find the report that a buffer starting with `report_id` is meant for.
+    ///
+    /// Devices that don't use report IDs expect their reports to start with 0 instead.
+    #[cfg_attr(
+        not(all(feature = "linux-native", target_os = "linux")),
+        allow(dead_code)
+    )]
+    pub(crate) fn check_report_id(
+        &self,
+        report_type: ReportType,
+        report_id: u8,
+    ) -> HidResult<&Report> {
+        match (report_id, self.uses_report_ids()) {
+            (0, true) => return Err(invalid_report("the device requires a report ID")),
+            (1.., false) => {
+                return Err(invalid_report(
+                    "the device does not use report IDs, the report has to start with 0",
+                ))
+            }
+            _ => {}
+        }
+        self.report(report_id, report_type)
+            .ok_or_else(|| invalid_report(&format!("no {report_type:?} report {report_id}")))
+    }
+
+    /// The (usage_page, usage) pairs of the collections in the descriptor.
+    ///
+    /// These are the same pairs that are reported by device enumeration on
+    /// backends which split a device into one [`DeviceInfo`] per usage.
+    ///
+    /// [`DeviceInfo`]: crate::DeviceInfo
+    pub fn usages(&self) -> &[(u16, u16)] {
+        &self.usages
+    }
+
+    /// The (usage_page, usage) pair of the first collection, which classifies the device.
+    pub fn primary_usage(&self) -> Option<(u16, u16)> {
+        self.usages.first().copied()
+    }
+}
+
+/// Check that a raw report descriptor read from a device is not larger than
+/// [`MAX_REPORT_DESCRIPTOR_SIZE`].
+#[cfg_attr(hidapi, allow(dead_code))]
+pub(crate) fn check_descriptor_size(size: usize) -> HidResult<()> {
+    if size > MAX_REPORT_DESCRIPTOR_SIZE {
+        return Err(HidError::ReportDescriptorTooLarge { size });
+    }
+    Ok(())
+}
+
+fn invalid_report(message: &str) -> HidError {
+    HidError::HidApiError {
+        message: format!("invalid report: {message}"),
+    }
+}
+
+fn malformed() -> HidError {
+    HidError::HidApiError {
+        message: "malformed report descriptor".into(),
+    }
+}
+
+/// A 4 byte usage contains the usage page in the upper 16 bits
+fn extended_usage(usage_page: u16, value: u32, data_len: usize) -> (u16, u16) {
+    if data_len == 4 {
+        ((value >> 16) as u16, value as u16)
+    } else {
+        (usage_page, value as u16)
+    }
+}
+
+/// Iterates over the top-level usages in a raw report descriptor
+pub(crate) struct UsageIterator<'a> {
+    usage_page: u16,
+    cursor: Cursor<&'a [u8]>,
+}
+
+impl<'a> UsageIterator<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        UsageIterator {
+            usage_page: 0,
+            cursor: Cursor::new(data),
+        }
+    }
+}
+
+impl<'a> Iterator for UsageIterator<'a> {
+    type Item = (u16, u16);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (usage_page, page) = next_hid_usage(&mut self.cursor, self.usage_page)?;
+
+        self.usage_page = usage_page;
+        Some((usage_page, page))
+    }
+}
+
+// This comes from hidapi which apparently comes from Apple's implementation of
+// this
+fn next_hid_usage(cursor: &mut Cursor<&[u8]>, mut usage_page: u16) -> Option<(u16, u16)> {
+    let mut usage = None;
+    let mut usage_pair = None;
+    let initial = cursor.position() == 0;
+
+    while let Some(Ok(key)) = cursor.bytes().next() {
+        // The amount to skip is calculated based off of the start of the
+        // iteration so we need to keep track of that.
+        let position = cursor.position() - 1;
+        let key_cmd = key & 0xfc;
+
+        let (data_len, key_size) = hid_item_size(key, cursor)?;
+
+        // Long items carry vendor defined data, their payload must not be read as short items
+        if key_size == 3 {
+            cursor
+                .seek(SeekFrom::Start(position + (data_len + key_size) as u64))
+                .ok()?;
+            continue;
+        }
+
+        match key_cmd {
+            // Usage Page 6.2.2.7 (Global)
+            0x4 => {
+                usage_page = match hid_report_bytes(cursor, data_len) {
+                    Ok(v) => v as u16,
+                    Err(_) => break,
+                }
+            }
+            // Usage 6.2.2.8 (Local)
+            0x8 => {
+                usage = match hid_report_bytes(cursor, data_len) {
+                    Ok(v) => Some(v as u16),
+                    Err(_) => break,
+                }
+            }
+            // Collection 6.2.2.4 (Main)
+            0xa0 => {
+                // Usage is a Local Item, unset it
+                if let Some(u) = usage.take() {
+                    usage_pair = Some((usage_page, u))
+                }
+            }
+            // Input, Output, Feature and End Collection 6.2.2.4 (Main)
+            0x80 | 0x90 | 0xb0 | 0xc0 => {
+                // Usage is a Local Item, unset it
+                usage.take();
+            }
+            _ => {}
+        }
+
+        if cursor
+            .seek(SeekFrom::Start(position + (data_len + key_size) as u64))
+            .is_err()
+        {
+            return None;
+        }
+
+        if let Some((usage_page, usage)) = usage_pair {
+            return Some((usage_page, usage));
+        }
+    }
+
+    if let (true, Some(usage)) = (initial, usage) {
+        return Some((usage_page, usage));
+    }
+
+    None
+}
+
+/// Gets the size of the HID item at the given position
+///
+/// Returns data_len and key_size when successful
+fn hid_item_size(key: u8, cursor: &mut Cursor<&[u8]>) -> Option<(usize, usize)> {
+    // Long Item. Next byte contains the length of the data section.
+    if (key & 0xf0) == 0xf0 {
+        if let Some(Ok(len)) = cursor.bytes().next() {
+            return Some((len.into(), 3));
+        }
+
+        // Malformed report
+        return None;
+    }
+
+    // Short Item. Bottom two bits contains the size code
+    match key & 0x03 {
+        v @ 0..=2 => Some((v.into(), 1)),
+        3 => Some((4, 1)),
+        _ => unreachable!(), // & 0x03 means this can't happen
+    }
+}
+
+/// Get the bytes from a HID report descriptor
+///
+/// Must only be called with `num_bytes` 0, 1, 2 or 4.
+fn hid_report_bytes(cursor: &mut Cursor<&[u8]>, num_bytes: usize) -> HidResult<u32> {
+    let mut bytes: [u8; 4] = [0; 4];
+    cursor.read_exact(&mut bytes[..num_bytes])?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::fs::read_to_string;
+
+    /// Read the raw bytes of a descriptor in `tests/pp_data`.
+    pub(crate) fn fixture_bytes(name: &str) -> Vec<u8> {
+        let path = format!("./tests/pp_data/{name}.expected");
+        read_to_string(path)
+            .unwrap()
+            .lines()
+            .flat_map(|line| {
+                line.split(',')
+                    .map(|hex| hex.trim())
+                    .filter(|hex| !hex.is_empty())
+                    .map(|hex| u8::from_str_radix(hex.strip_prefix("0x").unwrap(), 16).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn load_fixture(name: &str) -> ReportDescriptor {
+        ReportDescriptor::parse(&fixture_bytes(name)).expect("descriptor")
+    }
+
+    #[test]
+    fn test_unnumbered_keyboard() {
+        let desc = load_fixture("046A_0011_0006_0001");
+
+        assert_eq!(desc.usages(), &[(0x01, 0x06)]);
+        assert_eq!(desc.reports().len(), 2);
+        let input = desc.report(0, ReportType::Input).unwrap();
+        assert_eq!(input.bit_len(), 64);
+        assert_eq!(input.byte_len(), 8);
+        assert_eq!((input.usage_page(), input.usage()), (0x01, 0x06));
+        let output = desc.report(0, ReportType::Output).unwrap();
+        assert_eq!(output.byte_len(), 1);
+        assert_eq!(desc.max_report_len(ReportType::Input), 9);
+        assert_eq!(desc.max_report_len(ReportType::Output), 2);
+        assert_eq!(desc.max_report_len(ReportType::Feature), 0);
+    }
+
+    #[test]
+    fn test_numbered_vendor_reports() {
+        let desc = load_fixture("046D_B010_0001_FF00");
+
+        assert_eq!(desc.usages(), &[(0xff00, 0x01)]);
+        let reports = desc
+            .reports()
+            .iter()
+            .map(|r| (r.report_id(), r.report_type(), r.byte_len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reports,
+            vec![(0x10, ReportType::Input, 6), (0x10, ReportType::Output, 6)]
+        );
+    }
+
+    #[test]
+    fn test_report_ids() {
+        let desc = load_fixture("047F_C056_0005_000B");
+        assert_eq!(
+            desc.report_ids(),
+            vec![
+                (0x08, ReportType::Input),
+                (0x09, ReportType::Output),
+                (0x17, ReportType::Output),
+                (0x18, ReportType::Output),
+                (0x1E, ReportType::Output),
+                (0x20, ReportType::Output),
+                (0x2A, ReportType::Output),
+            ]
+        );
+
+        let desc = load_fixture("046A_0011_0006_0001");
+        assert_eq!(
+            desc.report_ids(),
+            vec![(0, ReportType::Input), (0, ReportType::Output)]
+        );
+    }
+
+    #[test]
+    fn test_primary_usage() {
+        let desc = load_fixture("046A_0011_0006_0001");
+        assert_eq!(desc.primary_usage(), Some((0x01, 0x06)));
+
+        let desc = ReportDescriptor::parse(include_bytes!("../tests/assets/mouse2.data")).unwrap();
+        assert_eq!(desc.primary_usage(), Some((0x01, 0x02)));
+
+        let desc = ReportDescriptor::parse(&[]).unwrap();
+        assert_eq!(desc.primary_usage(), None);
+    }
+
+    #[test]
+    fn test_frame_unnumbered_report() {
+        let desc = load_fixture("046A_0011_0006_0001");
+        assert!(!desc.uses_report_ids());
+
+        let framed = desc.frame_report(ReportType::Output, None, &[0x02]);
+        assert_eq!(framed.unwrap(), vec![0x00, 0x02]);
+        let framed = desc.frame_report(ReportType::Output, None, &[]);
+        assert_eq!(framed.unwrap(), vec![0x00, 0x00]);
+        assert!(desc
+            .frame_report(ReportType::Output, Some(1), &[0x02])
+            .is_err());
+        assert!(desc
+            .frame_report(ReportType::Output, None, &[0x02, 0x03])
+            .is_err());
+        assert!(desc.frame_report(ReportType::Feature, None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_frame_numbered_report() {
+        let desc = load_fixture("046D_B010_0001_FF00");
+        assert!(desc.uses_report_ids());
+
+        let framed = desc.frame_report(ReportType::Output, Some(0x10), &[0x01, 0x02]);
+        assert_eq!(
+            framed.unwrap(),
+            vec![0x10, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert!(desc
+            .frame_report(ReportType::Output, None, &[0x01])
+            .is_err());
+        assert!(desc
+            .frame_report(ReportType::Output, Some(0), &[0x01])
+            .is_err());
+        assert!(desc
+            .frame_report(ReportType::Output, Some(0x11), &[0x01])
+            .is_err());
+        assert!(desc
+            .frame_report(ReportType::Output, Some(0x10), &[0; 7])
+            .is_err());
+    }
+
+    #[test]
+    fn test_check_numbered_report_id() {
+        let desc = load_fixture("17CC_1130_0000_FF01");
+
+        let report = desc.check_report_id(ReportType::Feature, 0xD0).unwrap();
+        assert_eq!(report.byte_len(), 32);
+        let report = desc.check_report_id(ReportType::Feature, 0xF1).unwrap();
+        assert_eq!(report.byte_len(), 2);
+        // 0xD7 is skipped by the device
+        assert!(desc.check_report_id(ReportType::Feature, 0xD7).is_err());
+        assert!(desc.check_report_id(ReportType::Feature, 0).is_err());
+    }
+
+    #[test]
+    fn test_check_unnumbered_report_id() {
+        let desc = ReportDescriptor::parse(UNNUMBERED_FEATURE_DESCRIPTOR).unwrap();
+
+        let report = desc.check_report_id(ReportType::Feature, 0).unwrap();
+        assert_eq!(report.byte_len(), 2);
+        assert!(desc.check_report_id(ReportType::Feature, 1).is_err());
+        assert!(desc.check_report_id(ReportType::Input, 0).is_err());
+    }
+
+    #[test]
+    fn test_extended_usage_range() {
+        let desc = load_fixture("046D_C52F_0001_000C");
+
+        assert_eq!(desc.usages(), &[(0x0c, 0x01)]);
+        let input = desc.report(3, ReportType::Input).unwrap();
+        assert_eq!(input.byte_len(), 4);
+    }
+
+    #[test]
+    fn test_all_fixtures_parse() {
+        for entry in std::fs::read_dir("./tests/pp_data").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "expected") {
+                let name = path.file_stem().unwrap().to_str().unwrap();
+                let desc = load_fixture(name);
+                // The fixture names contain the usage page and usage of the
+                // first top-level collection
+                let mut parts = name.split('_').skip(2);
+                let usage = u16::from_str_radix(parts.next().unwrap(), 16).unwrap();
+                let usage_page = u16::from_str_radix(parts.next().unwrap(), 16).unwrap();
+                assert_eq!(desc.usages()[0], (usage_page, usage), "{name}");
+                assert!(!desc.reports().is_empty(), "{name}");
+            }
+        }
+    }
+
+    /// A single feature report with 2 bytes of data and no report ID
+    const UNNUMBERED_FEATURE_DESCRIPTOR: &[u8] = &[
+        0x06, 0x00, 0xff, 0x09, 0x01, 0xa1, 0x01, // Vendor-defined, Collection
+        0x75, 0x08, 0x95, 0x02, 0xb1, 0x02, 0xc0, // 2 bytes feature, End Collection
+    ];
+
+    /// Two application collections separated by a long item whose payload looks like a Usage
+    /// Page and a Collection item
+    const LONG_ITEM_DESCRIPTOR: &[u8] = &[
+        0x05, 0x01, 0x09, 0x02, 0xa1, 0x01, // Generic Desktop, Mouse, Collection
+        0xfe, 0x04, 0x10, 0x05, 0x0c, 0xa1, 0x01, // Long item, 4 bytes of data
+        0x75, 0x08, 0x95, 0x01, 0x81, 0x02, 0xc0, // 1 byte input, End Collection
+        0x05, 0x0c, 0x09, 0x01, 0xa1, 0x01, // Consumer, Consumer Control, Collection
+        0x75, 0x08, 0x95, 0x02, 0x81, 0x02, 0xc0, // 2 bytes input, End Collection
+    ];
+
+    #[test]
+    fn test_long_item() {
+        let desc = ReportDescriptor::parse(LONG_ITEM_DESCRIPTOR).unwrap();
+
+        assert_eq!(desc.usages(), &[(0x01, 0x02), (0x0c, 0x01)]);
+        let input = desc.report(0, ReportType::Input).unwrap();
+        assert_eq!(input.byte_len(), 3);
+        assert_eq!((input.usage_page(), input.usage()), (0x01, 0x02));
+
+        // A long item that runs past the end of the descriptor
+        assert!(ReportDescriptor::parse(&[0xfe, 0x08, 0x10, 0x05, 0x01]).is_err());
+        assert!(ReportDescriptor::parse(&[0xfe]).is_err());
+    }
+
+    #[test]
+    fn test_long_item_truncated() {
+        // Every prefix and every long item length must be handled without panicking
+        let mut data = LONG_ITEM_DESCRIPTOR.to_vec();
+        for len in 0..=u8::MAX {
+            data[7] = len;
+            for end in 0..=data.len() {
+                let prefix = data[..end].to_vec();
+                let usages = UsageIterator::new(&prefix).collect::<Vec<_>>();
+                // The payload must never be read as the Consumer usage page
+                if end <= 13 && len == 4 {
+                    assert!(usages.iter().all(|&(page, _)| page == 0x01), "{usages:?}");
+                }
+                let _ = ReportDescriptor::parse(&prefix);
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_descriptor_size() {
+        assert!(check_descriptor_size(0).is_ok());
+        assert!(check_descriptor_size(MAX_REPORT_DESCRIPTOR_SIZE).is_ok());
+        assert!(matches!(
+            check_descriptor_size(MAX_REPORT_DESCRIPTOR_SIZE + 1),
+            Err(HidError::ReportDescriptorTooLarge { size }) if size == MAX_REPORT_DESCRIPTOR_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn test_malformed() {
+        // Truncated Usage Page item
+        assert!(ReportDescriptor::parse(&[0x06, 0x00]).is_err());
+        // End Collection without Collection
+        assert!(ReportDescriptor::parse(&[0xc0]).is_err());
+        // Report Size and Report Count of 0xFFFFFFFF overflow the length on 32 bit platforms, and
+        // the sum of two Input items on 64 bit platforms
+        let huge = [
+            0x77, 0xff, 0xff, 0xff, 0xff, 0x97, 0xff, 0xff, 0xff, 0xff, 0x81, 0x02, 0x81, 0x02,
+        ];
+        assert!(ReportDescriptor::parse(&huge).is_err());
+    }
+}
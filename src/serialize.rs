@@ -0,0 +1,72 @@
+//! Helpers for the `serde` feature
+
+/// (De)serializes a [`CString`](std::ffi::CString) path as a lossy UTF-8 string, for readability,
+/// plus its raw bytes, so paths that are not valid UTF-8 survive a round trip.
+pub(crate) mod path {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+    use std::ffi::CString;
+
+    #[derive(Serialize, Deserialize)]
+    struct SerializedPath {
+        path: String,
+        bytes: Vec<u8>,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        path: &CString,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        SerializedPath {
+            path: path.to_string_lossy().into_owned(),
+            bytes: path.as_bytes().to_vec(),
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<CString, D::Error> {
+        let path = SerializedPath::deserialize(deserializer)?;
+        CString::new(path.bytes).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::MockDevice;
+    use crate::{BusType, DeviceInfo, HidDevice, HidString};
+    use std::ffi::CString;
+
+    #[test]
+    fn test_round_trip() {
+        let mut info = HidDevice::from_mock(MockDevice::new(0x1234, 0x5678))
+            .get_device_info()
+            .unwrap();
+        info.path = CString::new(b"/dev/hidraw\xff".to_vec()).unwrap();
+        info.manufacturer_string = HidString::String("Manufacturer".into());
+        info.serial_number = HidString::Raw(vec![0x41, 0x42]);
+        info.bus_type = BusType::Bluetooth;
+        info.release_number = 0x0100;
+
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(json.contains(r#""path":"/dev/hidraw�""#));
+
+        let decoded: DeviceInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, info);
+        assert_eq!(decoded.path(), info.path());
+        assert_eq!(decoded.manufacturer_string(), Some("Manufacturer"));
+        assert_eq!(decoded.release_number(), 0x0100);
+        assert!(matches!(decoded.bus_type(), BusType::Bluetooth));
+    }
+
+    #[test]
+    fn test_path_with_nul_is_rejected() {
+        let info = HidDevice::from_mock(MockDevice::new(0, 0))
+            .get_device_info()
+            .unwrap();
+        let json = serde_json::to_string(&info)
+            .unwrap()
+            .replace(r#""bytes":[109,111,99,107]"#, r#""bytes":[0,1]"#);
+        assert!(serde_json::from_str::<DeviceInfo>(&json).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Counters of the reports a device read and wrote, for health metrics of long-running services
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{HidErrorKind, HidResult};
+
+/// A snapshot of the counters of a [`HidDevice`], see [`HidDevice::stats`].
+///
+/// Reads and writes are counted, feature reports are not.
+///
+/// [`HidDevice`]: crate::HidDevice
+/// [`HidDevice::stats`]: crate::HidDevice::stats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HidStats {
+    /// Input reports read
+    pub reports_read: u64,
+    /// Bytes of the input reports read
+    pub bytes_read: u64,
+    /// Output reports written
+    pub reports_written: u64,
+    /// Reads that returned without a report because the timeout expired or no report was
+    /// available in non-blocking mode, and writes that timed out
+    pub timeouts: u64,
+    /// Reads and writes that failed for any other reason
+    pub errors: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct Counters {
+    reports_read: AtomicU64,
+    bytes_read: AtomicU64,
+    reports_written: AtomicU64,
+    timeouts: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn count_read(&self, res: &HidResult<usize>) {
+        match res {
+            Ok(0) => self.timeouts.fetch_add(1, Ordering::Relaxed),
+            Ok(len) => {
+                self.bytes_read.fetch_add(*len as u64, Ordering::Relaxed);
+                self.reports_read.fetch_add(1, Ordering::Relaxed)
+            }
+            Err(e) => self.count_error(e.kind()),
+        };
+    }
+
+    pub(crate) fn count_write<T>(&self, res: &HidResult<T>) {
+        match res {
+            Ok(_) => self.reports_written.fetch_add(1, Ordering::Relaxed),
+            Err(e) => self.count_error(e.kind()),
+        };
+    }
+
+    fn count_error(&self, kind: HidErrorKind) -> u64 {
+        match kind {
+            HidErrorKind::Timeout => self.timeouts.fetch_add(1, Ordering::Relaxed),
+            _ => self.errors.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> HidStats {
+        HidStats {
+            reports_read: self.reports_read.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            reports_written: self.reports_written.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::MockDevice;
+    use crate::{HidDevice, HidError};
+
+    #[test]
+    fn test_stats() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+        assert_eq!(device.stats(), HidStats::default());
+
+        mock.push_input(vec![0x01, 0x02, 0x03]);
+        mock.push_input(vec![0x01, 0x04]);
+        let mut buf = [0u8; 8];
+        device.read(&mut buf).unwrap();
+        device.read_timeout(&mut buf, 0).unwrap();
+        assert_eq!(device.read_timeout(&mut buf, 0).unwrap(), 0);
+        assert!(matches!(
+            device.read_timeout_strict(&mut buf, 0),
+            Err(HidError::Timeout)
+        ));
+
+        device.write(&[0x00, 0x01]).unwrap();
+        device.send_output_report(&[0x00, 0x02]).unwrap();
+        assert!(device.write(&[]).is_err());
+
+        assert_eq!(
+            device.stats(),
+            HidStats {
+                reports_read: 2,
+                bytes_read: 5,
+                reports_written: 2,
+                timeouts: 2,
+                errors: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_would_block_is_no_timeout() {
+        let counters = Counters::default();
+        let would_block = std::io::Error::from(std::io::ErrorKind::WouldBlock);
+        counters.count_write::<usize>(&Err(HidError::from(would_block)));
+        let stats = counters.snapshot();
+        assert_eq!((stats.timeouts, stats.errors), (0, 1));
+    }
+}
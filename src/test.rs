@@ -0,0 +1,529 @@
+//! An in-memory backend for testing code that uses [`HidDevice`] without hardware.
+//!
+//! ```
+//! use hidapi::test::MockDevice;
+//! use hidapi::HidDevice;
+//!
+//! let mock = MockDevice::new(0x1234, 0x5678);
+//! let device = HidDevice::from_mock(mock.clone());
+//!
+//! mock.push_input(vec![0x01, 0x02, 0x03]);
+//! let mut buf = [0u8; 8];
+//! assert_eq!(device.read(&mut buf).unwrap(), 3);
+//!
+//! device.write(&[0x00, 0xAA]).unwrap();
+//! assert_eq!(mock.take_writes(), vec![vec![0x00, 0xAA]]);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CString;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::{
+    BusType, DeviceInfo, HidDevice, HidDeviceBackendBase, HidError, HidProtocol, HidResult,
+    HidString,
+};
+
+#[derive(Clone, Default)]
+struct MockStrings {
+    manufacturer: Option<String>,
+    product: Option<String>,
+    serial_number: Option<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    blocking: bool,
+    closed: bool,
+    disconnected: bool,
+    input: VecDeque<Vec<u8>>,
+    writes: Vec<Vec<u8>>,
+    sent_output_reports: Vec<Vec<u8>>,
+    sent_feature_reports: Vec<Vec<u8>>,
+    feature_reports: HashMap<u8, Vec<u8>>,
+    input_reports: HashMap<u8, Vec<u8>>,
+    report_descriptor: Vec<u8>,
+    protocol: HidProtocol,
+    idle_rates: HashMap<u8, u8>,
+    strings: MockStrings,
+    read_strings: MockStrings,
+    usage: (u16, u16),
+    read_usage: (u16, u16),
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<MockState>,
+    input_ready: Condvar,
+}
+
+/// A fake device with programmable input and captured output.
+///
+/// Clones share the same state, so keep a clone around to feed input reports to and inspect the
+/// output of a [`HidDevice`] created with [`HidDevice::from_mock`].
+#[derive(Clone)]
+pub struct MockDevice {
+    vendor_id: u16,
+    product_id: u16,
+    shared: Arc<Shared>,
+}
+
+impl MockDevice {
+    /// Create a mock device with the given vendor and product ID.
+    pub fn new(vendor_id: u16, product_id: u16) -> Self {
+        let shared = Shared::default();
+        shared.state.lock().unwrap().blocking = true;
+        Self {
+            vendor_id,
+            product_id,
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// Queue an input report that will be returned by the next read.
+    pub fn push_input(&self, report: impl Into<Vec<u8>>) {
+        self.state().input.push_back(report.into());
+        self.shared.input_ready.notify_all();
+    }
+
+    /// Set the report returned by `get_feature_report`, keyed on its first byte (the report ID).
+    pub fn set_feature_report(&self, report: impl Into<Vec<u8>>) {
+        let report = report.into();
+        if let Some(&id) = report.first() {
+            self.state().feature_reports.insert(id, report);
+        }
+    }
+
+    /// Set the report returned by `get_input_report`, keyed on its first byte (the report ID).
+    pub fn set_input_report(&self, report: impl Into<Vec<u8>>) {
+        let report = report.into();
+        if let Some(&id) = report.first() {
+            self.state().input_reports.insert(id, report);
+        }
+    }
+
+    /// Set the report descriptor returned by `get_report_descriptor`.
+    pub fn set_report_descriptor(&self, descriptor: impl Into<Vec<u8>>) {
+        self.state().report_descriptor = descriptor.into();
+    }
+
+    /// Set the usage page and usage of the device, e.g. to simulate a mode switch that changes
+    /// the report descriptor.
+    ///
+    /// Like the strings, the new usage is only reported after a refresh, see
+    /// [`HidDevice::refresh_device_info`].
+    pub fn set_usage(&self, usage_page: u16, usage: u16) {
+        self.state().usage = (usage_page, usage);
+    }
+
+    /// Set the strings reported by the device.
+    ///
+    /// Like a real device, the [`HidDevice`] keeps returning the strings it read before until
+    /// [`HidDevice::refresh_strings`] is called.
+    pub fn set_strings(
+        &self,
+        manufacturer: Option<&str>,
+        product: Option<&str>,
+        serial_number: Option<&str>,
+    ) {
+        self.state().strings = MockStrings {
+            manufacturer: manufacturer.map(str::to_string),
+            product: product.map(str::to_string),
+            serial_number: serial_number.map(str::to_string),
+        };
+    }
+
+    /// Take all reports written with `write` so far.
+    pub fn take_writes(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state().writes)
+    }
+
+    /// Take all reports sent with `send_output_report` so far.
+    ///
+    /// These are kept apart from the writes, because real devices receive them over the control
+    /// endpoint instead of the interrupt OUT endpoint.
+    pub fn take_output_reports(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state().sent_output_reports)
+    }
+
+    /// Take all reports sent with `send_feature_report` so far.
+    pub fn take_feature_reports(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.state().sent_feature_reports)
+    }
+
+    /// Check if the device is in blocking mode, which it is until it is changed.
+    pub fn is_blocking(&self) -> bool {
+        self.state().blocking
+    }
+
+    /// Check if the device has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.state().closed
+    }
+
+    /// Simulate unplugging the device, every following operation fails with
+    /// [`HidError::Disconnected`].
+    ///
+    /// Blocked reads return right away.
+    pub fn disconnect(&self) {
+        self.state().disconnected = true;
+        self.shared.input_ready.notify_all();
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.shared.state.lock().unwrap()
+    }
+
+    /// Lock the state for an operation that needs the device to be connected.
+    fn connected(&self) -> HidResult<MutexGuard<'_, MockState>> {
+        let state = self.state();
+        if state.disconnected {
+            return Err(HidError::Disconnected);
+        }
+        Ok(state)
+    }
+
+    /// Wait for a queued report and copy it into `buf`, returns `None` if the timeout expired.
+    fn read_report(&self, buf: &mut [u8], timeout: i32) -> HidResult<Option<usize>> {
+        let mut state = self.connected()?;
+        let waiting = |s: &mut MockState| s.input.is_empty() && !s.disconnected;
+        if timeout < 0 {
+            state = self.shared.input_ready.wait_while(state, waiting).unwrap();
+        } else if timeout > 0 {
+            state = self
+                .shared
+                .input_ready
+                .wait_timeout_while(state, Duration::from_millis(timeout as u64), waiting)
+                .unwrap()
+                .0;
+        }
+        if state.disconnected {
+            return Err(HidError::Disconnected);
+        }
+        Ok(state
+            .input
+            .pop_front()
+            .map(|report| copy_report(&report, buf)))
+    }
+}
+
+impl HidDevice {
+    /// Create a [`HidDevice`] backed by a [`MockDevice`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "mock")))]
+    pub fn from_mock(mock: MockDevice) -> Self {
+        Self::from_backend(Box::new(mock))
+    }
+}
+
+fn copy_report(report: &[u8], buf: &mut [u8]) -> usize {
+    let len = report.len().min(buf.len());
+    buf[..len].copy_from_slice(&report[..len]);
+    len
+}
+
+fn wchar_string(s: Option<String>) -> HidString {
+    s.map_or(HidString::None, HidString::String)
+}
+
+impl HidDeviceBackendBase for MockDevice {
+    #[cfg(hidapi)]
+    fn check_error(&self) -> HidResult<HidError> {
+        Ok(HidError::HidApiErrorEmpty)
+    }
+
+    fn write(&self, data: &[u8]) -> HidResult<usize> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        self.connected()?.writes.push(data.to_vec());
+        Ok(data.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let timeout = if self.state().blocking { -1 } else { 0 };
+        self.read_timeout(buf, timeout)
+    }
+
+    fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        Ok(self.read_report(buf, timeout)?.unwrap_or(0))
+    }
+
+    fn read_timeout_strict(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        self.read_report(buf, timeout)?.ok_or(HidError::Timeout)
+    }
+
+    fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        self.connected()?.sent_feature_reports.push(data.to_vec());
+        Ok(())
+    }
+
+    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        let id = *buf.first().ok_or(HidError::InvalidZeroSizeData)?;
+        match self.connected()?.feature_reports.get(&id) {
+            Some(report) => Ok(copy_report(report, buf)),
+            None => Err(HidError::HidApiError {
+                message: format!("no feature report with id {id}"),
+            }),
+        }
+    }
+
+    fn send_output_report(&self, data: &[u8]) -> HidResult<()> {
+        if data.is_empty() {
+            return Err(HidError::InvalidZeroSizeData);
+        }
+        self.connected()?.sent_output_reports.push(data.to_vec());
+        Ok(())
+    }
+
+    #[cfg(any(hidapi, target_os = "linux"))]
+    fn get_input_report(&self, data: &mut [u8]) -> HidResult<usize> {
+        let id = *data.first().ok_or(HidError::InvalidZeroSizeData)?;
+        match self.connected()?.input_reports.get(&id) {
+            Some(report) => Ok(copy_report(report, data)),
+            None => Err(HidError::HidApiError {
+                message: format!("no input report with id {id}"),
+            }),
+        }
+    }
+
+    fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
+        self.state().blocking = blocking;
+        Ok(())
+    }
+
+    fn get_device_info(&self) -> HidResult<DeviceInfo> {
+        let state = self.state();
+        let strings = state.read_strings.clone();
+        let (usage_page, usage) = state.read_usage;
+        drop(state);
+        Ok(DeviceInfo {
+            path: CString::new("mock").unwrap(),
+            vendor_id: self.vendor_id,
+            product_id: self.product_id,
+            serial_number: wchar_string(strings.serial_number),
+            release_number: 0,
+            manufacturer_string: wchar_string(strings.manufacturer),
+            product_string: wchar_string(strings.product),
+            usage_page,
+            usage,
+            interface_number: -1,
+            bus_type: BusType::Unknown,
+            location_id: None,
+            bluetooth_address: None,
+            group_id: None,
+        })
+    }
+
+    fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
+        Ok(self.state().read_strings.manufacturer.clone())
+    }
+
+    fn get_product_string(&self) -> HidResult<Option<String>> {
+        Ok(self.state().read_strings.product.clone())
+    }
+
+    fn get_serial_number_string(&self) -> HidResult<Option<String>> {
+        Ok(self.state().read_strings.serial_number.clone())
+    }
+
+    fn refresh_strings(&self) -> HidResult<()> {
+        let mut state = self.state();
+        state.read_strings = state.strings.clone();
+        state.read_usage = state.usage;
+        Ok(())
+    }
+
+    fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
+        Ok(copy_report(&self.connected()?.report_descriptor, buf))
+    }
+
+    fn close(&self) -> HidResult<()> {
+        self.state().closed = true;
+        Ok(())
+    }
+
+    fn set_protocol(&self, protocol: HidProtocol) -> HidResult<()> {
+        self.connected()?.protocol = protocol;
+        Ok(())
+    }
+
+    fn get_protocol(&self) -> HidResult<HidProtocol> {
+        Ok(self.connected()?.protocol)
+    }
+
+    fn set_idle(&self, report_id: u8, rate: u8) -> HidResult<()> {
+        self.connected()?.idle_rates.insert(report_id, rate);
+        Ok(())
+    }
+
+    fn get_idle(&self, report_id: u8) -> HidResult<u8> {
+        Ok(self
+            .connected()?
+            .idle_rates
+            .get(&report_id)
+            .copied()
+            .unwrap_or(0))
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl crate::HidDeviceBackendWindows for MockDevice {
+    fn get_container_id(&self) -> HidResult<crate::GUID> {
+        Err(HidError::HidApiError {
+            message: "get_container_id: not supported".to_string(),
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl crate::HidDeviceBackendMacos for MockDevice {
+    fn get_location_id(&self) -> HidResult<u32> {
+        Err(HidError::HidApiError {
+            message: "get_location_id: not supported".to_string(),
+        })
+    }
+
+    fn is_open_exclusive(&self) -> HidResult<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_reports() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_feature_report(vec![0x02, 0x10, 0x20]);
+
+        let mut buf = [0x02, 0, 0, 0];
+        assert_eq!(device.get_feature_report(&mut buf).unwrap(), 3);
+        assert_eq!(buf, [0x02, 0x10, 0x20, 0]);
+        assert!(device.get_feature_report(&mut [0x03, 0]).is_err());
+
+        device.send_feature_report(&[0x02, 0xFF]).unwrap();
+        assert_eq!(mock.take_feature_reports(), vec![vec![0x02, 0xFF]]);
+    }
+
+    #[test]
+    fn test_non_blocking_read() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+        device.set_blocking_mode(false).unwrap();
+
+        let mut buf = [0u8; 4];
+        assert_eq!(device.read(&mut buf).unwrap(), 0);
+        assert_eq!(device.read_timeout(&mut buf, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_blocking_read_waits_for_input() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        let feeder = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            mock.push_input(vec![0x01]);
+        });
+        let mut buf = [0u8; 4];
+        assert_eq!(device.read(&mut buf).unwrap(), 1);
+        feeder.join().unwrap();
+    }
+
+    #[test]
+    fn test_disconnect_wakes_blocked_read() {
+        let mock = MockDevice::new(0, 0);
+        let device = HidDevice::from_mock(mock.clone());
+
+        let unplug = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            mock.disconnect();
+        });
+        let mut buf = [0u8; 4];
+        assert!(matches!(device.read(&mut buf), Err(HidError::Disconnected)));
+        assert!(matches!(device.write(&[0x00]), Err(HidError::Disconnected)));
+        unplug.join().unwrap();
+    }
+
+    #[test]
+    fn test_protocol() {
+        let device = HidDevice::from_mock(MockDevice::new(0, 0));
+        assert_eq!(device.get_protocol().unwrap(), HidProtocol::Report);
+        device.set_protocol(HidProtocol::Boot).unwrap();
+        assert_eq!(device.get_protocol().unwrap(), HidProtocol::Boot);
+    }
+
+    #[test]
+    fn test_idle() {
+        let device = HidDevice::from_mock(MockDevice::new(0, 0));
+        assert_eq!(device.get_idle(1).unwrap(), Duration::ZERO);
+        device.set_idle(1, Duration::from_millis(499)).unwrap();
+        assert_eq!(device.get_idle(1).unwrap(), Duration::from_millis(500));
+        assert_eq!(device.get_idle(2).unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_refresh_device_info() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+        mock.set_usage(0x01, 0x06);
+        device.refresh_device_info().unwrap();
+
+        // The device switches into another mode
+        mock.set_usage(0xFF00, 0x01);
+        mock.set_strings(None, Some("Bootloader"), None);
+        assert_eq!(device.get_device_info().unwrap().usage_page, 0x01);
+        let info = device.refresh_device_info().unwrap();
+        assert_eq!((info.usage_page, info.usage), (0xFF00, 0x01));
+        assert_eq!(info.product_string(), Some("Bootloader"));
+        assert_eq!(device.get_device_info().unwrap().usage_page, 0xFF00);
+    }
+
+    #[test]
+    fn test_refresh_strings() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+        assert_eq!(device.get_product_string().unwrap(), None);
+
+        mock.set_strings(Some("Vendor"), Some("Keyboard"), Some("0001"));
+        assert_eq!(device.get_product_string().unwrap(), None);
+        device.refresh_strings().unwrap();
+        assert_eq!(
+            device.get_manufacturer_string().unwrap().as_deref(),
+            Some("Vendor")
+        );
+        assert_eq!(
+            device.get_product_string().unwrap().as_deref(),
+            Some("Keyboard")
+        );
+        assert_eq!(
+            device.get_serial_number_string().unwrap().as_deref(),
+            Some("0001")
+        );
+
+        mock.set_strings(Some("Vendor"), Some("Bootloader"), None);
+        device.refresh_strings().unwrap();
+        let info = device.get_device_info().unwrap();
+        assert_eq!(info.product_string(), Some("Bootloader"));
+        assert_eq!(info.serial_number(), None);
+    }
+
+    #[test]
+    fn test_device_info_and_close() {
+        let mock = MockDevice::new(0x1234, 0x5678);
+        let device = HidDevice::from_mock(mock.clone());
+
+        let info = device.get_device_info().unwrap();
+        assert_eq!(info.vendor_id(), 0x1234);
+        assert_eq!(info.product_id(), 0x5678);
+
+        device.close().unwrap();
+        assert!(mock.is_closed());
+    }
+}
@@ -0,0 +1,255 @@
+//! Names of usage pages and usages from the HID Usage Tables
+//!
+//! Only the usages that identify devices and their most common controls are included, the full
+//! tables are much larger.
+
+/// The names of the usage pages, sorted by page
+const USAGE_PAGES: &[(u16, &str)] = &[
+    (0x01, "Generic Desktop"),
+    (0x02, "Simulation Controls"),
+    (0x03, "VR Controls"),
+    (0x04, "Sport Controls"),
+    (0x05, "Game Controls"),
+    (0x06, "Generic Device Controls"),
+    (0x07, "Keyboard/Keypad"),
+    (0x08, "LED"),
+    (0x09, "Button"),
+    (0x0A, "Ordinal"),
+    (0x0B, "Telephony Device"),
+    (0x0C, "Consumer"),
+    (0x0D, "Digitizers"),
+    (0x0E, "Haptics"),
+    (0x0F, "Physical Input Device"),
+    (0x10, "Unicode"),
+    (0x11, "SoC"),
+    (0x12, "Eye and Head Trackers"),
+    (0x14, "Auxiliary Display"),
+    (0x20, "Sensors"),
+    (0x40, "Medical Instrument"),
+    (0x41, "Braille Display"),
+    (0x59, "Lighting And Illumination"),
+    (0x80, "Monitor"),
+    (0x81, "Monitor Enumerated"),
+    (0x82, "VESA Virtual Controls"),
+    (0x84, "Power"),
+    (0x85, "Battery System"),
+    (0x8C, "Barcode Scanner"),
+    (0x8D, "Scales"),
+    (0x8E, "Magnetic Stripe Reader"),
+    (0x90, "Camera Control"),
+    (0x91, "Arcade"),
+    (0x92, "Gaming Device"),
+    (0xF1D0, "FIDO Alliance"),
+];
+
+const GENERIC_DESKTOP: &[(u16, &str)] = &[
+    (0x01, "Pointer"),
+    (0x02, "Mouse"),
+    (0x04, "Joystick"),
+    (0x05, "Gamepad"),
+    (0x06, "Keyboard"),
+    (0x07, "Keypad"),
+    (0x08, "Multi-axis Controller"),
+    (0x09, "Tablet PC System Controls"),
+    (0x0A, "Water Cooling Device"),
+    (0x0B, "Computer Chassis Device"),
+    (0x0C, "Wireless Radio Controls"),
+    (0x0D, "Portable Device Control"),
+    (0x0E, "System Multi-Axis Controller"),
+    (0x0F, "Spatial Controller"),
+    (0x10, "Assistive Control"),
+    (0x11, "Device Dock"),
+    (0x12, "Dockable Device"),
+    (0x13, "Call State Management Control"),
+    (0x30, "X"),
+    (0x31, "Y"),
+    (0x32, "Z"),
+    (0x33, "Rx"),
+    (0x34, "Ry"),
+    (0x35, "Rz"),
+    (0x36, "Slider"),
+    (0x37, "Dial"),
+    (0x38, "Wheel"),
+    (0x39, "Hat Switch"),
+    (0x3A, "Counted Buffer"),
+    (0x3B, "Byte Count"),
+    (0x3C, "Motion Wakeup"),
+    (0x3D, "Start"),
+    (0x3E, "Select"),
+    (0x40, "Vx"),
+    (0x41, "Vy"),
+    (0x42, "Vz"),
+    (0x43, "Vbrx"),
+    (0x44, "Vbry"),
+    (0x45, "Vbrz"),
+    (0x46, "Vno"),
+    (0x47, "Feature Notification"),
+    (0x48, "Resolution Multiplier"),
+    (0x80, "System Control"),
+    (0x81, "System Power Down"),
+    (0x82, "System Sleep"),
+    (0x83, "System Wake Up"),
+    (0x84, "System Context Menu"),
+    (0x85, "System Main Menu"),
+    (0x86, "System App Menu"),
+    (0x87, "System Menu Help"),
+    (0x88, "System Menu Exit"),
+    (0x89, "System Menu Select"),
+    (0x8A, "System Menu Right"),
+    (0x8B, "System Menu Left"),
+    (0x8C, "System Menu Up"),
+    (0x8D, "System Menu Down"),
+    (0x90, "D-pad Up"),
+    (0x91, "D-pad Down"),
+    (0x92, "D-pad Right"),
+    (0x93, "D-pad Left"),
+];
+
+const LED: &[(u16, &str)] = &[
+    (0x01, "Num Lock"),
+    (0x02, "Caps Lock"),
+    (0x03, "Scroll Lock"),
+    (0x04, "Compose"),
+    (0x05, "Kana"),
+];
+
+const TELEPHONY: &[(u16, &str)] = &[
+    (0x01, "Phone"),
+    (0x02, "Answering Machine"),
+    (0x03, "Message Controls"),
+    (0x04, "Handset"),
+    (0x05, "Headset"),
+    (0x06, "Telephony Key Pad"),
+    (0x07, "Programmable Button"),
+];
+
+const CONSUMER: &[(u16, &str)] = &[
+    (0x01, "Consumer Control"),
+    (0x02, "Numeric Key Pad"),
+    (0x03, "Programmable Buttons"),
+    (0x04, "Microphone"),
+    (0x05, "Headphone"),
+    (0x06, "Graphic Equalizer"),
+    (0xB0, "Play"),
+    (0xB1, "Pause"),
+    (0xB2, "Record"),
+    (0xB3, "Fast Forward"),
+    (0xB4, "Rewind"),
+    (0xB5, "Scan Next Track"),
+    (0xB6, "Scan Previous Track"),
+    (0xB7, "Stop"),
+    (0xB8, "Eject"),
+    (0xCD, "Play/Pause"),
+    (0xE2, "Mute"),
+    (0xE9, "Volume Increment"),
+    (0xEA, "Volume Decrement"),
+    (0x238, "AC Pan"),
+];
+
+const DIGITIZERS: &[(u16, &str)] = &[
+    (0x01, "Digitizer"),
+    (0x02, "Pen"),
+    (0x03, "Light Pen"),
+    (0x04, "Touch Screen"),
+    (0x05, "Touch Pad"),
+    (0x06, "Whiteboard"),
+    (0x07, "Coordinate Measuring Machine"),
+    (0x08, "3D Digitizer"),
+    (0x09, "Stereo Plotter"),
+    (0x0A, "Articulated Arm"),
+    (0x0B, "Armature"),
+    (0x0C, "Multiple Point Digitizer"),
+    (0x0D, "Free Space Wand"),
+    (0x0E, "Device Configuration"),
+    (0x0F, "Capacitive Heat Map Digitizer"),
+    (0x20, "Stylus"),
+    (0x22, "Finger"),
+    (0x42, "Tip Switch"),
+    (0x51, "Contact Identifier"),
+    (0x54, "Contact Count"),
+];
+
+const SENSORS: &[(u16, &str)] = &[(0x01, "Sensor")];
+
+const FIDO_ALLIANCE: &[(u16, &str)] = &[
+    (0x01, "U2F Authenticator Device"),
+    (0x20, "Input Report Data"),
+    (0x21, "Output Report Data"),
+];
+
+/// Get the name of a usage page, e.g. `"Generic Desktop"` for page `0x01`.
+///
+/// The pages `0xFF00` to `0xFFFF` are named `"Vendor-defined"`. Requires the `usage-tables`
+/// feature.
+pub fn usage_page_name(usage_page: u16) -> Option<&'static str> {
+    if usage_page >= 0xFF00 {
+        return Some("Vendor-defined");
+    }
+    lookup(USAGE_PAGES, usage_page)
+}
+
+/// Get the name of a usage, e.g. `"Mouse"` for usage `0x02` of page `0x01`.
+///
+/// Requires the `usage-tables` feature.
+pub fn usage_name(usage_page: u16, usage: u16) -> Option<&'static str> {
+    let table = match usage_page {
+        0x01 => GENERIC_DESKTOP,
+        0x08 => LED,
+        0x0B => TELEPHONY,
+        0x0C => CONSUMER,
+        0x0D => DIGITIZERS,
+        0x20 => SENSORS,
+        0xF1D0 => FIDO_ALLIANCE,
+        _ => return None,
+    };
+    lookup(table, usage)
+}
+
+fn lookup(table: &'static [(u16, &'static str)], id: u16) -> Option<&'static str> {
+    table
+        .binary_search_by_key(&id, |&(id, _)| id)
+        .ok()
+        .map(|index| table[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables_sorted() {
+        for table in [
+            USAGE_PAGES,
+            GENERIC_DESKTOP,
+            LED,
+            TELEPHONY,
+            CONSUMER,
+            DIGITIZERS,
+            SENSORS,
+            FIDO_ALLIANCE,
+        ] {
+            assert!(table.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+    }
+
+    #[test]
+    fn test_usage_page_name() {
+        assert_eq!(usage_page_name(0x01), Some("Generic Desktop"));
+        assert_eq!(usage_page_name(0x0C), Some("Consumer"));
+        assert_eq!(usage_page_name(0xF1D0), Some("FIDO Alliance"));
+        assert_eq!(usage_page_name(0xFF00), Some("Vendor-defined"));
+        assert_eq!(usage_page_name(0x13), None);
+    }
+
+    #[test]
+    fn test_usage_name() {
+        assert_eq!(usage_name(0x01, 0x02), Some("Mouse"));
+        assert_eq!(usage_name(0x01, 0x06), Some("Keyboard"));
+        assert_eq!(usage_name(0x01, 0x80), Some("System Control"));
+        assert_eq!(usage_name(0x0C, 0x01), Some("Consumer Control"));
+        assert_eq!(usage_name(0x0D, 0x05), Some("Touch Pad"));
+        assert_eq!(usage_name(0xF1D0, 0x01), Some("U2F Authenticator Device"));
+        assert_eq!(usage_name(0x01, 0x03), None);
+        assert_eq!(usage_name(0xFF00, 0x01), None);
+    }
+}
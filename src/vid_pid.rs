@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A vendor and product ID pair, as commonly written in `04d9:a052` form.
+///
+/// ```
+/// use hidapi::VidPid;
+///
+/// let id: VidPid = "0x04d9:A052".parse().unwrap();
+/// assert_eq!(id, VidPid { vid: 0x04d9, pid: 0xa052 });
+/// assert_eq!(id.to_string(), "04d9:a052");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VidPid {
+    pub vid: u16,
+    pub pid: u16,
+}
+
+impl VidPid {
+    /// Create a pair from a vendor and product ID.
+    pub fn new(vid: u16, pid: u16) -> Self {
+        Self { vid, pid }
+    }
+}
+
+impl Display for VidPid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}:{:04x}", self.vid, self.pid)
+    }
+}
+
+impl FromStr for VidPid {
+    type Err = ParseVidPidError;
+
+    /// Parse two hexadecimal IDs separated by a colon, each with an optional `0x` prefix.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (vid, pid) = s.split_once(':').ok_or(ParseVidPidError(()))?;
+        Ok(Self {
+            vid: parse_id(vid)?,
+            pid: parse_id(pid)?,
+        })
+    }
+}
+
+fn parse_id(s: &str) -> Result<u16, ParseVidPidError> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    // from_str_radix would also accept a sign
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ParseVidPidError(()));
+    }
+    u16::from_str_radix(digits, 16).map_err(|_| ParseVidPidError(()))
+}
+
+/// The error returned when parsing a [`VidPid`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVidPidError(());
+
+impl Display for ParseVidPidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a vendor and product ID in the form `vid:pid`")
+    }
+}
+
+impl Error for ParseVidPidError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let id = VidPid::new(0x04d9, 0xa052);
+        assert_eq!("04d9:a052".parse(), Ok(id));
+        assert_eq!("0x04D9:0XA052".parse(), Ok(id));
+        assert_eq!("4d9:a052".parse(), Ok(id));
+        assert_eq!("0:ffff".parse(), Ok(VidPid::new(0, 0xffff)));
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        for s in [
+            "",
+            ":",
+            "04d9",
+            "04d9:",
+            ":a052",
+            "04d9:a052:01",
+            "0x:a052",
+            "+4d9:a052",
+            "04d9 :a052",
+            "g4d9:a052",
+            "10000:a052",
+            "04d9:1a052",
+        ] {
+            assert!(s.parse::<VidPid>().is_err(), "{s:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(VidPid::new(0x4d9, 0xa).to_string(), "04d9:000a");
+        let id = VidPid::new(0xffff, 0x1234);
+        assert_eq!(id.to_string().parse(), Ok(id));
+    }
+}
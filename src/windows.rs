@@ -1,4 +1,10 @@
-use crate::{HidDevice, HidResult};
+use std::time::Duration;
+
+use crate::{HidDevice, HidError, HidResult};
+
+/// A Windows GUID, as returned by [`HidDevice::get_container_id`].
+///
+/// Re-exported from `windows-sys`, so it can be used without depending on that crate.
 pub use windows_sys::core::GUID;
 
 impl HidDevice {
@@ -7,7 +13,55 @@ impl HidDevice {
     /// This function returns the `DEVPKEY_Device_ContainerId` property of the
     /// given device. This can be used to correlate different interfaces/ports
     /// on the same hardware device.
+    ///
+    /// A composite device, e.g. a keyboard with a separate interface for its media keys, shows
+    /// up as multiple HID devices which all share the same container ID.
     pub fn get_container_id(&self) -> HidResult<GUID> {
         self.inner.get_container_id()
     }
+
+    /// Set the number of input reports the driver buffers for the device.
+    ///
+    /// The driver keeps the input reports that arrive while no read is pending in a ring buffer
+    /// and drops the oldest ones when it is full. A larger buffer helps with devices that send
+    /// reports at a high rate, like gaming mice, while a smaller one keeps latency-sensitive code
+    /// from reading stale reports. Devices are opened with a buffer of 64 reports.
+    ///
+    /// `count` must be between 2 and 512. Only the `windows-native` backend supports this.
+    pub fn set_input_report_buffer_count(&self, count: u32) -> HidResult<()> {
+        if !(2..=512).contains(&count) {
+            return Err(HidError::HidApiError {
+                message: format!(
+                    "input report buffer count must be between 2 and 512, got {count}"
+                ),
+            });
+        }
+        self.inner.set_input_report_buffer_count(count)
+    }
+
+    /// Get the number of input reports the driver buffers for the device.
+    ///
+    /// See [`HidDevice::set_input_report_buffer_count`].
+    pub fn get_input_report_buffer_count(&self) -> HidResult<u32> {
+        self.inner.get_input_report_buffer_count()
+    }
+
+    /// Get a copy of the preparsed data of the device.
+    ///
+    /// This is the `HIDP_PREPARSED_DATA` Windows builds from the report descriptor, for passing
+    /// to the `HidP_*` functions of `hid.dll` directly. [`HidDevice::get_report_descriptor`]
+    /// reconstructs the descriptor from the same data. Only the `windows-native` backend supports
+    /// this.
+    pub fn preparsed_data(&self) -> HidResult<Vec<u8>> {
+        self.inner.preparsed_data()
+    }
+
+    /// Get the interval in which the host polls the device for input reports.
+    ///
+    /// This is read from the interrupt IN endpoint of the USB interface, it bounds the rate of
+    /// input reports, e.g. 1ms for a gaming mouse with a report rate of 1000Hz. Returns `None` for
+    /// devices on other buses. Only the `windows-native` backend supports this.
+    pub fn polling_interval(&self) -> HidResult<Option<Duration>> {
+        self.inner.polling_interval()
+    }
 }
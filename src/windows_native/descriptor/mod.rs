@@ -14,6 +14,7 @@ use crate::windows_native::hid::PreparsedData;
 use crate::windows_native::utils::PeakIterExt;
 use std::collections::HashMap;
 use std::ffi::c_void;
+use std::mem::size_of;
 use std::slice;
 
 pub fn get_descriptor(pp_data: &PreparsedData) -> WinResult<Vec<u8>> {
@@ -28,6 +29,26 @@ unsafe fn get_descriptor_ptr(pp_data: *const c_void) -> WinResult<Vec<u8>> {
     encode_descriptor(&list, caps_list, link_collection_nodes)
 }
 
+/// Copy the raw preparsed data, which ends with the link collection array.
+pub fn copy_preparsed_data(pp_data: &PreparsedData) -> WinResult<Vec<u8>> {
+    unsafe { copy_preparsed_data_ptr(pp_data.as_ptr()) }
+}
+
+unsafe fn copy_preparsed_data_ptr(pp_data: *const c_void) -> WinResult<Vec<u8>> {
+    let header: *const HidpPreparsedData = pp_data as _;
+
+    ensure!(
+        &(*header).magic_key == b"HidP KDR",
+        Err(WinError::InvalidPreparsedData)
+    );
+
+    // The offset of the link collection array is relative to the caps, which follow the header
+    let len = size_of::<HidpPreparsedData>()
+        + (*header).first_byte_of_link_collection_array as usize
+        + (*header).number_link_collection_nodes as usize * size_of::<LinkCollectionNode>();
+    Ok(slice::from_raw_parts(pp_data as *const u8, len).to_vec())
+}
+
 unsafe fn extract_structures<'a>(
     pp_data: *const c_void,
 ) -> WinResult<(HidpPreparsedData, &'a [Caps], &'a [LinkCollectionNode])> {
@@ -1,4 +1,4 @@
-use crate::windows_native::descriptor::get_descriptor_ptr;
+use crate::windows_native::descriptor::{copy_preparsed_data_ptr, get_descriptor_ptr};
 use std::fs::read_to_string;
 
 #[test]
@@ -106,6 +106,11 @@ fn execute_testcase(filename: &str) {
     let expected_descriptor = decode_hex(&read_to_string(&expected_path).unwrap());
     let constructed_descriptor = unsafe { get_descriptor_ptr(pp_data.as_ptr() as _) }.unwrap();
     assert_eq!(constructed_descriptor, expected_descriptor);
+
+    // The dumps contain exactly the preparsed data
+    let copied = unsafe { copy_preparsed_data_ptr(pp_data.as_ptr() as _) }.unwrap();
+    assert!(copied.starts_with(b"HidP KDR"));
+    assert_eq!(copied, pp_data);
 }
 
 fn decode_hex(hex: &str) -> Vec<u8> {
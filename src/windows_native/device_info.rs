@@ -4,15 +4,16 @@ use crate::windows_native::hid::{get_hid_attributes, PreparsedData};
 use crate::windows_native::interfaces::Interface;
 use crate::windows_native::string::{U16Str, U16String, U16StringList};
 use crate::windows_native::types::{Handle, InternalBusType};
-use crate::{BusType, DeviceInfo, WcharString};
+use crate::{parse_bluetooth_address, BusType, DeviceInfo, GroupId, HidString};
 use std::ffi::{c_void, CString};
 use std::mem::{size_of, zeroed};
+use windows_sys::core::GUID;
 use windows_sys::Win32::Devices::HumanInterfaceDevice::{
     HidD_GetManufacturerString, HidD_GetProductString, HidD_GetSerialNumberString,
 };
 use windows_sys::Win32::Devices::Properties::{
-    DEVPKEY_Device_CompatibleIds, DEVPKEY_Device_HardwareIds, DEVPKEY_Device_InstanceId,
-    DEVPKEY_Device_Manufacturer, DEVPKEY_NAME,
+    DEVPKEY_Device_CompatibleIds, DEVPKEY_Device_ContainerId, DEVPKEY_Device_HardwareIds,
+    DEVPKEY_Device_InstanceId, DEVPKEY_Device_Manufacturer, DEVPKEY_NAME,
 };
 use windows_sys::Win32::Foundation::{BOOLEAN, HANDLE};
 use windows_sys::Win32::Storage::EnhancedStorage::{
@@ -23,7 +24,7 @@ use windows_sys::Win32::Storage::EnhancedStorage::{
 fn read_string(
     func: unsafe extern "system" fn(HANDLE, *mut c_void, u32) -> BOOLEAN,
     handle: &Handle,
-) -> WcharString {
+) -> HidString {
     // Return empty string on failure to match the c implementation
     let mut string = [0u16; 256];
     if unsafe {
@@ -35,12 +36,12 @@ fn read_string(
     } != 0
     {
         U16Str::from_slice_list(&string)
-            .map(WcharString::from)
+            .map(HidString::from)
             .next()
-            .unwrap_or_else(|| WcharString::String(String::new()))
+            .unwrap_or_else(|| HidString::String(String::new()))
     } else {
-        // WcharString::None
-        WcharString::String(String::new())
+        // HidString::None
+        HidString::String(String::new())
     }
 }
 
@@ -61,6 +62,9 @@ pub fn get_device_info(path: &U16Str, handle: &Handle) -> DeviceInfo {
         usage: caps.Usage,
         interface_number: -1,
         bus_type: BusType::Unknown,
+        location_id: None,
+        bluetooth_address: None,
+        group_id: None,
     };
 
     // If this fails just ignore it. The data might be incomplete but at least there is something
@@ -68,10 +72,34 @@ pub fn get_device_info(path: &U16Str, handle: &Handle) -> DeviceInfo {
     dev
 }
 
+/// Read the vendor and product ID of a device from its instance ID, without opening it.
+///
+/// Returns `None` if the instance ID doesn't contain them in the `VID_xxxx&PID_xxxx` form of USB
+/// devices, e.g. for Bluetooth devices.
+pub fn get_vid_pid_from_instance_id(interface_path: &U16Str) -> Option<(u16, u16)> {
+    let mut device_id: U16String =
+        Interface::get_property(interface_path, DEVPKEY_Device_InstanceId).ok()?;
+    device_id.make_uppercase_ascii();
+    parse_vid_pid(&device_id)
+}
+
+fn parse_vid_pid(device_id: &U16Str) -> Option<(u16, u16)> {
+    let vid = extract_int_token_value(device_id, "VID_")?;
+    let pid = extract_int_token_value(device_id, "PID_")?;
+    Some((u16::try_from(vid).ok()?, u16::try_from(pid).ok()?))
+}
+
 fn get_internal_info(interface_path: &U16Str, dev: &mut DeviceInfo) -> WinResult<()> {
     let device_id: U16String = Interface::get_property(interface_path, DEVPKEY_Device_InstanceId)?;
 
-    let dev_node = DevNode::from_device_id(&device_id)?.parent()?;
+    let dev_node = DevNode::from_device_id(&device_id)?;
+
+    // All interfaces of a composite device share the container ID
+    if let Ok(container_id) = dev_node.get_property::<GUID>(DEVPKEY_Device_ContainerId) {
+        dev.group_id = Some(GroupId::new(format_guid(&container_id)));
+    }
+
+    let dev_node = dev_node.parent()?;
 
     let compatible_ids: U16StringList = dev_node.get_property(DEVPKEY_Device_CompatibleIds)?;
 
@@ -108,6 +136,17 @@ fn get_internal_info(interface_path: &U16Str, dev: &mut DeviceInfo) -> WinResult
     Ok(())
 }
 
+fn format_guid(guid: &GUID) -> String {
+    let mut s = format!("{:08x}-{:04x}-{:04x}-", guid.data1, guid.data2, guid.data3);
+    for (i, byte) in guid.data4.iter().enumerate() {
+        if i == 2 {
+            s.push('-');
+        }
+        s.push_str(&format!("{:02x}", byte));
+    }
+    s
+}
+
 fn get_usb_info(dev: &mut DeviceInfo, mut dev_node: DevNode) -> WinResult<()> {
     let mut device_id: U16String = dev_node.get_property(DEVPKEY_Device_InstanceId)?;
 
@@ -194,11 +233,12 @@ fn get_ble_info(dev: &mut DeviceInfo, dev_node: DevNode) -> WinResult<()> {
         }
     }
 
-    if dev.serial_number().map_or(true, str::is_empty) {
-        if let Ok(serial_number) =
-            dev_node.get_property::<U16String>(PKEY_DeviceInterface_Bluetooth_DeviceAddress)
-        {
-            dev.serial_number = serial_number.into();
+    if let Ok(address) =
+        dev_node.get_property::<U16String>(PKEY_DeviceInterface_Bluetooth_DeviceAddress)
+    {
+        dev.bluetooth_address = parse_bluetooth_address(&address.to_string());
+        if dev.serial_number().map_or(true, str::is_empty) {
+            dev.serial_number = address.into();
         }
     }
 
@@ -225,3 +265,32 @@ fn extract_int_token_value(u16str: &U16Str, token: &str) -> Option<u32> {
         .map_while(|c| c.ok().and_then(|c| c.to_digit(16)))
         .reduce(|l, r| l * 16 + r)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(device_id: &str) -> Option<(u16, u16)> {
+        let device_id = std::ffi::CString::new(device_id).unwrap();
+        parse_vid_pid(&U16String::try_from(device_id.as_c_str()).unwrap())
+    }
+
+    #[test]
+    fn test_parse_vid_pid() {
+        assert_eq!(
+            parse(r"HID\VID_046D&PID_C52B&MI_02&COL01\8&1C6E1D7&0&0000"),
+            Some((0x046D, 0xC52B))
+        );
+        assert_eq!(
+            parse(r"HID\VID_045E&PID_028E&IG_00\3&D2D1B2F&0&00"),
+            Some((0x045E, 0x028E))
+        );
+        assert_eq!(
+            parse(
+                r"HID\{00001124-0000-1000-8000-00805F9B34FB}_VID&0002046D_PID&B010\9&2A7B5C3&0&0000"
+            ),
+            None
+        );
+        assert_eq!(parse(r"HID\VID_12345&PID_0001\1"), None);
+    }
+}
@@ -27,6 +27,9 @@ impl WinError {
 impl From<WinError> for HidError {
     fn from(value: WinError) -> Self {
         match value {
+            WinError::Win32(Win32Error::Generic(ERROR_DEVICE_NOT_CONNECTED)) => {
+                HidError::Disconnected
+            }
             WinError::Win32(Win32Error::Generic(err)) => HidError::IoError {
                 error: std::io::Error::from_raw_os_error(err as _),
             },
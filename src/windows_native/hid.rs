@@ -27,6 +27,10 @@ pub fn get_hid_attributes(handle: &Handle) -> HIDD_ATTRIBUTES {
     }
 }
 
+/// The parsed report descriptor of an open device.
+///
+/// The data is owned by this value and never modified, so it can be shared between threads like
+/// the device handle it was loaded from.
 #[repr(transparent)]
 pub struct PreparsedData(isize);
 
@@ -46,7 +50,6 @@ impl PreparsedData {
         Ok(Self(pp_data))
     }
 
-    #[allow(dead_code)]
     pub fn as_ptr(&self) -> *const c_void {
         self.0 as _
     }
@@ -0,0 +1,67 @@
+//! Device interface arrival and removal notifications from the configuration manager
+
+use std::ffi::c_void;
+use std::mem::{size_of, zeroed};
+use std::sync::Arc;
+
+use crate::hotplug::Signal;
+use crate::windows_native::error::{check_config, WinResult};
+use crate::windows_native::hid::get_interface_guid;
+use windows_sys::Win32::Devices::DeviceAndDriverInstallation::{
+    CM_Register_Notification, CM_Unregister_Notification, CM_NOTIFY_ACTION, CM_NOTIFY_EVENT_DATA,
+    CM_NOTIFY_FILTER, CM_NOTIFY_FILTER_TYPE_DEVICEINTERFACE, CR_SUCCESS, HCMNOTIFICATION,
+};
+use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+
+/// Signals a change whenever a HID interface arrives or is removed
+pub struct DeviceNotification {
+    handle: HCMNOTIFICATION,
+    // Referenced by the callback until the notification is unregistered
+    _signal: Arc<Signal>,
+}
+
+unsafe impl Send for DeviceNotification {}
+
+impl DeviceNotification {
+    pub fn register(signal: Arc<Signal>) -> WinResult<Self> {
+        let mut handle: HCMNOTIFICATION = unsafe { zeroed() };
+        let cr = unsafe {
+            let mut filter: CM_NOTIFY_FILTER = zeroed();
+            filter.cbSize = size_of::<CM_NOTIFY_FILTER>() as u32;
+            filter.FilterType = CM_NOTIFY_FILTER_TYPE_DEVICEINTERFACE;
+            filter.u.DeviceInterface.ClassGuid = get_interface_guid();
+            CM_Register_Notification(
+                &filter,
+                Arc::as_ptr(&signal) as *const c_void,
+                Some(notification_callback),
+                &mut handle,
+            )
+        };
+        check_config(cr, CR_SUCCESS)?;
+        Ok(Self {
+            handle,
+            _signal: signal,
+        })
+    }
+}
+
+impl Drop for DeviceNotification {
+    fn drop(&mut self) {
+        // Waits for running callbacks to finish, so the signal can't be used after this
+        unsafe {
+            CM_Unregister_Notification(self.handle);
+        }
+    }
+}
+
+unsafe extern "system" fn notification_callback(
+    _notify: HCMNOTIFICATION,
+    context: *const c_void,
+    _action: CM_NOTIFY_ACTION,
+    _event_data: *const CM_NOTIFY_EVENT_DATA,
+    _event_data_size: u32,
+) -> u32 {
+    let signal = &*(context as *const Signal);
+    signal.notify_changed();
+    ERROR_SUCCESS
+}
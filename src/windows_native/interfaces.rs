@@ -61,13 +61,13 @@ impl Interface {
         Ok(property)
     }
 
-    fn get_interface_list_length(interface: GUID) -> WinResult<usize> {
+    fn get_interface_list_length(interface: GUID, device_id: Option<&U16Str>) -> WinResult<usize> {
         let mut len = 0;
         let cr = unsafe {
             CM_Get_Device_Interface_List_SizeW(
                 &mut len,
                 &interface,
-                null(),
+                device_id.map_or(null(), U16Str::as_ptr),
                 CM_GET_DEVICE_INTERFACE_LIST_PRESENT,
             )
         };
@@ -76,15 +76,24 @@ impl Interface {
     }
 
     pub fn get_interface_list() -> WinResult<U16StringList> {
-        let interface_class_guid = get_interface_guid();
+        Self::get_device_interface_list(get_interface_guid(), None)
+    }
 
+    /// List the present interfaces of a class, only those of one device if `device_id` is given.
+    pub fn get_device_interface_list(
+        interface_class_guid: GUID,
+        device_id: Option<&U16Str>,
+    ) -> WinResult<U16StringList> {
         let mut device_interface_list = Vec::new();
         loop {
-            device_interface_list.resize(Self::get_interface_list_length(interface_class_guid)?, 0);
+            device_interface_list.resize(
+                Self::get_interface_list_length(interface_class_guid, device_id)?,
+                0,
+            );
             let cr = unsafe {
                 CM_Get_Device_Interface_ListW(
                     &interface_class_guid,
-                    null(),
+                    device_id.map_or(null(), U16Str::as_ptr),
                     device_interface_list.as_mut_ptr(),
                     device_interface_list.len() as u32,
                     CM_GET_DEVICE_INTERFACE_LIST_PRESENT,
@@ -13,34 +13,46 @@ mod dev_node;
 mod device_info;
 mod error;
 mod hid;
+mod hotplug;
 mod interfaces;
 mod string;
 mod types;
+mod usb;
 mod utils;
 
-use std::cell::{Cell, RefCell};
+use std::mem::MaybeUninit;
 use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
 use std::{
     ffi::CStr,
     fmt::{self, Debug},
 };
 
+use crate::report_descriptor::check_descriptor_size;
 use crate::windows_native::dev_node::DevNode;
-use crate::windows_native::device_info::get_device_info;
+use crate::windows_native::device_info::{get_device_info, get_vid_pid_from_instance_id};
 use crate::windows_native::error::{check_boolean, Win32Error, WinError, WinResult};
 use crate::windows_native::hid::{get_hid_attributes, PreparsedData};
 use crate::windows_native::interfaces::Interface;
 use crate::windows_native::string::{U16Str, U16String};
 use crate::windows_native::types::{Handle, Overlapped};
-use crate::{DeviceInfo, HidDeviceBackendBase, HidDeviceBackendWindows, HidError, HidResult};
+use crate::{
+    language_ids, read_string_growing, AccessMode, BusType, DeviceFilter, DeviceInfo,
+    HidDeviceBackendBase, HidDeviceBackendWindows, HidError, HidResult,
+};
 use windows_sys::core::GUID;
 use windows_sys::Win32::Devices::HumanInterfaceDevice::{
-    HidD_GetIndexedString, HidD_SetFeature, HidD_SetNumInputBuffers, HidD_SetOutputReport,
+    HidD_FlushQueue, HidD_GetIndexedString, HidD_GetNumInputBuffers, HidD_SetFeature,
+    HidD_SetNumInputBuffers, HidD_SetOutputReport,
 };
 use windows_sys::Win32::Devices::Properties::{
     DEVPKEY_Device_ContainerId, DEVPKEY_Device_InstanceId,
 };
-use windows_sys::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE, TRUE};
+use windows_sys::Win32::Foundation::{
+    ERROR_NOT_FOUND, GENERIC_READ, GENERIC_WRITE, INVALID_HANDLE_VALUE, TRUE,
+};
 use windows_sys::Win32::Storage::FileSystem::{
     CreateFileW, ReadFile, WriteFile, FILE_FLAG_OVERLAPPED, FILE_SHARE_READ, FILE_SHARE_WRITE,
     OPEN_EXISTING,
@@ -48,12 +60,24 @@ use windows_sys::Win32::Storage::FileSystem::{
 use windows_sys::Win32::System::Threading::ResetEvent;
 use windows_sys::Win32::System::IO::{CancelIoEx, DeviceIoControl};
 
-const STRING_BUF_LEN: usize = 128;
+pub use hotplug::DeviceNotification;
+
+/// How long `write` waits for a report to be sent
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Number of input reports buffered by the driver, unless changed with
+/// `set_input_report_buffer_count`
+const DEFAULT_INPUT_REPORT_BUFFER_COUNT: u32 = 64;
 
 pub struct HidApiBackend;
 impl HidApiBackend {
     pub fn get_hid_device_info_vector(vid: u16, pid: u16) -> HidResult<Vec<DeviceInfo>> {
-        Ok(enumerate_devices(vid, pid)?)
+        Ok(enumerate_devices(&DeviceFilter::from_vid_pid(vid, pid))?)
+    }
+
+    pub fn get_hid_device_info_vector_filtered(
+        filter: &DeviceFilter,
+    ) -> HidResult<Vec<DeviceInfo>> {
+        Ok(enumerate_devices(filter)?)
     }
 
     pub fn open(vid: u16, pid: u16) -> HidResult<HidDevice> {
@@ -67,17 +91,37 @@ impl HidApiBackend {
     pub fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
         open_path(device_path)
     }
+
+    pub fn open_path_with(device_path: &CStr, access: AccessMode) -> HidResult<HidDevice> {
+        open_path_with_info(device_path, access, None)
+    }
+
+    pub fn is_present(device_path: &CStr) -> bool {
+        let Ok(device_path) = U16String::try_from(device_path) else {
+            return false;
+        };
+        Interface::get_interface_list().is_ok_and(|interfaces| {
+            interfaces
+                .iter()
+                .any(|interface| interface.as_slice() == device_path.as_slice())
+        })
+    }
 }
 
 /// Object for accessing HID device
 pub struct HidDevice {
     device_handle: Handle,
-    device_info: DeviceInfo,
-    read_pending: Cell<bool>,
-    blocking: Cell<bool>,
-    read_state: RefCell<AsyncState>,
-    write_state: RefCell<AsyncState>,
-    feature_state: RefCell<AsyncState>,
+    device_info: Mutex<DeviceInfo>,
+    // Never changes for an open handle, so it is loaded once for the descriptor and caps
+    preparsed_data: PreparsedData,
+    read_pending: AtomicBool,
+    blocking: AtomicBool,
+    closed: AtomicBool,
+    // Reads, writes and output reports, and feature reports each have their own overlapped
+    // state, so a read in one thread does not block a write in another.
+    read_state: Mutex<AsyncState>,
+    write_state: Mutex<AsyncState>,
+    feature_state: Mutex<AsyncState>,
 }
 
 struct AsyncState {
@@ -86,29 +130,29 @@ struct AsyncState {
 }
 
 impl AsyncState {
-    fn new(report_size: usize) -> Self {
-        Self {
-            overlapped: Default::default(),
+    fn new(report_size: usize) -> WinResult<Self> {
+        Ok(Self {
+            overlapped: Box::new(Overlapped::new()?),
             buffer: vec![0u8; report_size],
-        }
+        })
     }
 
     fn clear_buffer(&mut self) {
         self.buffer.fill(0)
     }
 
-    fn fill_buffer(&mut self, data: &[u8]) {
+    fn fill_buffer(&mut self, data: &[u8]) -> HidResult<()> {
         // Make sure the right number of bytes are passed to WriteFile. Windows
         // expects the number of bytes which are in the _longest_ report (plus
         // one for the report number) bytes even if the data is a report
         // which is shorter than that. Windows gives us this value in
         // caps.OutputReportByteLength. If a user passes in fewer bytes than this,
-        // use cached temporary buffer which is the proper size.
-        let data_size = data.len().min(self.buffer.len());
-        self.buffer[..data_size].copy_from_slice(&data[..data_size]);
-        if data_size < self.buffer.len() {
-            self.buffer[data_size..].fill(0);
-        }
+        // use cached temporary buffer which is the proper size. Windows rejects
+        // longer buffers, so these fail instead of being truncated.
+        check_fits_report(data, self.buffer.len())?;
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.buffer[data.len()..].fill(0);
+        Ok(())
     }
 
     fn buffer_len(&self) -> usize {
@@ -128,9 +172,13 @@ impl Debug for HidDevice {
 
 impl HidDeviceBackendBase for HidDevice {
     fn write(&self, data: &[u8]) -> HidResult<usize> {
+        self.write_timeout(data, Some(DEFAULT_WRITE_TIMEOUT))
+    }
+
+    fn write_timeout(&self, data: &[u8], timeout: Option<Duration>) -> HidResult<usize> {
         ensure!(!data.is_empty(), Err(HidError::InvalidZeroSizeData));
-        let mut state = self.write_state.borrow_mut();
-        state.fill_buffer(data);
+        let mut state = self.write_state.lock().unwrap();
+        state.fill_buffer(data)?;
 
         let res = unsafe {
             WriteFile(
@@ -145,26 +193,37 @@ impl HidDeviceBackendBase for HidDevice {
         if res != TRUE {
             let err = Win32Error::last();
             ensure!(err == Win32Error::IoPending, Err(err.into()));
-            Ok(state
-                .overlapped
-                .get_result(&self.device_handle, Some(1000))?)
+            self.wait_or_cancel(&mut state.overlapped, timeout)
         } else {
             Ok(0)
         }
     }
 
     fn read(&self, buf: &mut [u8]) -> HidResult<usize> {
-        self.read_timeout(buf, if self.blocking.get() { -1 } else { 0 })
+        self.read_timeout(
+            buf,
+            if self.blocking.load(Ordering::Relaxed) {
+                -1
+            } else {
+                0
+            },
+        )
     }
 
     fn read_timeout(&self, buf: &mut [u8], timeout: i32) -> HidResult<usize> {
+        // SAFETY: Only the report read from the device is written into the buffer
+        let buf = unsafe { &mut *(buf as *mut [u8] as *mut [MaybeUninit<u8>]) };
+        self.read_timeout_uninit(buf, timeout)
+    }
+
+    fn read_timeout_uninit(&self, buf: &mut [MaybeUninit<u8>], timeout: i32) -> HidResult<usize> {
         ensure!(!buf.is_empty(), Err(HidError::InvalidZeroSizeData));
         let mut bytes_read = 0;
         let mut io_runnig = false;
-        let mut state = self.read_state.borrow_mut();
+        let mut state = self.read_state.lock().unwrap();
 
-        if !self.read_pending.get() {
-            self.read_pending.set(true);
+        if !self.read_pending.load(Ordering::Relaxed) {
+            self.read_pending.store(true, Ordering::Relaxed);
             state.clear_buffer();
             let res = unsafe {
                 ResetEvent(state.overlapped.event_handle());
@@ -180,7 +239,7 @@ impl HidDeviceBackendBase for HidDevice {
                 let err = Win32Error::last();
                 if err != Win32Error::IoPending {
                     unsafe { CancelIoEx(self.device_handle.as_raw(), state.overlapped.as_raw()) };
-                    self.read_pending.set(false);
+                    self.read_pending.store(false, Ordering::Relaxed);
                     return Err(err.into());
                 }
                 io_runnig = true;
@@ -198,12 +257,12 @@ impl HidDeviceBackendBase for HidDevice {
                 //There was no data this time. Return zero bytes available, but leave the Overlapped I/O running.
                 Err(WinError::WaitTimedOut) => return Ok(0),
                 Err(err) => {
-                    self.read_pending.set(false);
+                    self.read_pending.store(false, Ordering::Relaxed);
                     return Err(err.into());
                 }
             };
         }
-        self.read_pending.set(false);
+        self.read_pending.store(false, Ordering::Relaxed);
 
         let mut copy_len = 0;
         if bytes_read > 0 {
@@ -211,13 +270,15 @@ impl HidDeviceBackendBase for HidDevice {
             // number (0x0) on the beginning of the report anyway. To make this
             // work like the other platforms, and to make it work more like the
             // HID spec, we'll skip over this byte.
-            if state.buffer[0] == 0x0 {
+            let start = if state.buffer[0] == 0x0 {
                 bytes_read -= 1;
-                copy_len = usize::min(bytes_read as usize, buf.len());
-                buf[..copy_len].copy_from_slice(&state.buffer[1..(1 + copy_len)]);
+                1
             } else {
-                copy_len = usize::min(bytes_read as usize, buf.len());
-                buf[..copy_len].copy_from_slice(&state.buffer[0..copy_len]);
+                0
+            };
+            copy_len = usize::min(bytes_read as usize, buf.len());
+            for (dst, &src) in buf.iter_mut().zip(&state.buffer[start..(start + copy_len)]) {
+                *dst = MaybeUninit::new(src);
             }
         }
         Ok(copy_len)
@@ -225,8 +286,8 @@ impl HidDeviceBackendBase for HidDevice {
 
     fn send_feature_report(&self, data: &[u8]) -> HidResult<()> {
         ensure!(!data.is_empty(), Err(HidError::InvalidZeroSizeData));
-        let mut state = self.feature_state.borrow_mut();
-        state.fill_buffer(data);
+        let mut state = self.feature_state.lock().unwrap();
+        state.fill_buffer(data)?;
 
         check_boolean(unsafe {
             HidD_SetFeature(
@@ -239,14 +300,22 @@ impl HidDeviceBackendBase for HidDevice {
         Ok(())
     }
 
+    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+        self.get_feature_report_timeout(buf, None)
+    }
+
     /// Set the first byte of `buf` to the 'Report ID' of the report to be read.
     /// Upon return, the first byte will still contain the Report ID, and the
     /// report data will start in `buf[1]`.
-    fn get_feature_report(&self, buf: &mut [u8]) -> HidResult<usize> {
+    fn get_feature_report_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> HidResult<usize> {
         #[allow(clippy::identity_op, clippy::double_parens)]
         const IOCTL_HID_GET_FEATURE: u32 = ((0x0000000b) << 16) | ((0) << 14) | ((100) << 2) | (2);
         ensure!(!buf.is_empty(), Err(HidError::InvalidZeroSizeData));
-        let mut state = self.feature_state.borrow_mut();
+        let mut state = self.feature_state.lock().unwrap();
         let mut bytes_returned = 0;
 
         let res = unsafe {
@@ -267,7 +336,7 @@ impl HidDeviceBackendBase for HidDevice {
             ensure!(err == Win32Error::IoPending, Err(err.into()))
         }
 
-        bytes_returned = state.overlapped.get_result(&self.device_handle, None)? as u32;
+        bytes_returned = self.wait_or_cancel(&mut state.overlapped, timeout)? as u32;
 
         if buf[0] == 0x0 {
             bytes_returned += 1;
@@ -278,8 +347,10 @@ impl HidDeviceBackendBase for HidDevice {
 
     fn send_output_report(&self, data: &[u8]) -> HidResult<()> {
         ensure!(!data.is_empty(), Err(HidError::InvalidZeroSizeData));
-        let mut state = self.feature_state.borrow_mut();
-        state.fill_buffer(data);
+        // Output reports have to be padded to the output report length like writes, the
+        // difference is that HidD_SetOutputReport sends them over the control endpoint
+        let mut state = self.write_state.lock().unwrap();
+        state.fill_buffer(data)?;
 
         check_boolean(unsafe {
             HidD_SetOutputReport(
@@ -293,52 +364,103 @@ impl HidDeviceBackendBase for HidDevice {
     }
 
     fn set_blocking_mode(&self, blocking: bool) -> HidResult<()> {
-        self.blocking.set(blocking);
+        self.blocking.store(blocking, Ordering::Relaxed);
         Ok(())
     }
 
     fn get_manufacturer_string(&self) -> HidResult<Option<String>> {
-        Ok(self.device_info.manufacturer_string().map(String::from))
+        Ok(self
+            .device_info
+            .lock()
+            .unwrap()
+            .manufacturer_string()
+            .map(String::from))
     }
 
     fn get_product_string(&self) -> HidResult<Option<String>> {
-        Ok(self.device_info.product_string().map(String::from))
+        Ok(self
+            .device_info
+            .lock()
+            .unwrap()
+            .product_string()
+            .map(String::from))
     }
 
     fn get_serial_number_string(&self) -> HidResult<Option<String>> {
-        Ok(self.device_info.serial_number().map(String::from))
+        Ok(self
+            .device_info
+            .lock()
+            .unwrap()
+            .serial_number()
+            .map(String::from))
     }
 
     fn get_indexed_string(&self, index: i32) -> HidResult<Option<String>> {
-        let mut buf = [0u16; STRING_BUF_LEN];
-        let res = unsafe {
-            HidD_GetIndexedString(
-                self.device_handle.as_raw(),
-                index as u32,
-                buf.as_mut_ptr() as _,
-                STRING_BUF_LEN as u32,
-            )
-        };
-        check_boolean(res)?;
-        Ok(buf.split(|c| *c == 0).map(String::from_utf16_lossy).next())
+        let string = self.get_indexed_string_raw(index)?;
+        Ok(Some(String::from_utf16_lossy(&string)))
+    }
+
+    fn supported_languages(&self) -> HidResult<Vec<u16>> {
+        // The descriptor at index 0 holds the language IDs, which are returned like characters
+        language_ids(&self.get_indexed_string_raw(0)?)
     }
 
     fn get_device_info(&self) -> HidResult<DeviceInfo> {
-        Ok(self.device_info.clone())
+        Ok(self.device_info.lock().unwrap().clone())
+    }
+
+    fn refresh_strings(&self) -> HidResult<()> {
+        let mut device_info = self.device_info.lock().unwrap();
+        let path =
+            U16String::try_from(device_info.path()).expect("device path is not valid unicode");
+        *device_info = get_device_info(&path, &self.device_handle);
+        Ok(())
     }
 
     fn get_report_descriptor(&self, buf: &mut [u8]) -> HidResult<usize> {
-        let desc = descriptor::get_descriptor(&PreparsedData::load(&self.device_handle)?)?;
+        let desc = descriptor::get_descriptor(&self.preparsed_data)?;
+        check_descriptor_size(desc.len())?;
         let size = buf.len().min(desc.len());
         buf[..size].copy_from_slice(&desc[..size]);
         Ok(size)
     }
+
+    fn input_report_len(&self) -> HidResult<Option<usize>> {
+        Ok(Some(self.read_state.lock().unwrap().buffer_len()))
+    }
+
+    fn output_report_len(&self) -> HidResult<Option<usize>> {
+        Ok(Some(self.write_state.lock().unwrap().buffer_len()))
+    }
+
+    fn feature_report_len(&self) -> HidResult<Option<usize>> {
+        Ok(Some(self.feature_state.lock().unwrap().buffer_len()))
+    }
+
+    fn clear_input_buffer(&self) -> HidResult<()> {
+        let mut state = self.read_state.lock().unwrap();
+        if self.read_pending.swap(false, Ordering::Relaxed) {
+            // A report the pending read completed with in the meantime is dropped as well.
+            // The next read starts a new one.
+            unsafe {
+                if CancelIoEx(self.device_handle.as_raw(), state.overlapped.as_raw()) > 0 {
+                    _ = state.overlapped.get_result(&self.device_handle, None);
+                }
+            }
+        }
+        check_boolean(unsafe { HidD_FlushQueue(self.device_handle.as_raw()) })?;
+        Ok(())
+    }
+
+    fn close(&self) -> HidResult<()> {
+        Ok(self.cancel_pending_io()?)
+    }
 }
 
 impl HidDeviceBackendWindows for HidDevice {
     fn get_container_id(&self) -> HidResult<GUID> {
-        let path =
-            U16String::try_from(self.device_info.path()).expect("device path is not valid unicode");
+        let path = U16String::try_from(self.device_info.lock().unwrap().path())
+            .expect("device path is not valid unicode");
 
         let device_id: U16String = Interface::get_property(&path, DEVPKEY_Device_InstanceId)?;
 
@@ -346,46 +468,162 @@ impl HidDeviceBackendWindows for HidDevice {
         let guid = dev_node.get_property(DEVPKEY_Device_ContainerId)?;
         Ok(guid)
     }
+
+    fn set_input_report_buffer_count(&self, count: u32) -> HidResult<()> {
+        check_boolean(unsafe { HidD_SetNumInputBuffers(self.device_handle.as_raw(), count) })?;
+        Ok(())
+    }
+
+    fn get_input_report_buffer_count(&self) -> HidResult<u32> {
+        let mut count = 0;
+        check_boolean(unsafe { HidD_GetNumInputBuffers(self.device_handle.as_raw(), &mut count) })?;
+        Ok(count)
+    }
+
+    fn preparsed_data(&self) -> HidResult<Vec<u8>> {
+        Ok(descriptor::copy_preparsed_data(&self.preparsed_data)?)
+    }
+
+    fn polling_interval(&self) -> HidResult<Option<Duration>> {
+        let info = self.device_info.lock().unwrap().clone();
+        ensure!(info.bus_type == BusType::Usb, Ok(None));
+        let path = U16String::try_from(info.path()).expect("device path is not valid unicode");
+
+        let device_id: U16String = Interface::get_property(&path, DEVPKEY_Device_InstanceId)?;
+        // The HID device is a child of the USB device, or of its interface for composite devices
+        let mut usb_device = DevNode::from_device_id(&device_id)?.parent()?;
+        if info.interface_number != -1 {
+            usb_device = usb_device.parent()?;
+        }
+        let interface_number = info.interface_number.max(0) as u8;
+        Ok(usb::get_polling_interval(usb_device, interface_number)?)
+    }
 }
 
-impl Drop for HidDevice {
-    fn drop(&mut self) {
-        unsafe {
-            for state in [
-                &mut self.read_state,
-                &mut self.write_state,
-                &mut self.feature_state,
-            ] {
-                let mut state = state.borrow_mut();
-                if CancelIoEx(self.device_handle.as_raw(), state.overlapped.as_raw()) > 0 {
-                    _ = state.overlapped.get_result(&self.device_handle, None);
+/// Make sure `data` is sent completely by a function that doesn't report how many bytes it sent.
+///
+/// Those always send the whole buffer, which is padded to the report length, so only data longer
+/// than the report would be cut off. Such data is rejected without sending anything.
+fn check_fits_report(data: &[u8], report_len: usize) -> HidResult<()> {
+    ensure!(
+        data.len() <= report_len,
+        Err(HidError::IncompleteSendError {
+            sent: 0,
+            all: data.len(),
+        })
+    );
+    Ok(())
+}
+
+/// Convert a timeout to the milliseconds to wait for overlapped IO, where `None` means forever
+fn overlapped_timeout(timeout: Option<Duration>) -> Option<u32> {
+    u32::try_from(crate::timeout_millis(timeout)).ok()
+}
+
+impl HidDevice {
+    /// Wait for an overlapped operation to complete, cancelling it if `timeout` expires.
+    fn wait_or_cancel(
+        &self,
+        overlapped: &mut Overlapped,
+        timeout: Option<Duration>,
+    ) -> HidResult<usize> {
+        match overlapped.get_result(&self.device_handle, overlapped_timeout(timeout)) {
+            Ok(transferred) => Ok(transferred),
+            Err(WinError::WaitTimedOut) => {
+                // The buffer must not be written to after returning, so the operation has to go
+                unsafe {
+                    if CancelIoEx(self.device_handle.as_raw(), overlapped.as_raw()) > 0 {
+                        _ = overlapped.get_result(&self.device_handle, None);
+                    }
                 }
+                Err(HidError::IoError {
+                    error: std::io::ErrorKind::TimedOut.into(),
+                })
             }
+            Err(err) => Err(err.into()),
         }
     }
+
+    /// Cancel the IO that is still pending, once, for either `close` or `Drop`.
+    fn cancel_pending_io(&self) -> WinResult<()> {
+        if self.closed.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        for state in [&self.read_state, &self.write_state, &self.feature_state] {
+            let mut state = state.lock().unwrap();
+            if unsafe { CancelIoEx(self.device_handle.as_raw(), state.overlapped.as_raw()) } > 0 {
+                _ = state.overlapped.get_result(&self.device_handle, None);
+            } else {
+                // ERROR_NOT_FOUND just means there was nothing to cancel
+                let err = Win32Error::last();
+                if err != Win32Error::Generic(ERROR_NOT_FOUND) && result.is_ok() {
+                    result = Err(err.into());
+                }
+            }
+        }
+        result
+    }
+
+    /// Read the string descriptor with `index` as it is
+    fn get_indexed_string_raw(&self, index: i32) -> HidResult<Vec<u16>> {
+        read_string_growing(|buf: &mut [u16]| {
+            // The length of the buffer is given in bytes
+            let res = unsafe {
+                HidD_GetIndexedString(
+                    self.device_handle.as_raw(),
+                    index as u32,
+                    buf.as_mut_ptr() as _,
+                    std::mem::size_of_val(buf) as u32,
+                )
+            };
+            Ok(check_boolean(res)?)
+        })
+    }
 }
 
-fn enumerate_devices(vendor_id: u16, product_id: u16) -> WinResult<Vec<DeviceInfo>> {
+impl Drop for HidDevice {
+    fn drop(&mut self) {
+        _ = self.cancel_pending_io();
+    }
+}
+
+fn enumerate_devices(filter: &DeviceFilter) -> WinResult<Vec<DeviceInfo>> {
+    let (vendor_id, product_id) = filter.vid_pid();
     Ok(Interface::get_interface_list()?
         .iter()
         .filter_map(|device_interface| {
-            let device_handle = open_device(device_interface, false).ok()?;
+            let matches = |vid, pid| {
+                (vendor_id == 0 || vid == vendor_id) && (product_id == 0 || pid == product_id)
+            };
+            // Opening a device is slow, skip the ones that can be ruled out without it
+            if vendor_id != 0 || product_id != 0 {
+                if let Some((vid, pid)) = get_vid_pid_from_instance_id(device_interface) {
+                    ensure!(matches(vid, pid), None);
+                }
+            }
+            let device_handle = open_device(device_interface, 0).ok()?;
             let attrib = get_hid_attributes(&device_handle);
-            ((vendor_id == 0 || attrib.VendorID == vendor_id)
-                && (product_id == 0 || attrib.ProductID == product_id))
-                .then(|| get_device_info(device_interface, &device_handle))
+            ensure!(matches(attrib.VendorID, attrib.ProductID), None);
+            // Reading the strings and the bus type is slow too, the usage only needs the caps
+            if filter.has_usage() {
+                let caps = PreparsedData::load(&device_handle)
+                    .and_then(|data| data.get_caps())
+                    .ok()?;
+                ensure!(filter.matches_usage(caps.UsagePage, caps.Usage), None);
+            }
+            let info = get_device_info(device_interface, &device_handle);
+            filter.matches(&info).then_some(info)
         })
         .collect())
 }
 
-fn open_device(path: &U16Str, open_rw: bool) -> WinResult<Handle> {
+fn open_device(path: &U16Str, desired_access: u32) -> WinResult<Handle> {
     let handle = unsafe {
         CreateFileW(
             path.as_ptr(),
-            match open_rw {
-                true => GENERIC_WRITE | GENERIC_READ,
-                false => 0,
-            },
+            desired_access,
             FILE_SHARE_READ | FILE_SHARE_WRITE,
             null(),
             OPEN_EXISTING,
@@ -400,36 +638,118 @@ fn open_device(path: &U16Str, open_rw: bool) -> WinResult<Handle> {
     Ok(Handle::from_raw(handle))
 }
 
+/// The `CreateFileW` access rights for an [`AccessMode`]
+fn desired_access(access: AccessMode) -> u32 {
+    match access {
+        AccessMode::ReadWrite => GENERIC_READ | GENERIC_WRITE,
+        AccessMode::ReadOnly => GENERIC_READ,
+        AccessMode::WriteOnly => GENERIC_WRITE,
+    }
+}
+
 fn open(vid: u16, pid: u16, sn: Option<&str>) -> HidResult<HidDevice> {
-    let dev = enumerate_devices(vid, pid)?
+    let dev = enumerate_devices(&DeviceFilter::from_vid_pid(vid, pid))?
         .into_iter()
         .filter(|dev| dev.vendor_id == vid && dev.product_id == pid)
         .find(|dev| sn.map_or(true, |sn| dev.serial_number().is_some_and(|n| sn == n)))
         .ok_or(HidError::HidApiErrorEmpty)?;
-    open_path(dev.path())
+    // The info read during the enumeration is still current, don't read it again
+    let path = dev.path.clone();
+    open_path_with_info(&path, AccessMode::ReadWrite, Some(dev))
 }
 
 fn open_path(device_path: &CStr) -> HidResult<HidDevice> {
+    open_path_with_info(device_path, AccessMode::ReadWrite, None)
+}
+
+fn open_path_with_info(
+    device_path: &CStr,
+    access: AccessMode,
+    device_info: Option<DeviceInfo>,
+) -> HidResult<HidDevice> {
     let device_path = U16String::try_from(device_path).unwrap();
-    let handle = open_device(&device_path, true)
+    let handle = open_device(&device_path, desired_access(access)).or_else(|err| {
         // System devices, such as keyboards and mice, cannot be opened in
         // read-write mode, because the system takes exclusive control over
         // them.  This is to prevent keyloggers.  However, feature reports
         // can still be sent and received.  Retry opening the device, but
         // without read/write access.
-        .or_else(|_| open_device(&device_path, false))?;
-    check_boolean(unsafe { HidD_SetNumInputBuffers(handle.as_raw(), 64) })?;
-    let caps = PreparsedData::load(&handle)?.get_caps()?;
-    let device_info = get_device_info(&device_path, &handle);
+        match access {
+            AccessMode::ReadWrite => open_device(&device_path, 0),
+            _ => Err(err),
+        }
+    })?;
+    check_boolean(unsafe {
+        HidD_SetNumInputBuffers(handle.as_raw(), DEFAULT_INPUT_REPORT_BUFFER_COUNT)
+    })?;
+    let preparsed_data = PreparsedData::load(&handle)?;
+    let caps = preparsed_data.get_caps()?;
+    let device_info = device_info.unwrap_or_else(|| get_device_info(&device_path, &handle));
     let dev = HidDevice {
         device_handle: handle,
-        blocking: Cell::new(true),
-        read_pending: Cell::new(false),
-        read_state: RefCell::new(AsyncState::new(caps.InputReportByteLength as usize)),
-        write_state: RefCell::new(AsyncState::new(caps.OutputReportByteLength as usize)),
-        feature_state: RefCell::new(AsyncState::new(caps.FeatureReportByteLength as usize)),
-        device_info,
+        preparsed_data,
+        blocking: AtomicBool::new(true),
+        read_pending: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        read_state: Mutex::new(AsyncState::new(caps.InputReportByteLength as usize)?),
+        write_state: Mutex::new(AsyncState::new(caps.OutputReportByteLength as usize)?),
+        feature_state: Mutex::new(AsyncState::new(caps.FeatureReportByteLength as usize)?),
+        device_info: Mutex::new(device_info),
     };
 
     Ok(dev)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_fits_report() {
+        assert!(check_fits_report(&[0x01, 0x02], 3).is_ok());
+        assert!(check_fits_report(&[0x01, 0x02, 0x03], 3).is_ok());
+        assert!(matches!(
+            check_fits_report(&[0x01, 0x02, 0x03, 0x04], 3),
+            Err(HidError::IncompleteSendError { sent: 0, all: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_fill_buffer() {
+        let mut state = AsyncState::new(4).unwrap();
+        state.fill_buffer(&[0x01, 0x02]).unwrap();
+        assert_eq!(state.buffer, [0x01, 0x02, 0x00, 0x00]);
+        // A longer report is rejected instead of being truncated, keeping the buffer as it is
+        assert!(matches!(
+            state.fill_buffer(&[0x01, 0x02, 0x03, 0x04, 0x05]),
+            Err(HidError::IncompleteSendError { sent: 0, all: 5 })
+        ));
+        assert_eq!(state.buffer, [0x01, 0x02, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_desired_access() {
+        assert_eq!(
+            desired_access(AccessMode::ReadWrite),
+            GENERIC_READ | GENERIC_WRITE
+        );
+        assert_eq!(desired_access(AccessMode::ReadOnly), GENERIC_READ);
+        assert_eq!(desired_access(AccessMode::WriteOnly), GENERIC_WRITE);
+    }
+
+    #[test]
+    fn test_overlapped_timeout() {
+        assert_eq!(overlapped_timeout(None), None);
+        assert_eq!(overlapped_timeout(Some(Duration::ZERO)), Some(0));
+        assert_eq!(overlapped_timeout(Some(Duration::from_micros(10))), Some(1));
+        assert_eq!(
+            overlapped_timeout(Some(Duration::from_micros(1500))),
+            Some(2)
+        );
+        assert_eq!(overlapped_timeout(Some(Duration::from_secs(2))), Some(2000));
+        assert_eq!(
+            overlapped_timeout(Some(Duration::MAX)),
+            Some(i32::MAX as u32)
+        );
+    }
+}
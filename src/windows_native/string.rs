@@ -1,5 +1,5 @@
 use crate::windows_native::types::DeviceProperty;
-use crate::WcharString;
+use crate::HidString;
 use std::ffi::CStr;
 use std::fmt::{Debug, Formatter};
 use std::iter::once;
@@ -107,11 +107,11 @@ impl ToString for U16Str {
     }
 }
 
-impl From<&U16Str> for WcharString {
+impl From<&U16Str> for HidString {
     fn from(value: &U16Str) -> Self {
         String::from_utf16(value.as_slice())
-            .map(WcharString::String)
-            .unwrap_or_else(|_| WcharString::Raw(value.as_slice().to_vec()))
+            .map(HidString::String)
+            .unwrap_or_else(|_| HidString::Raw(value.as_slice().to_vec()))
     }
 }
 
@@ -156,7 +156,7 @@ impl TryFrom<&CStr> for U16String {
     }
 }
 
-impl From<U16String> for WcharString {
+impl From<U16String> for HidString {
     fn from(value: U16String) -> Self {
         (&*value).into()
     }
@@ -3,7 +3,9 @@ use crate::BusType;
 use std::mem::{size_of, zeroed};
 use std::ptr::null;
 use windows_sys::core::GUID;
-use windows_sys::Win32::Devices::Properties::{DEVPROPKEY, DEVPROPTYPE, DEVPROP_TYPE_GUID};
+use windows_sys::Win32::Devices::Properties::{
+    DEVPROPKEY, DEVPROPTYPE, DEVPROP_TYPE_GUID, DEVPROP_TYPE_UINT32,
+};
 use windows_sys::Win32::Foundation::{CloseHandle, FALSE, HANDLE, INVALID_HANDLE_VALUE, TRUE};
 use windows_sys::Win32::System::Threading::{CreateEventW, INFINITE};
 use windows_sys::Win32::System::IO::{GetOverlappedResultEx, OVERLAPPED};
@@ -30,6 +32,19 @@ unsafe impl DeviceProperty for GUID {
     }
 }
 
+unsafe impl DeviceProperty for u32 {
+    const TYPE: DEVPROPTYPE = DEVPROP_TYPE_UINT32;
+
+    fn create_sized(bytes: usize) -> Self {
+        assert_eq!(bytes, size_of::<u32>());
+        0
+    }
+
+    fn as_ptr_mut(&mut self) -> *mut u8 {
+        (self as *mut u32) as *mut u8
+    }
+}
+
 pub trait PropertyKey: Copy {
     fn as_ptr(&self) -> *const DEVPROPKEY;
 }
@@ -91,9 +106,23 @@ impl Drop for Handle {
     }
 }
 
+/// An `OVERLAPPED` structure with its own event, for waiting on asynchronous IO.
+///
+/// Creating the event can fail, e.g. when the process runs out of handles, so
+/// [`Overlapped::new`] is fallible instead of implementing `Default`. An overlapped
+/// structure without an event would make `get_result` wait on the file handle instead.
 pub struct Overlapped(OVERLAPPED);
 
 impl Overlapped {
+    pub fn new() -> WinResult<Self> {
+        let event = unsafe { CreateEventW(null(), FALSE, FALSE, null()) };
+        ensure!(event != 0, Err(WinError::last()));
+        Ok(Overlapped(OVERLAPPED {
+            hEvent: event,
+            ..unsafe { zeroed() }
+        }))
+    }
+
     pub fn event_handle(&self) -> HANDLE {
         self.0.hEvent
     }
@@ -119,25 +148,10 @@ impl Overlapped {
 
 unsafe impl Send for Overlapped {}
 
-impl Default for Overlapped {
-    fn default() -> Self {
-        Overlapped(unsafe {
-            OVERLAPPED {
-                //todo check if event is null
-                hEvent: CreateEventW(null(), FALSE, FALSE, null()),
-                ..zeroed()
-            }
-        })
-    }
-}
-
 impl Drop for Overlapped {
     fn drop(&mut self) {
-        if self.0.hEvent != INVALID_HANDLE_VALUE {
-            unsafe {
-                CloseHandle(self.0.hEvent);
-            }
+        unsafe {
+            CloseHandle(self.0.hEvent);
         }
-        self.0.hEvent = INVALID_HANDLE_VALUE;
     }
 }
@@ -0,0 +1,224 @@
+//! Reading the USB descriptors of a device through the hub it is connected to
+
+use crate::windows_native::dev_node::DevNode;
+use crate::windows_native::error::{WinError, WinResult};
+use crate::windows_native::interfaces::Interface;
+use crate::windows_native::string::{U16Str, U16String};
+use crate::windows_native::types::Handle;
+use std::mem::{size_of, zeroed};
+use std::ptr::{copy_nonoverlapping, null, null_mut};
+use std::time::Duration;
+use windows_sys::Win32::Devices::Properties::{DEVPKEY_Device_Address, DEVPKEY_Device_InstanceId};
+use windows_sys::Win32::Devices::Usb::{
+    UsbFullSpeed, UsbHighSpeed, UsbLowSpeed, UsbSuperSpeed, GUID_DEVINTERFACE_USB_HUB,
+    IOCTL_USB_GET_DESCRIPTOR_FROM_NODE_CONNECTION, IOCTL_USB_GET_NODE_CONNECTION_INFORMATION_EX,
+    USB_DESCRIPTOR_REQUEST, USB_DESCRIPTOR_REQUEST_0, USB_NODE_CONNECTION_INFORMATION_EX,
+};
+use windows_sys::Win32::Foundation::{GENERIC_WRITE, INVALID_HANDLE_VALUE, TRUE};
+use windows_sys::Win32::Storage::FileSystem::{CreateFileW, FILE_SHARE_WRITE, OPEN_EXISTING};
+use windows_sys::Win32::System::IO::DeviceIoControl;
+
+const USB_REQUEST_GET_DESCRIPTOR: u8 = 0x06;
+const USB_CONFIGURATION_DESCRIPTOR_TYPE: u8 = 0x02;
+const USB_INTERFACE_DESCRIPTOR_TYPE: u8 = 0x04;
+const USB_ENDPOINT_DESCRIPTOR_TYPE: u8 = 0x05;
+/// Large enough for the configuration descriptor of any HID device
+const MAX_CONFIGURATION_DESCRIPTOR_LEN: u16 = 4096;
+
+/// Get the polling interval of the interrupt IN endpoint of a HID interface of a USB device.
+///
+/// Returns `None` if the interface has no such endpoint.
+pub fn get_polling_interval(
+    usb_device: DevNode,
+    interface_number: u8,
+) -> WinResult<Option<Duration>> {
+    let port: u32 = usb_device.get_property(DEVPKEY_Device_Address)?;
+    let hub_id: U16String = usb_device
+        .parent()?
+        .get_property(DEVPKEY_Device_InstanceId)?;
+    let hub_interfaces =
+        Interface::get_device_interface_list(GUID_DEVINTERFACE_USB_HUB, Some(&*hub_id))?;
+    let hub_path = hub_interfaces
+        .iter()
+        .next()
+        .filter(|path| !path.as_slice().is_empty())
+        .ok_or(WinError::InvalidDeviceNode)?;
+    let hub = open_hub(hub_path)?;
+
+    let speed = get_speed(&hub, port)?;
+    let configuration = get_configuration_descriptor(&hub, port)?;
+    Ok(find_interrupt_in_interval(&configuration, interface_number)
+        .and_then(|interval| interval_duration(interval, speed)))
+}
+
+fn open_hub(path: &U16Str) -> WinResult<Handle> {
+    let handle = unsafe {
+        CreateFileW(
+            path.as_ptr(),
+            GENERIC_WRITE,
+            FILE_SHARE_WRITE,
+            null(),
+            OPEN_EXISTING,
+            0,
+            0,
+        )
+    };
+    ensure!(handle != INVALID_HANDLE_VALUE, Err(WinError::last()));
+    Ok(Handle::from_raw(handle))
+}
+
+/// Get the speed of the device connected to `port` of the hub
+fn get_speed(hub: &Handle, port: u32) -> WinResult<u8> {
+    let mut info: USB_NODE_CONNECTION_INFORMATION_EX = unsafe { zeroed() };
+    info.ConnectionIndex = port;
+    let info_ptr = &mut info as *mut USB_NODE_CONNECTION_INFORMATION_EX;
+    let size = size_of::<USB_NODE_CONNECTION_INFORMATION_EX>() as u32;
+    let mut bytes_returned = 0;
+    let res = unsafe {
+        DeviceIoControl(
+            hub.as_raw(),
+            IOCTL_USB_GET_NODE_CONNECTION_INFORMATION_EX,
+            info_ptr as _,
+            size,
+            info_ptr as _,
+            size,
+            &mut bytes_returned,
+            null_mut(),
+        )
+    };
+    ensure!(res == TRUE, Err(WinError::last()));
+    Ok(info.Speed)
+}
+
+/// Get the first configuration descriptor of the device connected to `port` of the hub
+fn get_configuration_descriptor(hub: &Handle, port: u32) -> WinResult<Vec<u8>> {
+    // The descriptor is returned in the buffer following the request
+    let request_len = size_of::<USB_DESCRIPTOR_REQUEST>() - 1;
+    let request = USB_DESCRIPTOR_REQUEST {
+        ConnectionIndex: port,
+        SetupPacket: USB_DESCRIPTOR_REQUEST_0 {
+            bmRequest: 0x80,
+            bRequest: USB_REQUEST_GET_DESCRIPTOR,
+            wValue: (USB_CONFIGURATION_DESCRIPTOR_TYPE as u16) << 8,
+            wIndex: 0,
+            wLength: MAX_CONFIGURATION_DESCRIPTOR_LEN,
+        },
+        Data: [0],
+    };
+    let mut buf = vec![0u8; request_len + MAX_CONFIGURATION_DESCRIPTOR_LEN as usize];
+    unsafe {
+        copy_nonoverlapping(
+            &request as *const USB_DESCRIPTOR_REQUEST as *const u8,
+            buf.as_mut_ptr(),
+            request_len,
+        )
+    };
+
+    let mut bytes_returned = 0;
+    let res = unsafe {
+        DeviceIoControl(
+            hub.as_raw(),
+            IOCTL_USB_GET_DESCRIPTOR_FROM_NODE_CONNECTION,
+            buf.as_ptr() as _,
+            buf.len() as u32,
+            buf.as_mut_ptr() as _,
+            buf.len() as u32,
+            &mut bytes_returned,
+            null_mut(),
+        )
+    };
+    ensure!(res == TRUE, Err(WinError::last()));
+    buf.truncate(bytes_returned as usize);
+    Ok(buf.split_off(request_len.min(buf.len())))
+}
+
+/// Find the `bInterval` of the interrupt IN endpoint of an interface in a configuration descriptor
+fn find_interrupt_in_interval(configuration: &[u8], interface_number: u8) -> Option<u8> {
+    let mut in_interface = false;
+    let mut rest = configuration;
+    while let [len, kind, ..] = *rest {
+        let len = len as usize;
+        if len < 2 || len > rest.len() {
+            return None;
+        }
+        let descriptor = &rest[..len];
+        match kind {
+            // Only the default alternate setting is used by the HID driver
+            USB_INTERFACE_DESCRIPTOR_TYPE if len >= 9 => {
+                in_interface = descriptor[2] == interface_number && descriptor[3] == 0;
+            }
+            USB_ENDPOINT_DESCRIPTOR_TYPE if in_interface && len >= 7 => {
+                let (address, attributes, interval) = (descriptor[2], descriptor[3], descriptor[6]);
+                if address & 0x80 != 0 && attributes & 0x03 == 0x03 {
+                    return Some(interval);
+                }
+            }
+            _ => {}
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+/// Convert `bInterval` into a duration, which depends on the speed of the device.
+///
+/// Low and full speed devices count in frames of 1ms, faster devices in powers of two of
+/// microframes of 125µs.
+fn interval_duration(interval: u8, speed: u8) -> Option<Duration> {
+    match i32::from(speed) {
+        UsbLowSpeed | UsbFullSpeed => {
+            (interval > 0).then(|| Duration::from_millis(interval.into()))
+        }
+        UsbHighSpeed | UsbSuperSpeed => (1..=16)
+            .contains(&interval)
+            .then(|| Duration::from_micros(125 << (interval - 1))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Configuration descriptor of a mouse with a boot mouse interface polled every 1ms and a
+    /// vendor interface with an interrupt OUT and IN endpoint polled every 8ms.
+    const CONFIGURATION: &[u8] = &[
+        0x09, 0x02, 0x42, 0x00, 0x02, 0x01, 0x00, 0xa0, 0x32, // Configuration
+        0x09, 0x04, 0x00, 0x00, 0x01, 0x03, 0x01, 0x02, 0x00, // Interface 0
+        0x09, 0x21, 0x11, 0x01, 0x00, 0x01, 0x22, 0x4d, 0x00, // HID
+        0x07, 0x05, 0x81, 0x03, 0x08, 0x00, 0x01, // Endpoint 0x81 IN, interval 1
+        0x09, 0x04, 0x01, 0x00, 0x02, 0x03, 0x00, 0x00, 0x00, // Interface 1
+        0x09, 0x21, 0x11, 0x01, 0x00, 0x01, 0x22, 0x1f, 0x00, // HID
+        0x07, 0x05, 0x02, 0x03, 0x40, 0x00, 0x08, // Endpoint 0x02 OUT, interval 8
+        0x07, 0x05, 0x82, 0x03, 0x40, 0x00, 0x08, // Endpoint 0x82 IN, interval 8
+    ];
+
+    #[test]
+    fn test_find_interrupt_in_interval() {
+        assert_eq!(find_interrupt_in_interval(CONFIGURATION, 0), Some(1));
+        assert_eq!(find_interrupt_in_interval(CONFIGURATION, 1), Some(8));
+        assert_eq!(find_interrupt_in_interval(CONFIGURATION, 2), None);
+        assert_eq!(find_interrupt_in_interval(&CONFIGURATION[..30], 0), None);
+        assert_eq!(find_interrupt_in_interval(&[0x00, 0x04], 0), None);
+    }
+
+    #[test]
+    fn test_interval_duration() {
+        let full_speed = UsbFullSpeed as u8;
+        let high_speed = UsbHighSpeed as u8;
+        assert_eq!(
+            interval_duration(8, full_speed),
+            Some(Duration::from_millis(8))
+        );
+        assert_eq!(interval_duration(0, full_speed), None);
+        assert_eq!(
+            interval_duration(1, high_speed),
+            Some(Duration::from_micros(125))
+        );
+        assert_eq!(
+            interval_duration(4, high_speed),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(interval_duration(17, high_speed), None);
+    }
+}
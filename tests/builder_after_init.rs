@@ -0,0 +1,11 @@
+//! Disabling device discovery after the hidapi C library was initialized with it. This changes
+//! the global state of the library, so it runs in its own test process.
+
+use hidapi::HidApi;
+
+#[test]
+#[should_panic(expected = "Cannot disable device discovery after HidApi has been initialized")]
+fn test_build_without_device_discovery_after_init() {
+    let _api = HidApi::new().unwrap();
+    let _ = HidApi::builder().device_discovery(false).build();
+}
@@ -0,0 +1,14 @@
+//! Building a `HidApi` with device discovery disabled. This changes the global state of the
+//! hidapi C library, so it runs in its own test process.
+
+use hidapi::HidApi;
+
+#[test]
+fn test_build_without_device_discovery() {
+    let api = HidApi::builder().device_discovery(false).build().unwrap();
+
+    // The context was initialized without device discovery, so disabling it again is fine
+    HidApi::disable_device_discovery();
+    let second = HidApi::builder().device_discovery(false).build().unwrap();
+    drop((api, second));
+}